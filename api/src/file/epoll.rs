@@ -0,0 +1,304 @@
+use core::{any::Any, ffi::c_int, ptr};
+
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use linux_raw_sys::general::{
+    EPOLLERR, EPOLLET, EPOLLEXCLUSIVE, EPOLLHUP, EPOLLIN, EPOLLONESHOT, EPOLLOUT, EPOLLWAKEUP,
+    epoll_event,
+};
+
+use super::{Directory, File, FileLike, Kstat, PollWaiter, poll_events};
+
+/// Maximum depth of nested epoll instances, as on Linux.
+const EPOLL_MAX_NESTS: usize = 4;
+/// Events that are always reported, whether requested or not.
+const EPOLL_ALWAYS: u32 = EPOLLERR | EPOLLHUP;
+/// Flags allowed together with `EPOLLEXCLUSIVE`.
+const EPOLL_EXCLUSIVE_OK: u32 =
+    EPOLLIN | EPOLLOUT | EPOLLERR | EPOLLHUP | EPOLLWAKEUP | EPOLLET | EPOLLEXCLUSIVE;
+
+/// A file registered with an epoll instance.
+struct EpollEntry {
+    fd: c_int,
+    /// The file is not kept alive by the entry, the entry goes away once the
+    /// last file descriptor referring to the file is closed.
+    file: Weak<dyn FileLike>,
+    events: u32,
+    data: u64,
+    /// The event sequence of the file when the entry was last reported, for
+    /// edge-triggered entries.
+    seq: Option<u64>,
+    /// The events ready at the last check, for edge-triggered entries on
+    /// files that cannot notify waiters and have no event sequence.
+    last_ready: u32,
+    /// The entry has fired in one-shot mode and waits for `EPOLL_CTL_MOD`.
+    disabled: bool,
+}
+
+impl EpollEntry {
+    fn is(&self, fd: c_int, file: &Arc<dyn FileLike>) -> bool {
+        self.fd == fd && ptr::addr_eq(self.file.as_ptr(), Arc::as_ptr(file))
+    }
+
+    /// Get the requested events the file is ready for.
+    fn ready(&self, file: Arc<dyn FileLike>) -> u32 {
+        if self.disabled {
+            return 0;
+        }
+        // A file that fails to poll is reported as being in error.
+        let events = poll_events(file).unwrap_or(EPOLLERR);
+        events & (self.events | EPOLL_ALWAYS)
+    }
+
+    /// Check the file, returning the requested events it is ready for and
+    /// its event sequence.
+    fn check(&self, file: Arc<dyn FileLike>) -> (u32, Option<u64>) {
+        // Taken before polling, so that an event arriving in between is
+        // reported again the next time.
+        let seq = file.event_seq(self.events | EPOLL_ALWAYS);
+        (self.ready(file), seq)
+    }
+
+    /// Whether the entry is to be reported for the `ready` events.
+    ///
+    /// Edge-triggered entries are only reported if the file has had new
+    /// events since the entry was last reported.
+    fn fires(&self, ready: u32, seq: Option<u64>) -> bool {
+        if ready == 0 {
+            return false;
+        }
+        if self.events & EPOLLET == 0 {
+            return true;
+        }
+        match seq {
+            Some(seq) => self.seq != Some(seq),
+            None => ready & !self.last_ready != 0,
+        }
+    }
+}
+
+/// An epoll instance, holding an interest list of files.
+///
+/// Readiness is found by polling the files. Edge-triggered entries
+/// (`EPOLLET`) fire when the file is ready and has had new events since the
+/// entry was last reported, or for files that cannot tell about new events,
+/// when an event becomes ready since the last check. `EPOLLEXCLUSIVE` is
+/// validated, all the instances waiting on a file see its events, which
+/// Linux allows.
+pub struct Epoll {
+    entries: Mutex<Vec<EpollEntry>>,
+}
+
+impl Epoll {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Whether `epoll` is reachable from this instance through nested
+    /// instances, or the nesting is deeper than allowed.
+    fn reaches(&self, epoll: &Epoll, depth: usize) -> bool {
+        if ptr::eq(self, epoll) || depth > EPOLL_MAX_NESTS {
+            return true;
+        }
+        let nested: Vec<_> = self
+            .entries
+            .lock()
+            .iter()
+            .filter_map(|entry| entry.file.upgrade())
+            .filter_map(|file| file.into_any().downcast::<Epoll>().ok())
+            .collect();
+        nested.iter().any(|nested| nested.reaches(epoll, depth + 1))
+    }
+
+    /// Add `file` referred to by `fd` to the interest list, as
+    /// `EPOLL_CTL_ADD` does.
+    pub fn add(&self, fd: c_int, file: Arc<dyn FileLike>, events: u32, data: u64) -> LinuxResult {
+        if events & EPOLLEXCLUSIVE != 0 && events & !EPOLL_EXCLUSIVE_OK != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let any = file.clone().into_any();
+        if any.is::<File>() || any.is::<Directory>() {
+            // Regular files and directories are always ready.
+            return Err(LinuxError::EPERM);
+        }
+        if let Ok(epoll) = any.downcast::<Epoll>() {
+            if ptr::eq(self, &*epoll) {
+                return Err(LinuxError::EINVAL);
+            }
+            if events & EPOLLEXCLUSIVE != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            if epoll.reaches(self, 1) {
+                return Err(LinuxError::ELOOP);
+            }
+        }
+
+        let mut entries = self.entries.lock();
+        entries.retain(|entry| entry.file.strong_count() > 0);
+        if entries.iter().any(|entry| entry.is(fd, &file)) {
+            return Err(LinuxError::EEXIST);
+        }
+        entries.push(EpollEntry {
+            fd,
+            file: Arc::downgrade(&file),
+            events,
+            data,
+            seq: None,
+            last_ready: 0,
+            disabled: false,
+        });
+        Ok(())
+    }
+
+    /// Change the events and data of an entry, as `EPOLL_CTL_MOD` does.
+    pub fn modify(
+        &self,
+        fd: c_int,
+        file: &Arc<dyn FileLike>,
+        events: u32,
+        data: u64,
+    ) -> LinuxResult {
+        let mut entries = self.entries.lock();
+        let entry = entries
+            .iter_mut()
+            .find(|entry| entry.is(fd, file))
+            .ok_or(LinuxError::ENOENT)?;
+        if (events | entry.events) & EPOLLEXCLUSIVE != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        entry.events = events;
+        entry.data = data;
+        entry.seq = None;
+        entry.last_ready = 0;
+        entry.disabled = false;
+        Ok(())
+    }
+
+    /// Remove an entry, as `EPOLL_CTL_DEL` does.
+    pub fn remove(&self, fd: c_int, file: &Arc<dyn FileLike>) -> LinuxResult {
+        let mut entries = self.entries.lock();
+        let pos = entries
+            .iter()
+            .position(|entry| entry.is(fd, file))
+            .ok_or(LinuxError::ENOENT)?;
+        entries.remove(pos);
+        Ok(())
+    }
+
+    /// Collect the ready events into `events`, returning their number.
+    ///
+    /// Edge-triggered and one-shot entries are consumed, and the entries are
+    /// rotated so that all of them get reported when `events` is short.
+    pub fn collect(&self, events: &mut [epoll_event]) -> usize {
+        let mut entries = self.entries.lock();
+        entries.retain(|entry| entry.file.strong_count() > 0);
+
+        let mut count = 0;
+        let mut scanned = 0;
+        for entry in entries.iter_mut() {
+            if count == events.len() {
+                break;
+            }
+            scanned += 1;
+            let Some(file) = entry.file.upgrade() else {
+                continue;
+            };
+            let (ready, seq) = entry.check(file);
+            let fires = entry.fires(ready, seq);
+            entry.last_ready = ready;
+            if !fires {
+                continue;
+            }
+            entry.seq = seq;
+            events[count] = epoll_event {
+                events: ready,
+                data: entry.data,
+            };
+            count += 1;
+            if entry.events & EPOLLONESHOT != 0 {
+                entry.disabled = true;
+            }
+        }
+        entries.rotate_left(scanned);
+        count
+    }
+
+    /// Whether some events are ready, without consuming them.
+    fn has_ready(&self) -> bool {
+        let entries = self.entries.lock();
+        entries.iter().any(|entry| {
+            let Some(file) = entry.file.upgrade() else {
+                return false;
+            };
+            let (ready, seq) = entry.check(file);
+            entry.fires(ready, seq)
+        })
+    }
+}
+
+impl FileLike for Epoll {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: 0o600u32, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: self.has_ready(),
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, _events: u32) -> bool {
+        let files: Vec<_> = self
+            .entries
+            .lock()
+            .iter()
+            .filter(|entry| !entry.disabled)
+            .filter_map(|entry| Some((entry.file.upgrade()?, entry.events | EPOLL_ALWAYS)))
+            .collect();
+        // Waiters for nested instances are woken up by the nested files.
+        files.iter().fold(true, |notify, (file, events)| {
+            file.register(waiter, *events) && notify
+        })
+    }
+
+    fn event_seq(&self, _events: u32) -> Option<u64> {
+        let files: Vec<_> = self
+            .entries
+            .lock()
+            .iter()
+            .filter(|entry| !entry.disabled)
+            .filter_map(|entry| Some((entry.file.upgrade()?, entry.events | EPOLL_ALWAYS)))
+            .collect();
+        files.iter().try_fold(0u64, |seq, (file, events)| {
+            Some(seq.wrapping_add(file.event_seq(*events)?))
+        })
+    }
+}
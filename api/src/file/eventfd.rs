@@ -0,0 +1,157 @@
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alloc::sync::Arc;
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use linux_raw_sys::general::{POLLIN, POLLOUT};
+
+use super::{FileLike, Kstat, PollSet, PollWaiter, wait_event};
+
+/// The largest value of the counter, writes that would exceed it block.
+const EVENTFD_MAX: u64 = u64::MAX - 1;
+
+/// An event counter created by `eventfd2`.
+///
+/// Reading returns and resets the counter, or decrements it by one in
+/// semaphore mode, and blocks while it is zero. Writing adds to the counter.
+pub struct EventFd {
+    count: Mutex<u64>,
+    semaphore: bool,
+    nonblocking: AtomicBool,
+    /// Waiters for the counter to become nonzero.
+    readers: PollSet,
+    /// Waiters for room in the counter.
+    writers: PollSet,
+}
+
+impl EventFd {
+    pub fn new(initval: u64, semaphore: bool) -> Self {
+        Self {
+            count: Mutex::new(initval),
+            semaphore,
+            nonblocking: AtomicBool::new(false),
+            readers: PollSet::new(),
+            writers: PollSet::new(),
+        }
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    /// Add `n` to the counter on behalf of the kernel, which saturates the
+    /// counter instead of blocking.
+    pub fn signal(&self, n: u64) {
+        {
+            let mut count = self.count.lock();
+            *count = count.saturating_add(n).min(EVENTFD_MAX);
+        }
+        if n > 0 {
+            self.readers.wake();
+        }
+    }
+}
+
+impl FileLike for EventFd {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let Some(buf) = buf.get_mut(..size_of::<u64>()) else {
+            return Err(LinuxError::EINVAL);
+        };
+        let nonblocking = self.is_nonblocking();
+        let value = wait_event(None, true, |waiter| {
+            self.readers.register(waiter);
+            let mut count = self.count.lock();
+            if *count == 0 {
+                return if nonblocking {
+                    Err(LinuxError::EAGAIN)
+                } else {
+                    Ok(None)
+                };
+            }
+            let value = if self.semaphore { 1 } else { *count };
+            *count -= value;
+            Ok(Some(value))
+        })?
+        .unwrap_or_default();
+        self.writers.wake();
+        buf.copy_from_slice(&value.to_ne_bytes());
+        Ok(buf.len())
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        let Some(buf) = buf.get(..size_of::<u64>()) else {
+            return Err(LinuxError::EINVAL);
+        };
+        let value = u64::from_ne_bytes(buf.try_into().unwrap());
+        if value == u64::MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        let nonblocking = self.is_nonblocking();
+        wait_event(None, true, |waiter| {
+            self.writers.register(waiter);
+            let mut count = self.count.lock();
+            if EVENTFD_MAX - *count < value {
+                return if nonblocking {
+                    Err(LinuxError::EAGAIN)
+                } else {
+                    Ok(None)
+                };
+            }
+            *count += value;
+            Ok(Some(()))
+        })?;
+        if value > 0 {
+            self.readers.wake();
+        }
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: 0o600u32, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let count = *self.count.lock();
+        Ok(PollState {
+            readable: count > 0,
+            writable: count < EVENTFD_MAX,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, events: u32) -> bool {
+        if events & POLLIN != 0 {
+            self.readers.register(waiter);
+        }
+        if events & POLLOUT != 0 {
+            self.writers.register(waiter);
+        }
+        true
+    }
+
+    fn event_seq(&self, events: u32) -> Option<u64> {
+        let mut seq = 0u64;
+        if events & POLLIN != 0 {
+            seq = seq.wrapping_add(self.readers.seq());
+        }
+        if events & POLLOUT != 0 {
+            seq = seq.wrapping_add(self.writers.seq());
+        }
+        Some(seq)
+    }
+}
@@ -5,14 +5,16 @@ use axerrno::{LinuxError, LinuxResult};
 use axfs::fops::DirEntry;
 use axio::PollState;
 use axsync::{Mutex, MutexGuard};
-use linux_raw_sys::general::S_IFDIR;
+use linux_raw_sys::general::{IN_CLOSE_NOWRITE, IN_CLOSE_WRITE, IN_MODIFY, S_IFDIR, S_IFSOCK};
 
-use super::{FileLike, Kstat, get_file_like};
+use super::{FileLike, Kstat, get_file_like, notify_fs_event, unix::is_unix_socket_node};
 
 /// File wrapper for `axfs::fops::File`.
 pub struct File {
     inner: Mutex<axfs::fops::File>,
     path: String,
+    /// The inotify event reported when the file is closed.
+    close_event: Option<u32>,
 }
 
 impl File {
@@ -20,9 +22,21 @@ impl File {
         Self {
             inner: Mutex::new(inner),
             path,
+            close_event: None,
         }
     }
 
+    /// Report `IN_CLOSE_WRITE` or `IN_CLOSE_NOWRITE` to inotify watches when
+    /// the file is closed, depending on whether it was opened for writing.
+    pub fn notify_close(mut self, writable: bool) -> Self {
+        self.close_event = Some(if writable {
+            IN_CLOSE_WRITE
+        } else {
+            IN_CLOSE_NOWRITE
+        });
+        self
+    }
+
     /// Get the path of the file.
     pub fn path(&self) -> &str {
         &self.path
@@ -40,16 +54,26 @@ impl FileLike for File {
     }
 
     fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        Ok(self.inner().write(buf)?)
+        let written = self.inner().write(buf)?;
+        if written > 0 {
+            notify_fs_event(&self.path, IN_MODIFY, false);
+        }
+        Ok(written)
     }
 
     fn stat(&self) -> LinuxResult<Kstat> {
         let metadata = self.inner().get_attr()?;
         let ty = metadata.file_type() as u8;
         let perm = metadata.perm().bits() as u32;
+        // Socket files are regular files in axfs, tell them apart here.
+        let mode = if is_unix_socket_node(&self.path) {
+            S_IFSOCK | perm
+        } else {
+            ((ty as u32) << 12) | perm
+        };
 
         Ok(Kstat {
-            mode: ((ty as u32) << 12) | perm,
+            mode,
             size: metadata.size(),
             blocks: metadata.blocks(),
             blksize: 512,
@@ -73,6 +97,14 @@ impl FileLike for File {
     }
 }
 
+impl Drop for File {
+    fn drop(&mut self) {
+        if let Some(event) = self.close_event {
+            notify_fs_event(&self.path, event, false);
+        }
+    }
+}
+
 /// Directory wrapper for `axfs::fops::Directory`.
 pub struct Directory {
     inner: Mutex<axfs::fops::Directory>,
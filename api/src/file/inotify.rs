@@ -0,0 +1,360 @@
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use linux_raw_sys::general::{
+    IN_ALL_EVENTS, IN_CREATE, IN_DELETE, IN_DELETE_SELF, IN_EXCL_UNLINK, IN_IGNORED, IN_ISDIR,
+    IN_MASK_ADD, IN_MASK_CREATE, IN_MOVE_SELF, IN_MOVED_FROM, IN_MOVED_TO, IN_ONESHOT,
+    IN_Q_OVERFLOW,
+};
+
+use super::{FileLike, Kstat, PollSet, PollWaiter, live_entries, wait_event};
+
+/// Maximum number of queued events of an instance, the default of
+/// `fs.inotify.max_queued_events` on Linux.
+const MAX_QUEUED_EVENTS: usize = 16384;
+/// Size of `struct inotify_event` without the name.
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// The live inotify instances, which the file system events are reported to.
+static INSTANCES: Mutex<Vec<Weak<Inotify>>> = Mutex::new(Vec::new());
+
+/// Get the path without trailing slashes, as watches are keyed by.
+fn normalize(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+struct InotifyEvent {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    name: String,
+}
+
+impl InotifyEvent {
+    /// Get the length of the record, with the name null-terminated and padded
+    /// to a multiple of the header size as on Linux.
+    fn name_len(&self) -> usize {
+        if self.name.is_empty() {
+            0
+        } else {
+            (self.name.len() + 1).next_multiple_of(EVENT_HEADER_SIZE)
+        }
+    }
+
+    fn write_to(&self, buf: &mut [u8]) {
+        let name_len = self.name_len();
+        buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.mask.to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(name_len as u32).to_ne_bytes());
+        let name = &mut buf[EVENT_HEADER_SIZE..EVENT_HEADER_SIZE + name_len];
+        name.fill(0);
+        name[..self.name.len()].copy_from_slice(self.name.as_bytes());
+    }
+}
+
+struct Watch {
+    path: String,
+    mask: u32,
+}
+
+struct InotifyState {
+    watches: BTreeMap<i32, Watch>,
+    next_wd: i32,
+    events: VecDeque<InotifyEvent>,
+}
+
+impl InotifyState {
+    fn push(&mut self, wd: i32, mask: u32, cookie: u32, name: &str) {
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            if self
+                .events
+                .back()
+                .is_none_or(|last| last.mask != IN_Q_OVERFLOW)
+            {
+                self.events.push_back(InotifyEvent {
+                    wd: -1,
+                    mask: IN_Q_OVERFLOW,
+                    cookie: 0,
+                    name: String::new(),
+                });
+            }
+            return;
+        }
+        // Like on Linux, an event identical to the last queued one is merged
+        // into it.
+        if self.events.back().is_some_and(|last| {
+            (last.wd, last.mask, last.cookie, last.name.as_str()) == (wd, mask, cookie, name)
+        }) {
+            return;
+        }
+        self.events.push_back(InotifyEvent {
+            wd,
+            mask,
+            cookie,
+            name: name.to_string(),
+        });
+    }
+
+    /// Remove a watch, queueing `IN_IGNORED` for it.
+    fn remove(&mut self, wd: i32) -> bool {
+        let removed = self.watches.remove(&wd).is_some();
+        if removed {
+            self.push(wd, IN_IGNORED, 0, "");
+        }
+        removed
+    }
+}
+
+/// An inotify instance, created by `inotify_init1`.
+///
+/// Events are reported by the system calls changing files through
+/// [`notify_fs_event`] and [`notify_fs_move`], for the watched paths and the
+/// entries of the watched directories.
+pub struct Inotify {
+    state: Mutex<InotifyState>,
+    nonblocking: AtomicBool,
+    /// Waiters for events.
+    readers: PollSet,
+}
+
+impl Inotify {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> Arc<Self> {
+        let inotify = Arc::new(Self {
+            state: Mutex::new(InotifyState {
+                watches: BTreeMap::new(),
+                next_wd: 1,
+                events: VecDeque::new(),
+            }),
+            nonblocking: AtomicBool::new(false),
+            readers: PollSet::new(),
+        });
+        let mut instances = INSTANCES.lock();
+        instances.retain(|instance| instance.strong_count() > 0);
+        instances.push(Arc::downgrade(&inotify));
+        inotify
+    }
+
+    /// Watch `path` for the events in `mask`, or change the events of the
+    /// watch of `path`, returning the watch descriptor.
+    pub fn add_watch(&self, path: &str, mask: u32) -> LinuxResult<i32> {
+        let path = normalize(path);
+        let events = mask & (IN_ALL_EVENTS | IN_ONESHOT | IN_EXCL_UNLINK);
+        let mut state = self.state.lock();
+        if let Some((wd, watch)) = state
+            .watches
+            .iter_mut()
+            .find(|(_, watch)| watch.path == path)
+        {
+            if mask & IN_MASK_CREATE != 0 {
+                return Err(LinuxError::EEXIST);
+            }
+            if mask & IN_MASK_ADD != 0 {
+                watch.mask |= events;
+            } else {
+                watch.mask = events;
+            }
+            return Ok(*wd);
+        }
+        let wd = state.next_wd;
+        state.next_wd += 1;
+        state.watches.insert(
+            wd,
+            Watch {
+                path: path.to_string(),
+                mask: events,
+            },
+        );
+        Ok(wd)
+    }
+
+    /// Remove the watch `wd`.
+    pub fn rm_watch(&self, wd: i32) -> LinuxResult {
+        if !self.state.lock().remove(wd) {
+            return Err(LinuxError::EINVAL);
+        }
+        self.readers.wake();
+        Ok(())
+    }
+
+    fn handle_event(&self, path: &str, mask: u32, cookie: u32, is_dir: bool) {
+        let parent_and_name = path
+            .rsplit_once('/')
+            .filter(|(_, name)| !name.is_empty())
+            .map(|(parent, name)| (normalize(parent), name));
+        // Events about the watched file itself.
+        let self_mask = match mask {
+            IN_DELETE => IN_DELETE_SELF,
+            IN_MOVED_FROM => IN_MOVE_SELF,
+            IN_CREATE | IN_MOVED_TO => 0,
+            _ if is_dir => mask | IN_ISDIR,
+            _ => mask,
+        };
+        let dir_flag = if is_dir { IN_ISDIR } else { 0 };
+
+        let mut state = self.state.lock();
+        let mut events = Vec::new();
+        for (wd, watch) in &state.watches {
+            if let Some((parent, name)) = parent_and_name {
+                if watch.path == parent && watch.mask & mask != 0 {
+                    events.push((*wd, mask | dir_flag, name, watch.mask));
+                }
+            }
+            if watch.path == path && watch.mask & self_mask & IN_ALL_EVENTS != 0 {
+                events.push((*wd, self_mask, "", watch.mask));
+            }
+        }
+        if events.is_empty() && self_mask != IN_DELETE_SELF {
+            return;
+        }
+        for (wd, mask, name, watch_mask) in &events {
+            state.push(*wd, *mask, cookie, name);
+            if watch_mask & IN_ONESHOT != 0 {
+                state.remove(*wd);
+            }
+        }
+        if self_mask == IN_DELETE_SELF {
+            // The watches of a deleted file go away.
+            let wds: Vec<_> = state
+                .watches
+                .iter()
+                .filter(|(_, watch)| watch.path == path)
+                .map(|(wd, _)| *wd)
+                .collect();
+            for wd in wds {
+                state.remove(wd);
+            }
+        }
+        drop(state);
+        self.readers.wake();
+    }
+
+    /// Update the paths of the watches after `old` has been renamed to `new`.
+    fn handle_move(&self, old: &str, new: &str) {
+        for watch in self.state.lock().watches.values_mut() {
+            if watch.path == old {
+                watch.path = new.to_string();
+            } else if let Some(rest) = watch.path.strip_prefix(old) {
+                if rest.starts_with('/') {
+                    watch.path = [new, rest].concat();
+                }
+            }
+        }
+    }
+}
+
+fn instances() -> Vec<Arc<Inotify>> {
+    live_entries(&INSTANCES)
+}
+
+/// Report the event `mask` on the file at `path`, to the watches of the file
+/// and of the directory containing it.
+///
+/// `IN_DELETE` and `IN_MOVED_FROM` are reported as `IN_DELETE_SELF` and
+/// `IN_MOVE_SELF` to the watches of the file itself.
+pub fn notify_fs_event(path: &str, mask: u32, is_dir: bool) {
+    for inotify in instances() {
+        inotify.handle_event(normalize(path), mask, 0, is_dir);
+    }
+}
+
+/// Report the rename of `old` to `new` as a pair of `IN_MOVED_FROM` and
+/// `IN_MOVED_TO` events sharing a cookie.
+pub fn notify_fs_move(old: &str, new: &str, is_dir: bool) {
+    static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+    let instances = instances();
+    if instances.is_empty() {
+        return;
+    }
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+    let (old, new) = (normalize(old), normalize(new));
+    for inotify in instances {
+        inotify.handle_event(old, IN_MOVED_FROM, cookie, is_dir);
+        inotify.handle_event(new, IN_MOVED_TO, cookie, is_dir);
+        inotify.handle_move(old, new);
+    }
+}
+
+impl FileLike for Inotify {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let nonblocking = self.nonblocking.load(Ordering::Acquire);
+        let read = wait_event(None, true, |waiter| {
+            self.readers.register(waiter);
+            let mut state = self.state.lock();
+            let Some(first) = state.events.front() else {
+                return if nonblocking {
+                    Err(LinuxError::EAGAIN)
+                } else {
+                    Ok(None)
+                };
+            };
+            if EVENT_HEADER_SIZE + first.name_len() > buf.len() {
+                return Err(LinuxError::EINVAL);
+            }
+            let mut read = 0;
+            while let Some(event) = state.events.front() {
+                let len = EVENT_HEADER_SIZE + event.name_len();
+                if read + len > buf.len() {
+                    break;
+                }
+                event.write_to(&mut buf[read..read + len]);
+                read += len;
+                state.events.pop_front();
+            }
+            Ok(Some(read))
+        })?;
+        Ok(read.unwrap_or_default())
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: 0o600u32, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: !self.state.lock().events.is_empty(),
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, _events: u32) -> bool {
+        self.readers.register(waiter);
+        true
+    }
+
+    fn event_seq(&self, _events: u32) -> Option<u64> {
+        Some(self.readers.seq())
+    }
+}
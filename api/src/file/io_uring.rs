@@ -0,0 +1,530 @@
+use core::{
+    any::Any,
+    mem::{self, offset_of},
+    ops::Deref,
+    ptr,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+};
+
+use alloc::{
+    collections::vec_deque::VecDeque,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axmm::AddrSpace;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::io_uring::{
+    IORING_OFF_CQ_RING, IORING_OFF_SQ_RING, IORING_OFF_SQES, io_cqring_offsets, io_sqring_offsets,
+    io_uring_cqe, io_uring_sqe,
+};
+use memory_addr::VirtAddr;
+
+use super::{Canceler, EventFd, FileLike, Kstat, PollSet, PollWaiter};
+use crate::ptr::{UserConstPtr, UserPtr};
+
+/// The header of the submission ring, followed by the array of indices of the
+/// submitted entries.
+#[repr(C)]
+struct SqRing {
+    head: AtomicU32,
+    tail: AtomicU32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: AtomicU32,
+    dropped: AtomicU32,
+}
+
+/// The header of the completion ring, followed by the completion entries.
+#[repr(C)]
+struct CqRing {
+    head: AtomicU32,
+    tail: AtomicU32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: AtomicU32,
+    flags: AtomicU32,
+}
+
+/// Maximum number of workers executing the requests of an instance at once.
+/// Further chains of requests wait for a worker to become free.
+pub const MAX_WORKERS: usize = 16;
+
+const SQ_ARRAY_OFFSET: usize = size_of::<SqRing>();
+const CQES_OFFSET: usize = size_of::<CqRing>().next_multiple_of(align_of::<io_uring_cqe>());
+
+/// A submission queue entry, copied out of the ring when submitted so that
+/// user space may reuse the entry right away.
+#[derive(Debug, Clone, Copy)]
+pub struct Submission {
+    pub opcode: u8,
+    pub flags: u8,
+    pub fd: i32,
+    pub off: u64,
+    pub addr: u64,
+    pub len: u32,
+    /// The opcode specific flags, such as `msg_flags` or `timeout_flags`.
+    pub op_flags: u32,
+    pub user_data: u64,
+    pub buf_index: u16,
+}
+
+impl From<&io_uring_sqe> for Submission {
+    fn from(sqe: &io_uring_sqe) -> Self {
+        // SAFETY: the unions only hold plain integers.
+        unsafe {
+            Self {
+                opcode: sqe.opcode,
+                flags: sqe.flags,
+                fd: sqe.fd,
+                off: sqe.__bindgen_anon_1.off,
+                addr: sqe.__bindgen_anon_2.addr,
+                len: sqe.len,
+                op_flags: sqe.__bindgen_anon_3.rw_flags as u32,
+                user_data: sqe.user_data,
+                buf_index: sqe.__bindgen_anon_4.buf_index,
+            }
+        }
+    }
+}
+
+/// The addresses the rings are mapped at in user space.
+#[derive(Default)]
+struct RingMaps {
+    sq: Option<VirtAddr>,
+    cq: Option<VirtAddr>,
+    sqes: Option<VirtAddr>,
+}
+
+/// Chains of linked requests waiting for a worker, with the number of
+/// completions posted when they were submitted.
+#[derive(Default)]
+struct WorkQueue {
+    chains: VecDeque<(Vec<Submission>, u64)>,
+    /// Number of workers taking chains from the queue.
+    workers: usize,
+}
+
+/// An io_uring instance, created by `io_uring_setup`.
+///
+/// The submission and completion rings are shared with user space through
+/// `mmap` of the instance at the `IORING_OFF_*` offsets, and live in the
+/// address space of the process which set up the instance. Submitted requests
+/// are executed by at most [`MAX_WORKERS`] kernel workers, which post their
+/// completions to the [`IoUringShared`] state they keep alive. Once the
+/// instance is closed, which its process does on exit, the pending requests
+/// are canceled.
+pub struct IoUring {
+    shared: Arc<IoUringShared>,
+}
+
+/// The state of an io_uring instance shared with its workers.
+pub struct IoUringShared {
+    sq_entries: u32,
+    cq_entries: u32,
+    /// The address space the rings are mapped in.
+    aspace: Weak<Mutex<AddrSpace>>,
+    maps: Mutex<RingMaps>,
+    /// The head of the submission ring, which only the kernel advances.
+    sq_head: Mutex<u32>,
+    /// The tail of the completion ring, which only the kernel advances.
+    cq_tail: Mutex<u32>,
+    /// Number of completions posted so far.
+    completions: AtomicU64,
+    /// Files registered with `IORING_REGISTER_FILES`.
+    files: Mutex<Option<Vec<Option<Arc<dyn FileLike>>>>>,
+    /// Buffers registered with `IORING_REGISTER_BUFFERS`, as address and
+    /// length.
+    buffers: Mutex<Option<Vec<(usize, usize)>>>,
+    /// The eventfd signaled on completions.
+    eventfd: Mutex<Option<Arc<EventFd>>>,
+    /// Waiters for completions.
+    completion_waiters: PollSet,
+    work: Mutex<WorkQueue>,
+    /// Cancels the waits of the workers once the instance is closed.
+    canceler: Arc<Canceler>,
+}
+
+impl IoUring {
+    /// Create an instance with `sq_entries` submission and `cq_entries`
+    /// completion entries, both powers of two.
+    pub fn new(sq_entries: u32, cq_entries: u32) -> Self {
+        let shared = IoUringShared {
+            sq_entries,
+            cq_entries,
+            aspace: Arc::downgrade(&current().task_ext().process_data().aspace),
+            maps: Mutex::new(RingMaps::default()),
+            sq_head: Mutex::new(0),
+            cq_tail: Mutex::new(0),
+            completions: AtomicU64::new(0),
+            files: Mutex::new(None),
+            buffers: Mutex::new(None),
+            eventfd: Mutex::new(None),
+            completion_waiters: PollSet::new(),
+            work: Mutex::new(WorkQueue::default()),
+            canceler: Canceler::new(),
+        };
+        Self {
+            shared: Arc::new(shared),
+        }
+    }
+
+    pub fn shared(&self) -> &Arc<IoUringShared> {
+        &self.shared
+    }
+
+    /// Get the offsets of the fields of the rings, as reported in
+    /// `io_uring_params`.
+    pub fn ring_offsets() -> (io_sqring_offsets, io_cqring_offsets) {
+        let sq_off = io_sqring_offsets {
+            head: offset_of!(SqRing, head) as _,
+            tail: offset_of!(SqRing, tail) as _,
+            ring_mask: offset_of!(SqRing, ring_mask) as _,
+            ring_entries: offset_of!(SqRing, ring_entries) as _,
+            flags: offset_of!(SqRing, flags) as _,
+            dropped: offset_of!(SqRing, dropped) as _,
+            array: SQ_ARRAY_OFFSET as _,
+            resv1: 0,
+            user_addr: 0,
+        };
+        let cq_off = io_cqring_offsets {
+            head: offset_of!(CqRing, head) as _,
+            tail: offset_of!(CqRing, tail) as _,
+            ring_mask: offset_of!(CqRing, ring_mask) as _,
+            ring_entries: offset_of!(CqRing, ring_entries) as _,
+            overflow: offset_of!(CqRing, overflow) as _,
+            cqes: CQES_OFFSET as _,
+            flags: offset_of!(CqRing, flags) as _,
+            resv1: 0,
+            user_addr: 0,
+        };
+        (sq_off, cq_off)
+    }
+}
+
+impl Deref for IoUring {
+    type Target = IoUringShared;
+
+    fn deref(&self) -> &IoUringShared {
+        &self.shared
+    }
+}
+
+impl Drop for IoUring {
+    fn drop(&mut self) {
+        self.shared.cancel();
+    }
+}
+
+impl IoUringShared {
+    /// Fail with `EFAULT` if the rings are not in the address space of the
+    /// current process.
+    fn check_owner(&self) -> LinuxResult {
+        let aspace = &current().task_ext().process_data().aspace;
+        if !ptr::eq(self.aspace.as_ptr(), Arc::as_ptr(aspace)) {
+            return Err(LinuxError::EFAULT);
+        }
+        Ok(())
+    }
+
+    /// Set up the ring at `offset` in the memory mapped at `start` with
+    /// `len` bytes in `aspace`, the address space of the current process.
+    pub fn mmap(
+        &self,
+        aspace: &AddrSpace,
+        offset: u64,
+        start: VirtAddr,
+        len: usize,
+    ) -> LinuxResult {
+        self.check_owner()?;
+        if offset > u32::MAX as u64 {
+            return Err(LinuxError::EINVAL);
+        }
+        let (size, header) = match offset as u32 {
+            IORING_OFF_SQ_RING => {
+                let head = *self.sq_head.lock();
+                let size = SQ_ARRAY_OFFSET + self.sq_entries as usize * size_of::<u32>();
+                (
+                    size,
+                    [head, head, self.sq_entries - 1, self.sq_entries, 0, 0],
+                )
+            }
+            IORING_OFF_CQ_RING => {
+                let tail = *self.cq_tail.lock();
+                let size = CQES_OFFSET + self.cq_entries as usize * size_of::<io_uring_cqe>();
+                (
+                    size,
+                    [tail, tail, self.cq_entries - 1, self.cq_entries, 0, 0],
+                )
+            }
+            IORING_OFF_SQES => (self.sq_entries as usize * size_of::<io_uring_sqe>(), [0; 6]),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if len < size {
+            return Err(LinuxError::EINVAL);
+        }
+        if offset as u32 != IORING_OFF_SQES {
+            let header: Vec<u8> = header.iter().flat_map(|v| v.to_ne_bytes()).collect();
+            aspace.write(start, &header)?;
+        }
+
+        let mut maps = self.maps.lock();
+        match offset as u32 {
+            IORING_OFF_SQ_RING => maps.sq = Some(start),
+            IORING_OFF_CQ_RING => maps.cq = Some(start),
+            _ => maps.sqes = Some(start),
+        }
+        Ok(())
+    }
+
+    fn sq_ring(&self) -> LinuxResult<(&'static SqRing, VirtAddr)> {
+        self.check_owner()?;
+        let start = self.maps.lock().sq.ok_or(LinuxError::EFAULT)?;
+        let ring = UserConstPtr::<SqRing>::from(start.as_usize()).get_as_ref()?;
+        Ok((ring, start))
+    }
+
+    fn cq_ring(&self) -> LinuxResult<(&'static CqRing, VirtAddr)> {
+        self.check_owner()?;
+        let start = self.maps.lock().cq.ok_or(LinuxError::EFAULT)?;
+        let ring = UserConstPtr::<CqRing>::from(start.as_usize()).get_as_ref()?;
+        Ok((ring, start))
+    }
+
+    /// Consume at most `max` entries from the submission ring.
+    ///
+    /// Entries with an invalid index are counted as dropped and skipped.
+    pub fn pop_submissions(&self, max: u32) -> LinuxResult<Vec<Submission>> {
+        let (ring, start) = self.sq_ring()?;
+        let sqes = self.maps.lock().sqes.ok_or(LinuxError::EFAULT)?;
+        let array = UserConstPtr::<u32>::from(start.as_usize() + SQ_ARRAY_OFFSET)
+            .get_as_slice(self.sq_entries as _)?;
+        let sqes = UserConstPtr::<io_uring_sqe>::from(sqes.as_usize())
+            .get_as_slice(self.sq_entries as _)?;
+
+        let mut head = self.sq_head.lock();
+        let tail = ring.tail.load(Ordering::Acquire);
+        let count = tail.wrapping_sub(*head).min(self.sq_entries).min(max);
+        let mut submissions = Vec::with_capacity(count as _);
+        for _ in 0..count {
+            let index = array[(*head & (self.sq_entries - 1)) as usize];
+            match sqes.get(index as usize) {
+                Some(sqe) => submissions.push(Submission::from(sqe)),
+                None => {
+                    ring.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            *head = head.wrapping_add(1);
+        }
+        ring.head.store(*head, Ordering::Release);
+        Ok(submissions)
+    }
+
+    /// Post a completion with `res` for the request `user_data`.
+    ///
+    /// The completion is counted as overflown if the completion ring is full.
+    pub fn post_completion(&self, user_data: u64, res: i32, flags: u32) {
+        // The rings are checked before taking the tail lock, as checking them
+        // locks the address space, which `mmap` holds while taking it.
+        let rings = self.cq_ring().and_then(|(ring, start)| {
+            let cqes = UserPtr::<io_uring_cqe>::from(start.as_usize() + CQES_OFFSET)
+                .get_as_mut_slice(self.cq_entries as _)?;
+            Ok((ring, cqes))
+        });
+        if let Ok((ring, cqes)) = rings {
+            let mut tail = self.cq_tail.lock();
+            let head = ring.head.load(Ordering::Acquire);
+            if tail.wrapping_sub(head) >= self.cq_entries {
+                ring.overflow.fetch_add(1, Ordering::Relaxed);
+            } else {
+                let cqe = &mut cqes[(*tail & (self.cq_entries - 1)) as usize];
+                cqe.user_data = user_data;
+                cqe.res = res;
+                cqe.flags = flags;
+                *tail = tail.wrapping_add(1);
+                ring.tail.store(*tail, Ordering::Release);
+            }
+        }
+
+        self.completions.fetch_add(1, Ordering::AcqRel);
+        self.completion_waiters.wake();
+        if let Some(eventfd) = self.eventfd.lock().clone() {
+            eventfd.signal(1);
+        }
+    }
+
+    /// Get the number of completions posted so far.
+    pub fn completions(&self) -> u64 {
+        self.completions.load(Ordering::Acquire)
+    }
+
+    /// Get the number of entries in the completion ring not yet consumed by
+    /// user space.
+    pub fn pending_completions(&self) -> LinuxResult<u32> {
+        let (ring, _) = self.cq_ring()?;
+        let head = ring.head.load(Ordering::Acquire);
+        Ok(ring.tail.load(Ordering::Acquire).wrapping_sub(head))
+    }
+
+    pub fn cq_entries(&self) -> u32 {
+        self.cq_entries
+    }
+
+    /// Register the files used by requests with `IOSQE_FIXED_FILE`.
+    pub fn register_files(&self, files: Vec<Option<Arc<dyn FileLike>>>) -> LinuxResult {
+        let mut registered = self.files.lock();
+        if registered.is_some() {
+            return Err(LinuxError::EBUSY);
+        }
+        *registered = Some(files);
+        Ok(())
+    }
+
+    pub fn unregister_files(&self) -> LinuxResult {
+        self.files.lock().take().map(drop).ok_or(LinuxError::ENXIO)
+    }
+
+    /// Get the registered file at `index`.
+    pub fn fixed_file(&self, index: u32) -> LinuxResult<Arc<dyn FileLike>> {
+        self.files
+            .lock()
+            .as_ref()
+            .and_then(|files| files.get(index as usize)?.clone())
+            .ok_or(LinuxError::EBADF)
+    }
+
+    /// Register the buffers used by `READ_FIXED` and `WRITE_FIXED` requests.
+    pub fn register_buffers(&self, buffers: Vec<(usize, usize)>) -> LinuxResult {
+        let mut registered = self.buffers.lock();
+        if registered.is_some() {
+            return Err(LinuxError::EBUSY);
+        }
+        *registered = Some(buffers);
+        Ok(())
+    }
+
+    pub fn unregister_buffers(&self) -> LinuxResult {
+        self.buffers
+            .lock()
+            .take()
+            .map(drop)
+            .ok_or(LinuxError::ENXIO)
+    }
+
+    /// Check that `len` bytes at `addr` are within the registered buffer at
+    /// `index`.
+    pub fn check_fixed_buffer(&self, index: u16, addr: usize, len: usize) -> LinuxResult {
+        let buffers = self.buffers.lock();
+        let (start, size) = buffers
+            .as_ref()
+            .and_then(|buffers| buffers.get(index as usize).copied())
+            .ok_or(LinuxError::EFAULT)?;
+        if addr < start || addr.saturating_add(len) > start + size {
+            return Err(LinuxError::EFAULT);
+        }
+        Ok(())
+    }
+
+    /// Set the eventfd signaled on completions, or remove it.
+    pub fn set_eventfd(&self, eventfd: Option<Arc<EventFd>>) -> LinuxResult {
+        let mut registered = self.eventfd.lock();
+        match (&*registered, &eventfd) {
+            (Some(_), Some(_)) => return Err(LinuxError::EBUSY),
+            (None, None) => return Err(LinuxError::ENXIO),
+            _ => {}
+        }
+        *registered = eventfd;
+        Ok(())
+    }
+
+    /// Register `waiter` to be woken up on completions.
+    pub fn register_waiter(&self, waiter: &Arc<PollWaiter>) {
+        self.completion_waiters.register(waiter);
+    }
+
+    pub fn canceler(&self) -> &Arc<Canceler> {
+        &self.canceler
+    }
+
+    /// Queue a chain of linked requests submitted when `completions`
+    /// completions were posted.
+    ///
+    /// Returns whether a worker is to be spawned for it, which is the case
+    /// as long as there are less than [`MAX_WORKERS`].
+    pub fn queue_chain(&self, chain: Vec<Submission>, completions: u64) -> bool {
+        let mut work = self.work.lock();
+        work.chains.push_back((chain, completions));
+        if work.workers >= MAX_WORKERS {
+            return false;
+        }
+        work.workers += 1;
+        true
+    }
+
+    /// Take the next queued chain for a worker, which exits if there is none.
+    pub fn next_chain(&self) -> Option<(Vec<Submission>, u64)> {
+        let mut work = self.work.lock();
+        let chain = work.chains.pop_front();
+        if chain.is_none() {
+            work.workers -= 1;
+        }
+        chain
+    }
+
+    /// Cancel the waits of the workers and complete the queued requests with
+    /// `ECANCELED`.
+    fn cancel(&self) {
+        self.canceler.cancel();
+        let chains = mem::take(&mut self.work.lock().chains);
+        for sub in chains.iter().flat_map(|(chain, _)| chain) {
+            self.post_completion(sub.user_data, -LinuxError::ECANCELED.code(), 0);
+        }
+    }
+}
+
+impl FileLike for IoUring {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: 0o600u32, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let readable = self.pending_completions().is_ok_and(|pending| pending > 0);
+        let writable = self.sq_ring().is_ok_and(|(ring, _)| {
+            let head = *self.sq_head.lock();
+            ring.tail.load(Ordering::Acquire).wrapping_sub(head) < self.sq_entries
+        });
+        Ok(PollState { readable, writable })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, _events: u32) -> bool {
+        // Room in the submission ring is only made by `io_uring_enter`, so
+        // only completions are waited for.
+        self.register_waiter(waiter);
+        true
+    }
+
+    fn event_seq(&self, _events: u32) -> Option<u64> {
+        Some(self.completion_waiters.seq())
+    }
+}
@@ -1,22 +1,50 @@
+mod epoll;
+mod eventfd;
 mod fs;
+mod inotify;
+mod io_uring;
 mod net;
+mod netlink;
+mod pidfd;
 mod pipe;
+mod poll;
+mod signalfd;
 mod stdio;
+mod timerfd;
+mod udp;
+mod unix;
 
 use core::{any::Any, ffi::c_int};
 
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
 use axns::{ResArc, def_resource};
+use axsync::Mutex;
 use flatten_objects::FlattenObjects;
-use linux_raw_sys::general::{stat, statx};
+use linux_raw_sys::general::{
+    POLLERR, POLLHUP, POLLIN, POLLOUT, POLLRDHUP, POLLRDNORM, POLLWRNORM, stat, statx,
+};
 use spin::RwLock;
 
 pub use self::{
+    epoll::Epoll,
+    eventfd::EventFd,
     fs::{Directory, File},
-    net::Socket,
+    inotify::{Inotify, notify_fs_event, notify_fs_move},
+    io_uring::{IoUring, IoUringShared, Submission},
+    net::{ShutdownState, Socket, SocketOptions},
+    pidfd::{PidFd, notify_pidfds},
     pipe::Pipe,
+    poll::{
+        Canceler, POLL_INTERVAL, PollSet, PollWaiter, current_worker, run_as_worker, wait_event,
+    },
+    signalfd::{SignalFd, notify_signalfds},
+    timerfd::TimerFd,
+    unix::{UnixAncillary, UnixSocketType, current_cred},
 };
 
 pub const AX_FILE_LIMIT: usize = 1024;
@@ -92,6 +120,25 @@ pub trait FileLike: Send + Sync {
     fn poll(&self) -> LinuxResult<PollState>;
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
 
+    /// Register `waiter` to be woken up when the file may have become ready
+    /// for the `POLL*` events in `events`.
+    ///
+    /// Returns `false` if the file cannot notify waiters, which then have to
+    /// poll it.
+    fn register(&self, _waiter: &Arc<PollWaiter>, _events: u32) -> bool {
+        false
+    }
+
+    /// Get a counter that moves each time the file has new events among the
+    /// `POLL*` events in `events`, usually when it wakes the waiters
+    /// registered for them, which tells edge-triggered epoll entries about
+    /// new events.
+    ///
+    /// Returns `None` if the file cannot tell about new events.
+    fn event_seq(&self, _events: u32) -> Option<u64> {
+        None
+    }
+
     fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>>
     where
         Self: Sized + 'static,
@@ -134,6 +181,40 @@ impl FD_TABLE {
     }
 }
 
+/// Get the `POLL*` events a file is ready for.
+///
+/// `POLLHUP` and `POLLERR` are reported for pipes whose other end is
+/// closed, and for sockets that are shut down or have a pending error.
+pub fn poll_events(file: Arc<dyn FileLike>) -> LinuxResult<u32> {
+    let state = file.poll()?;
+    let mut events = 0;
+    if state.readable {
+        events |= POLLIN | POLLRDNORM;
+    }
+    if state.writable {
+        events |= POLLOUT | POLLWRNORM;
+    }
+
+    let any = file.into_any();
+    if let Some(pipe) = any.downcast_ref::<Pipe>() {
+        if pipe.closed() {
+            events |= if pipe.readable() { POLLHUP } else { POLLERR };
+        }
+    } else if let Some(socket) = any.downcast_ref::<Socket>() {
+        let shutdown = socket.shutdown_state();
+        if shutdown.read {
+            events |= POLLRDHUP;
+        }
+        if shutdown.read && shutdown.write {
+            events |= POLLHUP;
+        }
+        if socket.has_error() {
+            events |= POLLERR;
+        }
+    }
+    Ok(events)
+}
+
 /// Get a file-like object by `fd`.
 pub fn get_file_like(fd: c_int) -> LinuxResult<Arc<dyn FileLike>> {
     FD_TABLE
@@ -148,6 +229,13 @@ pub fn add_file_like(f: Arc<dyn FileLike>) -> LinuxResult<c_int> {
     Ok(FD_TABLE.write().add(f).map_err(|_| LinuxError::EMFILE)? as c_int)
 }
 
+/// Replace the file at `fd` with `f`, keeping the file descriptor.
+pub fn replace_file_like(fd: c_int, f: Arc<dyn FileLike>) {
+    let mut table = FD_TABLE.write();
+    let _ = table.remove(fd as usize);
+    let _ = table.add_at(fd as usize, f);
+}
+
 /// Close a file by `fd`.
 pub fn close_file_like(fd: c_int) -> LinuxResult {
     let f = FD_TABLE
@@ -158,6 +246,14 @@ pub fn close_file_like(fd: c_int) -> LinuxResult {
     Ok(())
 }
 
+/// Get the objects of a registry of weak references that are still alive.
+///
+/// The lock is released before returning, in case the last reference to an
+/// object is dropped by the caller, whose destructor takes the lock again.
+pub fn live_entries<T>(registry: &Mutex<Vec<Weak<T>>>) -> Vec<Arc<T>> {
+    registry.lock().iter().filter_map(Weak::upgrade).collect()
+}
+
 #[ctor_bare::register_ctor]
 fn init_stdio() {
     let mut fd_table = flatten_objects::FlattenObjects::new();
@@ -1,78 +1,1130 @@
-use core::net::SocketAddr;
+use core::{
+    ffi::c_int,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    time::Duration,
+};
 
-use alloc::sync::Arc;
-use axerrno::{LinuxError, LinuxResult};
+use alloc::{sync::Arc, vec, vec::Vec};
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
 use axio::PollState;
 use axnet::{TcpSocket, UdpSocket};
-use axsync::Mutex;
-use linux_raw_sys::general::S_IFSOCK;
+use axsync::{Mutex, MutexGuard};
+use axtask::{TaskInner, WaitQueue};
+use linux_raw_sys::{
+    general::{POLLIN, POLLOUT, S_IFSOCK},
+    net::{
+        MSG_DONTWAIT, MSG_NOSIGNAL, MSG_PEEK, MSG_TRUNC, MSG_WAITALL, SOCK_DGRAM, SOCK_STREAM,
+        ucred,
+    },
+};
 
-use super::{FileLike, Kstat};
+use super::{
+    FileLike, Kstat, POLL_INTERVAL, PollSet, PollWaiter, get_file_like,
+    netlink::NetlinkSocket,
+    udp::{UdpState, loop_multicast},
+    unix::{UnixAncillary, UnixSocket, UnixSocketType},
+    wait_event,
+};
+use crate::{
+    netif::interfaces,
+    signal::send_sigpipe,
+    socket::{NetlinkSocketAddr, SocketAddress, UnixSocketAddr},
+};
 
-pub enum Socket {
-    Udp(Mutex<UdpSocket>),
-    Tcp(Mutex<TcpSocket>),
+/// Default size of the send and receive buffers reported to user space,
+/// matching the buffers axnet allocates for each socket.
+const DEFAULT_BUF_SIZE: usize = 64 * 1024;
+/// Maximum payload of a UDP datagram.
+const UDP_MAX_PAYLOAD: usize = 65507;
+/// Minimum size of the send and receive buffers accepted by `setsockopt`.
+const MIN_BUF_SIZE: usize = 2048;
+/// Idle time before keep-alive probes are sent, the default of Linux.
+const TCP_KEEPALIVE_TIME: Duration = Duration::from_secs(7200);
+
+/// Waiters for TCP and UDP sockets.
+///
+/// axnet is not driven by interrupts, so the waiters are woken up by
+/// [`net_poller`] every [`POLL_INTERVAL`] while there are any, and right
+/// away after an operation that may have made another socket ready, like
+/// sending on the loopback interface.
+static NET_WAITERS: PollSet = PollSet::new();
+/// Some waiters registered with [`NET_WAITERS`] since the last pass of the
+/// poller.
+static NET_WAITING: AtomicBool = AtomicBool::new(false);
+static NET_POLLER_STARTED: AtomicBool = AtomicBool::new(false);
+static NET_POLLER_WQ: WaitQueue = WaitQueue::new();
+
+/// Process the packets on the interfaces, and wake up the waiters for TCP
+/// and UDP sockets.
+fn wake_net_waiters() {
+    axnet::poll_interfaces();
+    NET_WAITERS.wake();
 }
 
-macro_rules! impl_socket {
-    ($pub:vis fn $name:ident(&self $(,$arg:ident: $arg_ty:ty)*) -> $ret:ty) => {
-        $pub fn $name(&self, $($arg: $arg_ty),*) -> $ret {
-            match self {
-                Socket::Udp(udpsocket) => Ok(udpsocket.lock().$name($($arg),*)?),
-                Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().$name($($arg),*)?),
-            }
+fn register_net_waiter(waiter: &Arc<PollWaiter>) {
+    NET_WAITERS.register(waiter);
+    if !NET_POLLER_STARTED.swap(true, Ordering::AcqRel) {
+        axtask::spawn_task(TaskInner::new(
+            net_poller,
+            "net-poller".into(),
+            axconfig::plat::KERNEL_STACK_SIZE,
+        ));
+    }
+    if !NET_WAITING.swap(true, Ordering::AcqRel) {
+        NET_POLLER_WQ.notify_one(false);
+    }
+}
+
+/// Drive axnet on behalf of the threads waiting for sockets.
+fn net_poller() {
+    loop {
+        NET_POLLER_WQ.wait_until(|| NET_WAITING.load(Ordering::Acquire));
+        axtask::sleep(POLL_INTERVAL);
+        // Waiters that are woken up register again if they keep waiting.
+        NET_WAITING.store(false, Ordering::Release);
+        wake_net_waiters();
+    }
+}
+
+/// The rising edges of the readiness of a TCP or UDP socket, which are the
+/// event sequence of the socket for edge-triggered epoll entries.
+///
+/// axnet does not tell when a socket becomes ready, so readiness is sampled
+/// whenever the sequence is read. A socket counts as not readable again once
+/// a receive takes data from it, and as not ready for the events an
+/// operation fails with `EAGAIN` on, so that data arriving after a drain is
+/// a new edge.
+#[derive(Default)]
+struct ReadyEdges {
+    seq: AtomicU64,
+    /// The `POLLIN` and `POLLOUT` events ready at the last sample.
+    last: AtomicU32,
+}
+
+impl ReadyEdges {
+    /// Sample the readiness `state` of the socket, returning the sequence.
+    fn sample(&self, state: PollState) -> u64 {
+        let mut ready = 0;
+        if state.readable {
+            ready |= POLLIN;
         }
-    };
+        if state.writable {
+            ready |= POLLOUT;
+        }
+        let last = self.last.swap(ready, Ordering::AcqRel);
+        if ready & !last != 0 {
+            self.seq.fetch_add(1, Ordering::AcqRel) + 1
+        } else {
+            self.seq.load(Ordering::Acquire)
+        }
+    }
+
+    /// Forget that the socket was ready for the `POLL*` events in `events`.
+    fn clear(&self, events: u32) {
+        self.last.fetch_and(!events, Ordering::AcqRel);
+    }
+}
+
+/// Per-socket options set through `setsockopt`.
+///
+/// `SO_REUSEADDR`, `SO_KEEPALIVE` and `TCP_NODELAY` are passed down to the
+/// axnet sockets by [`Socket::apply_options`], and the timeouts are honored
+/// by the blocking loop of [`Socket`]. axnet has no knobs for `SO_REUSEPORT`
+/// and the buffer sizes, which are only recorded and reported back by
+/// `getsockopt`.
+#[derive(Debug, Clone)]
+pub struct SocketOptions {
+    /// `SO_REUSEADDR`
+    pub reuse_addr: bool,
+    /// `SO_REUSEPORT`
+    pub reuse_port: bool,
+    /// `SO_KEEPALIVE`
+    pub keep_alive: bool,
+    /// `TCP_NODELAY`
+    pub no_delay: bool,
+    /// `SO_SNDBUF`
+    pub send_buf_size: usize,
+    /// `SO_RCVBUF`
+    pub recv_buf_size: usize,
+    /// `SO_SNDTIMEO`, `None` means blocking forever.
+    pub send_timeout: Option<Duration>,
+    /// `SO_RCVTIMEO`, `None` means blocking forever.
+    pub recv_timeout: Option<Duration>,
+    /// `SO_PASSCRED`
+    pub pass_cred: bool,
+    /// `SO_LINGER`, the timeout in seconds if enabled. Only Unix and UDP
+    /// sockets, which close right away anyway, accept it.
+    pub linger: Option<u32>,
+    /// `SO_BROADCAST`
+    pub broadcast: bool,
+    /// `IP_MULTICAST_LOOP` and `IPV6_MULTICAST_LOOP`
+    pub multicast_loop: bool,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            reuse_addr: false,
+            reuse_port: false,
+            keep_alive: false,
+            no_delay: false,
+            send_buf_size: DEFAULT_BUF_SIZE,
+            recv_buf_size: DEFAULT_BUF_SIZE,
+            send_timeout: None,
+            recv_timeout: None,
+            pass_cred: false,
+            linger: None,
+            broadcast: false,
+            multicast_loop: true,
+        }
+    }
+}
+
+impl SocketOptions {
+    /// Set the size of a socket buffer the way Linux does: the value is
+    /// doubled to leave room for bookkeeping overhead, and clamped to a
+    /// minimum.
+    pub fn buf_size_from_user(size: c_int) -> usize {
+        (size.max(0) as usize * 2).max(MIN_BUF_SIZE)
+    }
+}
+
+pub enum SocketInner {
+    Udp(Mutex<UdpSocket>, Arc<UdpState>),
+    Tcp(Mutex<TcpSocket>),
+    Unix(Arc<UnixSocket>),
+    Netlink(NetlinkSocket),
+}
+
+/// Which directions of a socket are shut down, either locally or by the
+/// peer.
+///
+/// `poll` reports `POLLRDHUP` if the receiving side is shut down, and
+/// `POLLHUP` if both are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShutdownState {
+    /// No more data will be received.
+    pub read: bool,
+    /// No more data can be sent.
+    pub write: bool,
+}
+
+/// A socket file.
+///
+/// The underlying axnet sockets are always kept in non-blocking mode, the
+/// blocking behavior (including `SO_RCVTIMEO` and `SO_SNDTIMEO`) is
+/// implemented here on top of them.
+pub struct Socket {
+    inner: SocketInner,
+    nonblocking: AtomicBool,
+    options: Mutex<SocketOptions>,
+    /// TCP data received by `MSG_PEEK` but not consumed yet.
+    tcp_peeked: Mutex<Vec<u8>>,
+    /// A TCP connection is being established in the background.
+    connecting: AtomicBool,
+    /// The error reported by `SO_ERROR`.
+    pending_error: Mutex<Option<LinuxError>>,
+    /// The receiving side is shut down by `SHUT_RD`.
+    shut_rd: AtomicBool,
+    /// The sending side is shut down by `SHUT_WR`.
+    shut_wr: AtomicBool,
+    /// The peer of a TCP connection has shut down its sending side.
+    tcp_eof: AtomicBool,
+    /// The reset of a TCP connection has been reported by a send.
+    tcp_reset: AtomicBool,
+    /// The event sequence of TCP and UDP sockets.
+    edges: ReadyEdges,
+}
+
+/// Get the internet address an inet socket operates on.
+fn inet_addr(addr: SocketAddress) -> LinuxResult<SocketAddr> {
+    match addr {
+        SocketAddress::Inet(addr) => Ok(addr),
+        _ => Err(LinuxError::EAFNOSUPPORT),
+    }
+}
+
+/// Get the Unix address a Unix socket operates on.
+fn unix_addr(addr: SocketAddress) -> LinuxResult<UnixSocketAddr> {
+    match addr {
+        SocketAddress::Unix(addr) => Ok(addr),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Get the netlink address a netlink socket operates on.
+fn netlink_addr(addr: SocketAddress) -> LinuxResult<NetlinkSocketAddr> {
+    match addr {
+        SocketAddress::Netlink(addr) => Ok(addr),
+        _ => Err(LinuxError::EINVAL),
+    }
 }
 
 impl Socket {
+    fn new(inner: SocketInner) -> Self {
+        match &inner {
+            SocketInner::Udp(udpsocket, _) => udpsocket.lock().set_nonblocking(true),
+            SocketInner::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(true),
+            SocketInner::Unix(_) | SocketInner::Netlink(_) => {}
+        }
+        Self {
+            inner,
+            nonblocking: AtomicBool::new(false),
+            options: Mutex::new(SocketOptions::default()),
+            tcp_peeked: Mutex::new(Vec::new()),
+            connecting: AtomicBool::new(false),
+            pending_error: Mutex::new(None),
+            shut_rd: AtomicBool::new(false),
+            shut_wr: AtomicBool::new(false),
+            tcp_eof: AtomicBool::new(false),
+            tcp_reset: AtomicBool::new(false),
+            edges: ReadyEdges::default(),
+        }
+    }
+
+    /// Create a new TCP socket.
+    pub fn new_tcp() -> Self {
+        Self::new(SocketInner::Tcp(Mutex::new(TcpSocket::new())))
+    }
+
+    /// Create a new UDP socket.
+    pub fn new_udp() -> Self {
+        Self::new(SocketInner::Udp(
+            Mutex::new(UdpSocket::new()),
+            UdpState::new(),
+        ))
+    }
+
+    /// Create a new Unix domain socket.
+    pub fn new_unix(ty: UnixSocketType) -> Self {
+        Self::new(SocketInner::Unix(Arc::new(UnixSocket::new(ty))))
+    }
+
+    /// Create a new `NETLINK_ROUTE` socket of type `SOCK_RAW` or `SOCK_DGRAM`.
+    pub fn new_netlink(ty: u32) -> Self {
+        Self::new(SocketInner::Netlink(NetlinkSocket::new(ty)))
+    }
+
+    /// Create a pair of Unix domain sockets connected to each other.
+    pub fn new_unix_pair(ty: UnixSocketType) -> (Self, Self) {
+        let (socket1, socket2) = UnixSocket::new_pair(ty);
+        (
+            Self::new(SocketInner::Unix(Arc::new(socket1))),
+            Self::new(SocketInner::Unix(Arc::new(socket2))),
+        )
+    }
+
+    /// Get the socket type (`SOCK_STREAM`, `SOCK_DGRAM` or `SOCK_RAW`).
+    pub fn socket_type(&self) -> u32 {
+        match &self.inner {
+            SocketInner::Udp(..) => SOCK_DGRAM,
+            SocketInner::Tcp(_) => SOCK_STREAM,
+            SocketInner::Unix(unixsocket) => match unixsocket.socket_type() {
+                UnixSocketType::Stream => SOCK_STREAM,
+                UnixSocketType::Datagram => SOCK_DGRAM,
+            },
+            SocketInner::Netlink(netlinksocket) => netlinksocket.socket_type(),
+        }
+    }
+
+    /// Get the Unix socket this socket wraps, if it is one.
+    pub fn unix_socket(&self) -> Option<&Arc<UnixSocket>> {
+        match &self.inner {
+            SocketInner::Unix(unixsocket) => Some(unixsocket),
+            _ => None,
+        }
+    }
+
+    /// Get the options of the socket.
+    pub fn options(&self) -> MutexGuard<SocketOptions> {
+        self.options.lock()
+    }
+
+    /// Whether the socket is in non-blocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    /// Run `f` until it stops failing with `EAGAIN`, sleeping until the
+    /// socket may have become ready for the `POLL*` events in `events` in
+    /// between.
+    ///
+    /// If `nonblocking` is set the first result is returned as is. Otherwise,
+    /// `EAGAIN` is returned once `timeout` has elapsed.
+    fn block_on<T>(
+        &self,
+        events: u32,
+        nonblocking: bool,
+        timeout: Option<Duration>,
+        mut f: impl FnMut() -> LinuxResult<T>,
+    ) -> LinuxResult<T> {
+        let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+        let inet = matches!(self.inner, SocketInner::Udp(..) | SocketInner::Tcp(_));
+        let res = wait_event(deadline, true, |waiter| {
+            if inet {
+                axnet::poll_interfaces();
+            }
+            waiter.watch(self, events);
+            let res = f();
+            if inet {
+                match &res {
+                    Ok(_) => self.edges.clear(events & POLLIN),
+                    Err(LinuxError::EAGAIN) => self.edges.clear(events),
+                    Err(_) => {}
+                }
+            }
+            match res {
+                Err(LinuxError::EAGAIN) if !nonblocking => Ok(None),
+                res => res.map(Some),
+            }
+        })?;
+        if inet && res.is_some() {
+            wake_net_waiters();
+        }
+        res.ok_or(LinuxError::EAGAIN)
+    }
+
     pub fn recv(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        match self {
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().recv_from(buf).map(|e| e.0)?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf)?),
+        let (len, ..) = self.recv_msg(buf, 0)?;
+        Ok(len.min(buf.len()))
+    }
+
+    pub fn send(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.send_msg(buf, None, UnixAncillary::default(), 0)
+    }
+
+    /// Send `buf` to `addr`, or to the connected peer if `addr` is `None`.
+    ///
+    /// `ancillary` can only be passed through Unix sockets. `flags` are the
+    /// `MSG_*` flags of `sendmsg`, of which `MSG_DONTWAIT` and `MSG_NOSIGNAL`
+    /// change the behavior here.
+    ///
+    /// Sending on a stream socket whose sending side is shut down, or whose
+    /// connection is broken, fails with `EPIPE` and raises `SIGPIPE`, unless
+    /// `MSG_NOSIGNAL` is set.
+    pub fn send_msg(
+        &self,
+        buf: &[u8],
+        addr: Option<SocketAddress>,
+        ancillary: UnixAncillary,
+        flags: u32,
+    ) -> LinuxResult<usize> {
+        if !matches!(self.inner, SocketInner::Unix(_))
+            && (!ancillary.rights.is_empty() || ancillary.cred.is_some())
+        {
+            return Err(LinuxError::EINVAL);
         }
+        let res = if self.shut_wr.load(Ordering::Acquire) {
+            Err(LinuxError::EPIPE)
+        } else {
+            self.send_inner(buf, addr, ancillary, flags)
+        };
+        if matches!(res, Err(LinuxError::EPIPE))
+            && self.socket_type() == SOCK_STREAM
+            && flags & MSG_NOSIGNAL == 0
+        {
+            send_sigpipe();
+        }
+        res
     }
 
-    pub fn sendto(&self, buf: &[u8], addr: SocketAddr) -> LinuxResult<usize> {
-        match self {
-            // diff: must bind before sendto
-            Socket::Udp(udpsocket) => Ok(udpsocket.lock().send_to(buf, addr)?),
-            Socket::Tcp(_) => Err(LinuxError::EISCONN),
+    fn send_inner(
+        &self,
+        buf: &[u8],
+        addr: Option<SocketAddress>,
+        mut ancillary: UnixAncillary,
+        flags: u32,
+    ) -> LinuxResult<usize> {
+        let nonblocking = flags & MSG_DONTWAIT != 0 || self.is_nonblocking();
+        let timeout = self.options().send_timeout;
+        match &self.inner {
+            SocketInner::Udp(udpsocket, state) => {
+                let dst = match addr {
+                    Some(addr) => inet_addr(addr)?,
+                    None => state.peer().ok_or(LinuxError::EDESTADDRREQ)?,
+                };
+                self.check_broadcast(dst.ip())?;
+                let src = udp_autobind(udpsocket, state, &dst)?;
+                if dst.ip().is_multicast() {
+                    if self.options().multicast_loop {
+                        loop_multicast(buf, udp_source(src), dst);
+                    }
+                    // Like on Linux, a multicast datagram counts as sent even
+                    // if it cannot leave the host.
+                    if let Err(err) = udpsocket.lock().send_to(buf, dst) {
+                        warn!("udp: failed to send to {}: {:?}", dst, err);
+                    }
+                    return Ok(buf.len());
+                }
+                self.block_on(POLLOUT, nonblocking, timeout, || {
+                    Ok(udpsocket.lock().send_to(buf, dst)?)
+                })
+            }
+            SocketInner::Tcp(_) if addr.is_some() => Err(LinuxError::EISCONN),
+            SocketInner::Tcp(tcpsocket) => self.block_on(POLLOUT, nonblocking, timeout, || {
+                // axnet fails with `ECONNRESET` after a concurrent `SHUT_WR`.
+                if self.shut_wr.load(Ordering::Acquire) {
+                    return Err(LinuxError::EPIPE);
+                }
+                match tcpsocket.lock().send(buf) {
+                    // Like on Linux, the reset of the connection is reported
+                    // once, and sending fails with `EPIPE` afterwards.
+                    Err(AxError::ConnectionReset)
+                        if self.tcp_reset.swap(true, Ordering::AcqRel) =>
+                    {
+                        Err(LinuxError::EPIPE)
+                    }
+                    res => Ok(res?),
+                }
+            }),
+            SocketInner::Unix(unixsocket) => {
+                let addr = addr.map(unix_addr).transpose()?;
+                self.block_on(POLLOUT, nonblocking, timeout, || {
+                    unixsocket.send(buf, addr.as_ref(), &mut ancillary)
+                })
+            }
+            // Replies are queued right away, sending never blocks.
+            SocketInner::Netlink(netlinksocket) => {
+                let addr = addr.map(netlink_addr).transpose()?;
+                netlinksocket.send(buf, addr.as_ref())
+            }
+        }
+    }
+
+    /// Receive data into `buf`, returning the length of the received data,
+    /// the source address if the protocol provides one, and the ancillary
+    /// data received by Unix sockets.
+    ///
+    /// `flags` are the `MSG_*` flags of `recvmsg`, `MSG_TRUNC` is handled by
+    /// the caller except for TCP. For datagram sockets the
+    /// returned length is the full length of the datagram, which exceeds
+    /// `buf.len()` if it was truncated.
+    pub fn recv_msg(
+        &self,
+        buf: &mut [u8],
+        flags: u32,
+    ) -> LinuxResult<(usize, Option<SocketAddress>, UnixAncillary)> {
+        if flags & MSG_TRUNC != 0 && matches!(self.inner, SocketInner::Tcp(_)) {
+            return self.tcp_discard(buf.len(), flags & !MSG_TRUNC);
+        }
+        let nonblocking = flags & MSG_DONTWAIT != 0 || self.is_nonblocking();
+        let peek = flags & MSG_PEEK != 0;
+        let timeout = self.options().recv_timeout;
+        // Receive buffer of UDP datagrams, allocated on first use.
+        let mut dgram = Vec::new();
+
+        if flags & MSG_WAITALL != 0 && !peek && !nonblocking && self.socket_type() == SOCK_STREAM {
+            // Keep receiving until the buffer is full, the peer shuts down
+            // or an error occurs after some data has been received.
+            let mut read = 0;
+            let mut src = None;
+            let mut ancillary = UnixAncillary::default();
+            while read < buf.len() {
+                match self.block_on(POLLIN, nonblocking, timeout, || {
+                    self.recv_once(&mut buf[read..], false, &mut dgram)
+                }) {
+                    Ok((0, ..)) => break,
+                    Ok((len, addr, mut received)) => {
+                        read += len;
+                        src = src.or(addr);
+                        ancillary.rights.append(&mut received.rights);
+                        ancillary.cred = ancillary.cred.or(received.cred);
+                    }
+                    Err(err) if read == 0 => return Err(err),
+                    Err(_) => break,
+                }
+            }
+            return Ok((read, src, ancillary));
+        }
+        self.block_on(POLLIN, nonblocking, timeout, || {
+            self.recv_once(buf, peek, &mut dgram)
+        })
+    }
+
+    /// Receive and discard up to `len` bytes from a TCP connection, as
+    /// `MSG_TRUNC` does, in chunks no larger than the receive buffer.
+    fn tcp_discard(
+        &self,
+        len: usize,
+        flags: u32,
+    ) -> LinuxResult<(usize, Option<SocketAddress>, UnixAncillary)> {
+        let mut discard = vec![0; len.min(DEFAULT_BUF_SIZE)];
+        let mut discarded = 0;
+        while discarded < len {
+            let chunk = (len - discarded).min(discard.len());
+            let flags = if discarded == 0 || flags & MSG_WAITALL != 0 {
+                flags
+            } else {
+                flags | MSG_DONTWAIT
+            };
+            match self.recv_msg(&mut discard[..chunk], flags) {
+                Ok((received, ..)) => {
+                    discarded += received;
+                    if received < chunk || flags & MSG_PEEK != 0 {
+                        break;
+                    }
+                }
+                Err(err) if discarded == 0 => return Err(err),
+                Err(_) => break,
+            }
+        }
+        Ok((discarded, None, UnixAncillary::default()))
+    }
+
+    fn recv_once(
+        &self,
+        buf: &mut [u8],
+        peek: bool,
+        dgram: &mut Vec<u8>,
+    ) -> LinuxResult<(usize, Option<SocketAddress>, UnixAncillary)> {
+        match self.recv_inner(buf, peek, dgram) {
+            // Nothing arrives after `SHUT_RD`, reads return end-of-file once
+            // the queued data is consumed.
+            Err(LinuxError::EAGAIN) if self.shut_rd.load(Ordering::Acquire) => {
+                Ok((0, None, UnixAncillary::default()))
+            }
+            res => res,
         }
     }
 
-    pub fn recvfrom(&self, buf: &mut [u8]) -> LinuxResult<(usize, Option<SocketAddr>)> {
-        match self {
-            // diff: must bind before recvfrom
-            Socket::Udp(udpsocket) => Ok(udpsocket
+    fn recv_inner(
+        &self,
+        buf: &mut [u8],
+        peek: bool,
+        dgram: &mut Vec<u8>,
+    ) -> LinuxResult<(usize, Option<SocketAddress>, UnixAncillary)> {
+        match &self.inner {
+            SocketInner::Udp(udpsocket, state) => {
+                if let Some((len, src)) = state.recv_looped(buf, peek) {
+                    return Ok((len, Some(src.into()), UnixAncillary::default()));
+                }
+                let udpsocket = udpsocket.lock();
+                // Nothing arrives at an unbound socket.
+                if udpsocket.local_addr().is_err() {
+                    return Err(LinuxError::EAGAIN);
+                }
+                // axnet refuses to truncate datagrams, so receive the whole
+                // datagram first.
+                if dgram.is_empty() {
+                    dgram.resize(UDP_MAX_PAYLOAD, 0);
+                }
+                loop {
+                    let (len, src) = if peek {
+                        udpsocket.peek_from(dgram)?
+                    } else {
+                        udpsocket.recv_from(dgram)?
+                    };
+                    if !state.accepts(&src) {
+                        // Drop datagrams from others than the connected peer.
+                        if peek {
+                            udpsocket.recv_from(dgram)?;
+                        }
+                        continue;
+                    }
+                    let copied = len.min(buf.len());
+                    buf[..copied].copy_from_slice(&dgram[..copied]);
+                    return Ok((len, Some(src.into()), UnixAncillary::default()));
+                }
+            }
+            SocketInner::Tcp(tcpsocket) => {
+                // axnet cannot peek TCP data, so peeked data is moved into
+                // `tcp_peeked` and handed out before anything else.
+                let mut peeked = self.tcp_peeked.lock();
+                if peek && peeked.is_empty() {
+                    // No more than the receive buffer can be pending.
+                    let mut data = vec![0; buf.len().min(DEFAULT_BUF_SIZE)];
+                    let len = self.tcp_recv(&tcpsocket.lock(), &mut data)?;
+                    peeked.extend(&data[..len]);
+                }
+                if peeked.is_empty() {
+                    let len = self.tcp_recv(&tcpsocket.lock(), buf)?;
+                    return Ok((len, None, UnixAncillary::default()));
+                }
+                let len = peeked.len().min(buf.len());
+                buf[..len].copy_from_slice(&peeked[..len]);
+                if !peek {
+                    peeked.drain(..len);
+                }
+                Ok((len, None, UnixAncillary::default()))
+            }
+            // Linux reports no address for messages from unbound senders.
+            SocketInner::Unix(unixsocket) => {
+                unixsocket.recv(buf, peek).map(|(len, from, ancillary)| {
+                    let from = (from != UnixSocketAddr::Unnamed).then(|| from.into());
+                    (len, from, ancillary)
+                })
+            }
+            SocketInner::Netlink(netlinksocket) => {
+                let len = netlinksocket.recv(buf, peek)?;
+                let from = NetlinkSocketAddr { pid: 0, groups: 0 };
+                Ok((len, Some(from.into()), UnixAncillary::default()))
+            }
+        }
+    }
+
+    /// Receive from a TCP connection, recording the end-of-file.
+    fn tcp_recv(&self, tcpsocket: &TcpSocket, buf: &mut [u8]) -> LinuxResult<usize> {
+        match tcpsocket.recv(buf) {
+            Ok(0) if !buf.is_empty() => {
+                self.tcp_eof.store(true, Ordering::Release);
+                Ok(0)
+            }
+            // axnet refuses to receive once the connection is fully closed,
+            // which happens as soon as the peer closes after `SHUT_WR`.
+            Err(AxError::ConnectionRefused) if self.shut_wr.load(Ordering::Acquire) => Ok(0),
+            res => Ok(res?),
+        }
+    }
+
+    /// Check whether the peer of a TCP connection has shut down its sending
+    /// side.
+    ///
+    /// axnet only reports the end-of-file to reads, so a single pending byte
+    /// is moved into `tcp_peeked` to find out.
+    fn tcp_peer_closed(&self, tcpsocket: &Mutex<TcpSocket>) -> bool {
+        if self.tcp_eof.load(Ordering::Acquire) {
+            return true;
+        }
+        let mut peeked = self.tcp_peeked.lock();
+        if !peeked.is_empty() {
+            return false;
+        }
+        let tcpsocket = tcpsocket.lock();
+        if !tcpsocket.poll().is_ok_and(|state| state.readable) {
+            return false;
+        }
+        let mut byte = [0; 1];
+        if let Ok(len) = self.tcp_recv(&tcpsocket, &mut byte) {
+            peeked.extend(&byte[..len]);
+        }
+        self.tcp_eof.load(Ordering::Acquire)
+    }
+
+    pub fn listen(&self, backlog: usize) -> LinuxResult {
+        match &self.inner {
+            SocketInner::Udp(..) | SocketInner::Netlink(_) => Err(LinuxError::EOPNOTSUPP),
+            // The pending queue of TCP sockets is managed by axnet.
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
+            SocketInner::Unix(unixsocket) => unixsocket.listen(backlog),
+        }
+    }
+
+    pub fn accept(&self) -> LinuxResult<Socket> {
+        let timeout = self.options().recv_timeout;
+        let inner = match &self.inner {
+            SocketInner::Udp(..) | SocketInner::Netlink(_) => {
+                return Err(LinuxError::EOPNOTSUPP);
+            }
+            SocketInner::Tcp(tcpsocket) => SocketInner::Tcp(Mutex::new(self.block_on(
+                POLLIN,
+                self.is_nonblocking(),
+                timeout,
+                || Ok(tcpsocket.lock().accept()?),
+            )?)),
+            SocketInner::Unix(unixsocket) => {
+                SocketInner::Unix(self.block_on(POLLIN, self.is_nonblocking(), timeout, || {
+                    unixsocket.accept()
+                })?)
+            }
+        };
+        // Like on Linux, the new connection inherits the options of the
+        // listening socket.
+        let socket = Self::new(inner);
+        *socket.options() = self.options().clone();
+        socket.apply_options();
+        Ok(socket)
+    }
+
+    pub fn connect(&self, addr: SocketAddress) -> LinuxResult {
+        if addr == SocketAddress::Unspec {
+            return self.disconnect();
+        }
+        let tcpsocket = match &self.inner {
+            // axnet cannot dissolve the association of a UDP socket, so the
+            // peer is only recorded here.
+            SocketInner::Udp(udpsocket, state) => {
+                let addr = inet_addr(addr)?;
+                self.check_broadcast(addr.ip())?;
+                udp_autobind(udpsocket, state, &addr)?;
+                state.set_peer(Some(addr));
+                return Ok(());
+            }
+            SocketInner::Tcp(tcpsocket) => tcpsocket,
+            SocketInner::Unix(unixsocket) => {
+                let addr = unix_addr(addr)?;
+                let timeout = self.options().send_timeout;
+                return self.block_on(POLLOUT, self.is_nonblocking(), timeout, || {
+                    unixsocket.connect(&addr)
+                });
+            }
+            SocketInner::Netlink(netlinksocket) => {
+                return netlinksocket.connect(&netlink_addr(addr)?);
+            }
+        };
+
+        axnet::poll_interfaces();
+        if self.poll_connect() {
+            return Err(LinuxError::EALREADY);
+        }
+        // A failed connection attempt is reported once, as Linux does.
+        if let Some(err) = self.take_error() {
+            return Err(err);
+        }
+        let res = tcpsocket.lock().connect(inet_addr(addr)?);
+        // The connection exists now, so its options can be applied.
+        self.apply_options();
+        match res {
+            Err(AxError::WouldBlock) => {}
+            Err(AxError::AlreadyExists) => return Err(LinuxError::EISCONN),
+            res => return Ok(res?),
+        }
+        self.connecting.store(true, Ordering::Release);
+        if self.is_nonblocking() {
+            return Err(LinuxError::EINPROGRESS);
+        }
+
+        // Linux applies the send timeout to `connect`. On timeout the
+        // connection keeps being established in the background.
+        let timeout = self.options().send_timeout;
+        self.block_on(POLLOUT, false, timeout, || {
+            if self.poll_connect() {
+                return Err(LinuxError::EAGAIN);
+            }
+            match self.take_error() {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        })
+        .map_err(|err| match err {
+            LinuxError::EAGAIN => LinuxError::EINPROGRESS,
+            err => err,
+        })
+    }
+
+    /// Pass the options of the socket down to the axnet socket.
+    ///
+    /// `SO_REUSEADDR` takes effect on the next `bind`. smoltcp keeps the
+    /// Nagle and keep-alive settings per connection, so they can only be
+    /// applied once `connect` or `accept` has created one, and are applied
+    /// again then.
+    pub fn apply_options(&self) {
+        let opts = self.options().clone();
+        match &self.inner {
+            SocketInner::Udp(udpsocket, _) => udpsocket.lock().set_reuse_addr(opts.reuse_addr),
+            SocketInner::Tcp(tcpsocket) => {
+                let tcpsocket = tcpsocket.lock();
+                tcpsocket.set_reuse_addr(opts.reuse_addr);
+                let _ = tcpsocket.set_nagle_enabled(!opts.no_delay);
+                let _ = tcpsocket.set_keep_alive(opts.keep_alive.then_some(TCP_KEEPALIVE_TIME));
+            }
+            SocketInner::Unix(_) | SocketInner::Netlink(_) => {}
+        }
+    }
+
+    /// Set `SO_LINGER`.
+    ///
+    /// axnet neither reports when the data sent over a TCP connection is
+    /// acknowledged nor can it abort a connection, so lingering on close
+    /// cannot be implemented for TCP sockets and is refused for them.
+    pub fn set_linger(&self, linger: Option<u32>) -> LinuxResult {
+        if linger.is_some() && matches!(self.inner, SocketInner::Tcp(_)) {
+            warn!("setsockopt: SO_LINGER is not supported on TCP sockets");
+            return Err(LinuxError::ENOPROTOOPT);
+        }
+        self.options().linger = linger;
+        Ok(())
+    }
+
+    /// Dissolve the association of the socket, as `connect` with `AF_UNSPEC`
+    /// does.
+    fn disconnect(&self) -> LinuxResult {
+        match &self.inner {
+            SocketInner::Udp(_, state) => state.set_peer(None),
+            // axnet cannot abort connections.
+            SocketInner::Tcp(_) => return Err(LinuxError::EOPNOTSUPP),
+            SocketInner::Unix(unixsocket) => unixsocket.disconnect()?,
+            SocketInner::Netlink(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Check that sending to `ip` is allowed, broadcast addresses require
+    /// `SO_BROADCAST`.
+    fn check_broadcast(&self, ip: IpAddr) -> LinuxResult {
+        let IpAddr::V4(ip) = ip else {
+            return Ok(());
+        };
+        let broadcast = ip.is_broadcast()
+            || interfaces()
+                .iter()
+                .any(|iface| !iface.is_loopback() && iface.broadcast() == ip);
+        if broadcast && !self.options().broadcast {
+            return Err(LinuxError::EACCES);
+        }
+        Ok(())
+    }
+
+    /// Join the multicast `group` on the interface with index `ifindex`.
+    pub fn join_multicast(&self, group: IpAddr, ifindex: c_int) -> LinuxResult {
+        match &self.inner {
+            SocketInner::Udp(_, state) => state.join(group, ifindex),
+            _ => Err(LinuxError::EPROTO),
+        }
+    }
+
+    /// Leave the multicast `group` joined on the interface with index
+    /// `ifindex`.
+    pub fn leave_multicast(&self, group: IpAddr, ifindex: c_int) -> LinuxResult {
+        match &self.inner {
+            SocketInner::Udp(_, state) => state.leave(group, ifindex),
+            _ => Err(LinuxError::EPROTO),
+        }
+    }
+
+    /// Check on a TCP connection being established in the background,
+    /// recording a failure as the pending error.
+    ///
+    /// Return whether the connection is still in progress.
+    fn poll_connect(&self) -> bool {
+        let SocketInner::Tcp(tcpsocket) = &self.inner else {
+            return false;
+        };
+        if !self.connecting.load(Ordering::Acquire) {
+            return false;
+        }
+        let tcpsocket = tcpsocket.lock();
+        let error = match tcpsocket.poll() {
+            Ok(state) if !state.writable => return true,
+            // axnet resets the peer address if the connection failed.
+            Ok(_) => tcpsocket
+                .peer_addr()
+                .err()
+                .map(|_| LinuxError::ECONNREFUSED),
+            Err(err) => Some(err.into()),
+        };
+        self.connecting.store(false, Ordering::Release);
+        if error.is_some() {
+            *self.pending_error.lock() = error;
+        }
+        false
+    }
+
+    /// Take the pending error of the socket, as `SO_ERROR` does.
+    pub fn take_error(&self) -> Option<LinuxError> {
+        if self.connecting.load(Ordering::Acquire) {
+            axnet::poll_interfaces();
+            self.poll_connect();
+        }
+        self.pending_error.lock().take()
+    }
+
+    /// Whether the socket has a pending error, which `poll` reports as
+    /// `POLLERR`.
+    pub fn has_error(&self) -> bool {
+        if self.connecting.load(Ordering::Acquire) {
+            axnet::poll_interfaces();
+            self.poll_connect();
+        }
+        self.pending_error.lock().is_some()
+    }
+
+    pub fn poll(&self) -> LinuxResult<PollState> {
+        let mut state = self.poll_inner()?;
+        // Reads return end-of-file and sends fail right away after a
+        // shutdown.
+        state.readable |= self.shut_rd.load(Ordering::Acquire);
+        state.writable |= self.shut_wr.load(Ordering::Acquire);
+        Ok(state)
+    }
+
+    fn poll_inner(&self) -> LinuxResult<PollState> {
+        match &self.inner {
+            SocketInner::Udp(udpsocket, state) => {
+                axnet::poll_interfaces();
+                let udpsocket = udpsocket.lock();
+                let mut poll_state = if udpsocket.local_addr().is_ok() {
+                    udpsocket.poll()?
+                } else {
+                    PollState {
+                        readable: false,
+                        writable: true,
+                    }
+                };
+                poll_state.readable |= state.has_looped();
+                Ok(poll_state)
+            }
+            SocketInner::Tcp(tcpsocket) => {
+                axnet::poll_interfaces();
+                if self.poll_connect() {
+                    return Ok(PollState {
+                        readable: false,
+                        writable: false,
+                    });
+                }
+                if self.pending_error.lock().is_some() {
+                    // Linux reports `POLLERR`, which wakes up both readers
+                    // and writers.
+                    return Ok(PollState {
+                        readable: true,
+                        writable: true,
+                    });
+                }
+                let mut state = tcpsocket.lock().poll()?;
+                state.readable |= !self.tcp_peeked.lock().is_empty();
+                Ok(state)
+            }
+            SocketInner::Unix(unixsocket) => Ok(unixsocket.poll()),
+            SocketInner::Netlink(netlinksocket) => Ok(netlinksocket.poll()),
+        }
+    }
+
+    pub fn local_addr(&self) -> LinuxResult<SocketAddress> {
+        match &self.inner {
+            // Linux reports the unspecified address for unbound sockets.
+            SocketInner::Udp(udpsocket, _) => Ok(udpsocket
                 .lock()
-                .recv_from(buf)
-                .map(|res| (res.0, Some(res.1)))?),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().recv(buf).map(|res| (res, None))?),
+                .local_addr()
+                .unwrap_or((Ipv4Addr::UNSPECIFIED, 0).into())
+                .into()),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().local_addr()?.into()),
+            SocketInner::Unix(unixsocket) => Ok(unixsocket.local_addr().into()),
+            SocketInner::Netlink(netlinksocket) => Ok(netlinksocket.local_addr().into()),
         }
     }
 
-    pub fn listen(&self) -> LinuxResult {
-        match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().listen()?),
+    pub fn peer_addr(&self) -> LinuxResult<SocketAddress> {
+        match &self.inner {
+            SocketInner::Udp(_, state) => Ok(state.peer().ok_or(LinuxError::ENOTCONN)?.into()),
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().peer_addr()?.into()),
+            SocketInner::Unix(unixsocket) => Ok(unixsocket.peer_addr()?.into()),
+            // Messages always go to the kernel.
+            SocketInner::Netlink(_) => Ok(NetlinkSocketAddr { pid: 0, groups: 0 }.into()),
         }
     }
 
-    pub fn accept(&self) -> LinuxResult<TcpSocket> {
-        match self {
-            Socket::Udp(_) => Err(LinuxError::EOPNOTSUPP),
-            Socket::Tcp(tcpsocket) => Ok(tcpsocket.lock().accept()?),
+    /// Get the credentials of the peer for `SO_PEERCRED`, only known for
+    /// connected Unix sockets.
+    pub fn peer_cred(&self) -> Option<ucred> {
+        match &self.inner {
+            SocketInner::Unix(unixsocket) => unixsocket.peer_cred(),
+            _ => None,
         }
     }
 
-    impl_socket!(pub fn send(&self, buf: &[u8]) -> LinuxResult<usize>);
-    impl_socket!(pub fn poll(&self) -> LinuxResult<PollState>);
-    impl_socket!(pub fn local_addr(&self) -> LinuxResult<SocketAddr>);
-    impl_socket!(pub fn peer_addr(&self) -> LinuxResult<SocketAddr>);
-    impl_socket!(pub fn bind(&self, addr: SocketAddr) -> LinuxResult);
-    impl_socket!(pub fn connect(&self, addr: SocketAddr) -> LinuxResult);
-    impl_socket!(pub fn shutdown(&self) -> LinuxResult);
+    pub fn bind(&self, addr: SocketAddress) -> LinuxResult {
+        match &self.inner {
+            SocketInner::Udp(udpsocket, state) => {
+                let udpsocket = udpsocket.lock();
+                udpsocket.bind(inet_addr(addr)?)?;
+                state.set_port(udpsocket.local_addr()?.port());
+                Ok(())
+            }
+            SocketInner::Tcp(tcpsocket) => Ok(tcpsocket.lock().bind(inet_addr(addr)?)?),
+            SocketInner::Unix(unixsocket) => unixsocket.bind(unix_addr(addr)?),
+            SocketInner::Netlink(netlinksocket) => netlinksocket.bind(netlink_addr(addr)?),
+        }
+    }
+
+    /// Shut down the receiving side of the socket if `read` is set, and the
+    /// sending side if `write` is set.
+    pub fn shutdown(&self, read: bool, write: bool) -> LinuxResult {
+        match &self.inner {
+            // There is nothing to shut down, but Linux requires a connected
+            // socket.
+            SocketInner::Udp(..) => {
+                self.peer_addr()?;
+            }
+            SocketInner::Tcp(tcpsocket) => {
+                let tcpsocket = tcpsocket.lock();
+                if tcpsocket.peer_addr().is_ok() {
+                    // axnet sends a FIN and keeps receiving, a shut down
+                    // receiving side is only enforced here.
+                    if write {
+                        tcpsocket.shutdown()?;
+                    }
+                } else if tcpsocket.local_addr().is_ok() {
+                    // A listening socket stops accepting connections.
+                    if read {
+                        tcpsocket.shutdown()?;
+                    }
+                } else {
+                    return Err(LinuxError::ENOTCONN);
+                }
+            }
+            SocketInner::Unix(unixsocket) => unixsocket.shutdown(read, write),
+            SocketInner::Netlink(_) => return Err(LinuxError::EOPNOTSUPP),
+        }
+        if read {
+            self.shut_rd.store(true, Ordering::Release);
+        }
+        if write {
+            self.shut_wr.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Get which directions of the socket are shut down.
+    pub fn shutdown_state(&self) -> ShutdownState {
+        let mut state = ShutdownState {
+            read: self.shut_rd.load(Ordering::Acquire),
+            write: self.shut_wr.load(Ordering::Acquire),
+        };
+        match &self.inner {
+            SocketInner::Tcp(tcpsocket) => {
+                axnet::poll_interfaces();
+                state.read |= self.tcp_peer_closed(tcpsocket);
+            }
+            SocketInner::Unix(unixsocket) => {
+                state.read |= unixsocket.read_closed();
+                state.write |= unixsocket.write_closed();
+            }
+            _ => {}
+        }
+        state
+    }
+}
+
+/// Bind a UDP socket to an ephemeral port if it is not bound yet, as Linux
+/// does on the first send or `connect`, and return its local address.
+fn udp_autobind(
+    udpsocket: &Mutex<UdpSocket>,
+    state: &UdpState,
+    dst: &SocketAddr,
+) -> LinuxResult<SocketAddr> {
+    let udpsocket = udpsocket.lock();
+    if let Ok(addr) = udpsocket.local_addr() {
+        return Ok(addr);
+    }
+    let any = match dst {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    udpsocket.bind((any, 0).into())?;
+    let addr = udpsocket.local_addr()?;
+    state.set_port(addr.port());
+    Ok(addr)
+}
+
+/// Get the source address of a datagram sent from `local_addr`, filling in
+/// the address of the interface of the default route if it is unspecified.
+///
+/// Without a known interface of the default route, the loopback address is
+/// used.
+fn udp_source(local_addr: SocketAddr) -> SocketAddr {
+    if !local_addr.ip().is_unspecified() {
+        return local_addr;
+    }
+    let ip = match local_addr {
+        SocketAddr::V4(_) => interfaces()
+            .iter()
+            .find(|iface| iface.gateway.is_some())
+            .map_or(Ipv4Addr::LOCALHOST, |iface| iface.addr)
+            .into(),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+    };
+    SocketAddr::new(ip, local_addr.port())
 }
 
 impl FileLike for Socket {
@@ -102,10 +1154,42 @@ impl FileLike for Socket {
     }
 
     fn set_nonblocking(&self, nonblock: bool) -> LinuxResult {
-        match self {
-            Socket::Udp(udpsocket) => udpsocket.lock().set_nonblocking(nonblock),
-            Socket::Tcp(tcpsocket) => tcpsocket.lock().set_nonblocking(nonblock),
-        }
+        self.nonblocking.store(nonblock, Ordering::Release);
         Ok(())
     }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, events: u32) -> bool {
+        match &self.inner {
+            SocketInner::Unix(unixsocket) => {
+                unixsocket.register(waiter, events & POLLIN != 0, events & POLLOUT != 0)
+            }
+            SocketInner::Udp(..) | SocketInner::Tcp(_) => {
+                register_net_waiter(waiter);
+                true
+            }
+            // Replies are queued while sending.
+            SocketInner::Netlink(_) => false,
+        }
+    }
+
+    fn event_seq(&self, events: u32) -> Option<u64> {
+        match &self.inner {
+            SocketInner::Unix(unixsocket) => {
+                unixsocket.event_seq(events & POLLIN != 0, events & POLLOUT != 0)
+            }
+            // TCP and UDP waiters are woken up on every pass of the poller,
+            // whether the socket changed or not, so edges are counted instead.
+            SocketInner::Udp(..) | SocketInner::Tcp(_) => {
+                Some(self.edges.sample(self.poll().ok()?))
+            }
+            SocketInner::Netlink(_) => None,
+        }
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::ENOTSOCK)
+    }
 }
@@ -0,0 +1,469 @@
+use core::{
+    mem::size_of,
+    net::Ipv4Addr,
+    ptr, slice,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use alloc::{
+    collections::{btree_set::BTreeSet, vec_deque::VecDeque},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{
+    net::{AF_INET, AF_UNSPEC, IF_OPER_UNKNOWN, IF_OPER_UP},
+    netlink::{
+        IFA_ADDRESS, IFA_BROADCAST, IFA_F_PERMANENT, IFA_LABEL, IFA_LOCAL, IFLA_ADDRESS,
+        IFLA_BROADCAST, IFLA_IFNAME, IFLA_MTU, IFLA_OPERSTATE, IFLA_TXQLEN, NLM_F_ACK, NLM_F_DUMP,
+        NLM_F_MULTI, NLM_F_REPLACE, NLM_F_REQUEST, NLMSG_ALIGNTO, NLMSG_DONE, NLMSG_ERROR,
+        RTM_BASE, RTM_GETADDR, RTM_GETLINK, RTM_GETROUTE, RTM_NEWADDR, RTM_NEWLINK, RTM_NEWROUTE,
+        RTN_UNICAST, RTPROT_BOOT, RTPROT_KERNEL, ifaddrmsg, ifinfomsg, nlmsgerr, nlmsghdr,
+        rt_class_t, rt_scope_t, rtattr, rtattr_type_t, rtmsg,
+    },
+};
+
+use crate::{
+    netif::{NetInterface, interfaces},
+    socket::NetlinkSocketAddr,
+};
+
+/// Length of the transmit queue reported for every interface.
+const TX_QUEUE_LEN: u32 = 1000;
+
+/// Port IDs of the bound netlink sockets.
+static NETLINK_PORTS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+fn nlmsg_align(len: usize) -> usize {
+    let align = NLMSG_ALIGNTO as usize;
+    (len + align - 1) & !(align - 1)
+}
+
+fn as_bytes<T: Copy>(value: &T) -> &[u8] {
+    // SAFETY: `T` is plain data without padding.
+    unsafe { slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) }
+}
+
+/// Read a `T` from the start of `buf`, if it is long enough.
+fn read_struct<T: Copy>(buf: &[u8]) -> Option<T> {
+    if buf.len() < size_of::<T>() {
+        return None;
+    }
+    // SAFETY: `buf` holds at least `size_of::<T>()` bytes, and `T` is plain
+    // data.
+    Some(unsafe { ptr::read_unaligned(buf.as_ptr() as *const T) })
+}
+
+/// A netlink message being built in reply to a request.
+struct MessageBuilder {
+    buf: Vec<u8>,
+}
+
+impl MessageBuilder {
+    fn new(ty: u32, flags: u32, req: &nlmsghdr, port_id: u32) -> Self {
+        let mut msg = Self { buf: Vec::new() };
+        msg.push(&nlmsghdr {
+            nlmsg_len: 0,
+            nlmsg_type: ty as u16,
+            nlmsg_flags: flags as u16,
+            nlmsg_seq: req.nlmsg_seq,
+            nlmsg_pid: port_id,
+        });
+        msg
+    }
+
+    /// Append the fixed part of the message.
+    fn push<T: Copy>(&mut self, value: &T) {
+        self.buf.extend_from_slice(as_bytes(value));
+        self.buf.resize(nlmsg_align(self.buf.len()), 0);
+    }
+
+    /// Append an attribute.
+    fn attr(&mut self, ty: u32, data: &[u8]) {
+        let len = size_of::<rtattr>() + data.len();
+        self.push(&rtattr {
+            rta_len: len as u16,
+            rta_type: ty as u16,
+        });
+        self.buf.extend_from_slice(data);
+        self.buf.resize(nlmsg_align(self.buf.len()), 0);
+    }
+
+    /// Append a NUL-terminated string attribute.
+    fn attr_str(&mut self, ty: u32, s: &str) {
+        let mut data = Vec::from(s.as_bytes());
+        data.push(0);
+        self.attr(ty, &data);
+    }
+
+    /// Fill in the length of the message and append it to `out`.
+    fn finish(mut self, out: &mut Vec<u8>) {
+        let len = self.buf.len() as u32;
+        self.buf[..size_of::<u32>()].copy_from_slice(&len.to_ne_bytes());
+        out.append(&mut self.buf);
+    }
+}
+
+fn link_message(iface: &NetInterface, req: &nlmsghdr, port_id: u32, flags: u32) -> MessageBuilder {
+    let mut msg = MessageBuilder::new(RTM_NEWLINK as u32, flags, req, port_id);
+    msg.push(&ifinfomsg {
+        ifi_family: AF_UNSPEC as _,
+        __ifi_pad: 0,
+        ifi_type: iface.hw_type,
+        ifi_index: iface.index,
+        ifi_flags: iface.flags,
+        ifi_change: 0,
+    });
+    msg.attr_str(IFLA_IFNAME as u32, iface.name);
+    msg.attr(IFLA_MTU as u32, &(iface.mtu as u32).to_ne_bytes());
+    msg.attr(IFLA_TXQLEN as u32, &TX_QUEUE_LEN.to_ne_bytes());
+    msg.attr(IFLA_ADDRESS as u32, &iface.hw_addr);
+    // Linux reports the loopback device with an unknown operational state.
+    if iface.is_loopback() {
+        msg.attr(IFLA_BROADCAST as u32, &[0; 6]);
+        msg.attr(IFLA_OPERSTATE as u32, &[IF_OPER_UNKNOWN as u8]);
+    } else {
+        msg.attr(IFLA_BROADCAST as u32, &[0xff; 6]);
+        msg.attr(IFLA_OPERSTATE as u32, &[IF_OPER_UP as u8]);
+    }
+    msg
+}
+
+fn addr_message(iface: &NetInterface, req: &nlmsghdr, port_id: u32) -> MessageBuilder {
+    let scope = if iface.is_loopback() {
+        rt_scope_t::RT_SCOPE_HOST
+    } else {
+        rt_scope_t::RT_SCOPE_UNIVERSE
+    };
+    let mut msg = MessageBuilder::new(RTM_NEWADDR as u32, NLM_F_MULTI, req, port_id);
+    msg.push(&ifaddrmsg {
+        ifa_family: AF_INET as _,
+        ifa_prefixlen: iface.prefix_len,
+        ifa_flags: IFA_F_PERMANENT as _,
+        ifa_scope: scope as _,
+        ifa_index: iface.index as _,
+    });
+    msg.attr(IFA_ADDRESS as u32, &iface.addr.octets());
+    msg.attr(IFA_LOCAL as u32, &iface.addr.octets());
+    if !iface.is_loopback() {
+        msg.attr(IFA_BROADCAST as u32, &iface.broadcast().octets());
+    }
+    msg.attr_str(IFA_LABEL as u32, iface.name);
+    msg
+}
+
+/// Build a route of the main table through `iface`, to `dst` if the route
+/// is not the default route.
+fn route_message(
+    iface: &NetInterface,
+    dst: Option<(Ipv4Addr, u8)>,
+    gateway: Option<Ipv4Addr>,
+    req: &nlmsghdr,
+    port_id: u32,
+) -> MessageBuilder {
+    let (dst_len, protocol, scope) = match dst {
+        Some((_, prefix_len)) => (prefix_len, RTPROT_KERNEL, rt_scope_t::RT_SCOPE_LINK),
+        None => (0, RTPROT_BOOT, rt_scope_t::RT_SCOPE_UNIVERSE),
+    };
+    let table = rt_class_t::RT_TABLE_MAIN as u32;
+    let mut msg = MessageBuilder::new(RTM_NEWROUTE as u32, NLM_F_MULTI, req, port_id);
+    msg.push(&rtmsg {
+        rtm_family: AF_INET as _,
+        rtm_dst_len: dst_len,
+        rtm_src_len: 0,
+        rtm_tos: 0,
+        rtm_table: table as _,
+        rtm_protocol: protocol as _,
+        rtm_scope: scope as _,
+        rtm_type: RTN_UNICAST as _,
+        rtm_flags: 0,
+    });
+    msg.attr(rtattr_type_t::RTA_TABLE as u32, &table.to_ne_bytes());
+    if let Some((dst, _)) = dst {
+        msg.attr(rtattr_type_t::RTA_DST as u32, &dst.octets());
+        msg.attr(rtattr_type_t::RTA_PREFSRC as u32, &iface.addr.octets());
+    }
+    if let Some(gateway) = gateway {
+        msg.attr(rtattr_type_t::RTA_GATEWAY as u32, &gateway.octets());
+    }
+    msg.attr(
+        rtattr_type_t::RTA_OIF as u32,
+        &(iface.index as u32).to_ne_bytes(),
+    );
+    msg
+}
+
+/// Handle `RTM_NEWADDR`.
+///
+/// axnet cannot reconfigure interfaces, so only addresses that are
+/// already assigned are accepted.
+fn new_addr(req: &nlmsghdr, payload: &[u8], ifaces: &[NetInterface]) -> LinuxResult<bool> {
+    let info = read_struct::<ifaddrmsg>(payload).ok_or(LinuxError::EINVAL)?;
+    let iface = ifaces
+        .iter()
+        .find(|iface| iface.index == info.ifa_index as i32)
+        .ok_or(LinuxError::ENODEV)?;
+
+    let mut addr = None;
+    let mut attrs = &payload[nlmsg_align(size_of::<ifaddrmsg>()).min(payload.len())..];
+    while let Some(attr) = read_struct::<rtattr>(attrs) {
+        let len = attr.rta_len as usize;
+        if len < size_of::<rtattr>() || len > attrs.len() {
+            return Err(LinuxError::EINVAL);
+        }
+        let data = &attrs[size_of::<rtattr>()..len];
+        if attr.rta_type as u32 == IFA_LOCAL as u32
+            || (attr.rta_type as u32 == IFA_ADDRESS as u32 && addr.is_none())
+        {
+            let octets: [u8; 4] = data.try_into().map_err(|_| LinuxError::EINVAL)?;
+            addr = Some(Ipv4Addr::from(octets));
+        }
+        attrs = &attrs[nlmsg_align(len).min(attrs.len())..];
+    }
+
+    if info.ifa_family as u32 == AF_INET
+        && addr == Some(iface.addr)
+        && info.ifa_prefixlen == iface.prefix_len
+    {
+        return if req.nlmsg_flags as u32 & NLM_F_REPLACE != 0 {
+            Ok(false)
+        } else {
+            Err(LinuxError::EEXIST)
+        };
+    }
+    warn!("netlink: cannot change addresses of {}", iface.name);
+    Err(LinuxError::EOPNOTSUPP)
+}
+
+/// A `NETLINK_ROUTE` socket.
+///
+/// Requests are answered synchronously from the interface table in
+/// [`crate::netif`], the replies are queued until they are received.
+/// Multicast notifications are never sent, since the interfaces do not
+/// change.
+pub struct NetlinkSocket {
+    ty: u32,
+    /// The port ID, 0 if the socket is not bound yet.
+    port_id: AtomicU32,
+    groups: AtomicU32,
+    queue: Mutex<VecDeque<Vec<u8>>>,
+}
+
+impl NetlinkSocket {
+    /// Create a new netlink socket of type `SOCK_RAW` or `SOCK_DGRAM`.
+    pub fn new(ty: u32) -> Self {
+        Self {
+            ty,
+            port_id: AtomicU32::new(0),
+            groups: AtomicU32::new(0),
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn socket_type(&self) -> u32 {
+        self.ty
+    }
+
+    pub fn local_addr(&self) -> NetlinkSocketAddr {
+        NetlinkSocketAddr {
+            pid: self.port_id.load(Ordering::Acquire),
+            groups: self.groups.load(Ordering::Acquire),
+        }
+    }
+
+    /// Bind the socket to `addr`.
+    ///
+    /// A port ID of 0 picks one automatically, the process ID if it is still
+    /// free.
+    pub fn bind(&self, addr: NetlinkSocketAddr) -> LinuxResult {
+        self.groups.store(addr.groups, Ordering::Release);
+        let port_id = self.port_id.load(Ordering::Acquire);
+        if port_id != 0 {
+            return if addr.pid == 0 || addr.pid == port_id {
+                Ok(())
+            } else {
+                Err(LinuxError::EINVAL)
+            };
+        }
+        let port_id = if addr.pid != 0 {
+            if !NETLINK_PORTS.lock().insert(addr.pid) {
+                return Err(LinuxError::EADDRINUSE);
+            }
+            addr.pid
+        } else {
+            Self::autobind()
+        };
+        self.port_id.store(port_id, Ordering::Release);
+        Ok(())
+    }
+
+    fn autobind() -> u32 {
+        // Like Linux, fall back to negative port IDs that cannot collide
+        // with process IDs.
+        static NEXT_AUTOBIND: AtomicU32 = AtomicU32::new(-4097_i32 as u32);
+
+        let mut ports = NETLINK_PORTS.lock();
+        let pid = current().task_ext().thread.process().pid();
+        if ports.insert(pid) {
+            return pid;
+        }
+        loop {
+            let port_id = NEXT_AUTOBIND.fetch_sub(1, Ordering::Relaxed);
+            if ports.insert(port_id) {
+                return port_id;
+            }
+        }
+    }
+
+    /// Get the port ID of the socket, binding it first if needed.
+    fn bound_port_id(&self) -> LinuxResult<u32> {
+        match self.port_id.load(Ordering::Acquire) {
+            0 => {
+                self.bind(self.local_addr())?;
+                Ok(self.port_id.load(Ordering::Acquire))
+            }
+            port_id => Ok(port_id),
+        }
+    }
+
+    /// Check the destination of messages, only the kernel can be reached.
+    pub fn connect(&self, addr: &NetlinkSocketAddr) -> LinuxResult {
+        if addr.pid != 0 {
+            return Err(LinuxError::ECONNREFUSED);
+        }
+        self.bound_port_id()?;
+        Ok(())
+    }
+
+    /// Send the requests in `buf` to the kernel and queue the replies.
+    pub fn send(&self, buf: &[u8], addr: Option<&NetlinkSocketAddr>) -> LinuxResult<usize> {
+        if let Some(addr) = addr {
+            self.connect(addr)?;
+        }
+        let port_id = self.bound_port_id()?;
+
+        let mut reply = Vec::new();
+        let mut rest = buf;
+        while let Some(req) = read_struct::<nlmsghdr>(rest) {
+            let len = req.nlmsg_len as usize;
+            if len < size_of::<nlmsghdr>() || len > rest.len() {
+                break;
+            }
+            self.handle_request(&req, &rest[size_of::<nlmsghdr>()..len], port_id, &mut reply);
+            rest = &rest[nlmsg_align(len).min(rest.len())..];
+        }
+        if !reply.is_empty() {
+            self.queue.lock().push_back(reply);
+        }
+        Ok(buf.len())
+    }
+
+    fn handle_request(&self, req: &nlmsghdr, payload: &[u8], port_id: u32, reply: &mut Vec<u8>) {
+        let flags = req.nlmsg_flags as u32;
+        // Control messages and messages that are not requests are ignored.
+        if flags & NLM_F_REQUEST == 0 || (req.nlmsg_type as u32) < RTM_BASE as u32 {
+            return;
+        }
+        debug!(
+            "netlink request <= type: {}, flags: {:#x}, seq: {}",
+            req.nlmsg_type, flags, req.nlmsg_seq
+        );
+        let ifaces = interfaces();
+        let dump = flags & NLM_F_DUMP != 0;
+        let res = match req.nlmsg_type as u32 {
+            ty if ty == RTM_GETLINK as u32 && dump => {
+                for iface in &ifaces {
+                    link_message(iface, req, port_id, NLM_F_MULTI).finish(reply);
+                }
+                Ok(true)
+            }
+            ty if ty == RTM_GETLINK as u32 => read_struct::<ifinfomsg>(payload)
+                .ok_or(LinuxError::EINVAL)
+                .and_then(|info| {
+                    ifaces
+                        .iter()
+                        .find(|iface| iface.index == info.ifi_index)
+                        .ok_or(LinuxError::ENODEV)
+                })
+                .map(|iface| {
+                    link_message(iface, req, port_id, 0).finish(reply);
+                    false
+                }),
+            ty if ty == RTM_GETADDR as u32 && dump => {
+                let family = payload.first().map_or(AF_UNSPEC, |&family| family as u32);
+                if family == AF_UNSPEC || family == AF_INET {
+                    for iface in &ifaces {
+                        addr_message(iface, req, port_id).finish(reply);
+                    }
+                }
+                Ok(true)
+            }
+            ty if ty == RTM_GETROUTE as u32 && dump => {
+                let family = payload.first().map_or(AF_UNSPEC, |&family| family as u32);
+                if family == AF_UNSPEC || family == AF_INET {
+                    for iface in ifaces.iter().filter(|iface| !iface.is_loopback()) {
+                        if let Some(gateway) = iface.gateway {
+                            route_message(iface, None, Some(gateway), req, port_id).finish(reply);
+                        }
+                        let dst = Some((iface.network(), iface.prefix_len));
+                        route_message(iface, dst, None, req, port_id).finish(reply);
+                    }
+                }
+                Ok(true)
+            }
+            ty if ty == RTM_NEWADDR as u32 => new_addr(req, payload, &ifaces),
+            ty => {
+                warn!("netlink: unsupported request type: {}", ty);
+                Err(LinuxError::EOPNOTSUPP)
+            }
+        };
+
+        match res {
+            Ok(true) => {
+                let mut done = MessageBuilder::new(NLMSG_DONE, NLM_F_MULTI, req, port_id);
+                done.push(&0_i32);
+                done.finish(reply);
+            }
+            Ok(false) if flags & NLM_F_ACK == 0 => {}
+            res => {
+                let mut err = MessageBuilder::new(NLMSG_ERROR, 0, req, port_id);
+                err.push(&nlmsgerr {
+                    error: res.err().map_or(0, |err| -err.code()),
+                    msg: *req,
+                });
+                err.finish(reply);
+            }
+        }
+    }
+
+    /// Receive a reply into `buf`, returning its full length.
+    pub fn recv(&self, buf: &mut [u8], peek: bool) -> LinuxResult<usize> {
+        let mut queue = self.queue.lock();
+        let reply = queue.front().ok_or(LinuxError::EAGAIN)?;
+        let len = reply.len();
+        let copied = len.min(buf.len());
+        buf[..copied].copy_from_slice(&reply[..copied]);
+        if !peek {
+            queue.pop_front();
+        }
+        Ok(len)
+    }
+
+    pub fn poll(&self) -> PollState {
+        PollState {
+            readable: !self.queue.lock().is_empty(),
+            writable: true,
+        }
+    }
+}
+
+impl Drop for NetlinkSocket {
+    fn drop(&mut self) {
+        let port_id = *self.port_id.get_mut();
+        if port_id != 0 {
+            NETLINK_PORTS.lock().remove(&port_id);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alloc::sync::Arc;
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axprocess::Process;
+
+use super::{FileLike, Kstat, PollSet, PollWaiter};
+
+/// Waiters for processes referred to by pidfds to exit.
+static PIDFD_READERS: PollSet = PollSet::new();
+
+/// Wake up the waiters of pidfds, after a process has exited.
+pub fn notify_pidfds() {
+    PIDFD_READERS.wake();
+}
+
+/// A file referring to a process, created by `pidfd_open` or `clone` with
+/// `CLONE_PIDFD`.
+///
+/// The file becomes readable when the process exits, while reading it fails.
+pub struct PidFd {
+    process: Arc<Process>,
+    nonblocking: AtomicBool,
+}
+
+impl PidFd {
+    pub fn new(process: Arc<Process>) -> Self {
+        Self {
+            process,
+            nonblocking: AtomicBool::new(false),
+        }
+    }
+
+    /// Get the process the pidfd refers to.
+    pub fn process(&self) -> &Arc<Process> {
+        &self.process
+    }
+
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+}
+
+impl FileLike for PidFd {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: 0o600u32, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: self.process.is_zombie(),
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, _events: u32) -> bool {
+        PIDFD_READERS.register(waiter);
+        true
+    }
+
+    fn event_seq(&self, _events: u32) -> Option<u64> {
+        Some(PIDFD_READERS.seq())
+    }
+}
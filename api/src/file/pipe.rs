@@ -1,94 +1,214 @@
-use core::any::Any;
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use alloc::sync::Arc;
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, sync::Arc, vec};
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
 use axsync::Mutex;
 use linux_raw_sys::general::S_IFIFO;
+use memory_addr::PAGE_SIZE_4K;
 
-use super::{FileLike, Kstat};
+use super::{FileLike, Kstat, PollSet, PollWaiter, wait_event};
+use crate::signal::send_sigpipe;
 
-#[derive(Copy, Clone, PartialEq)]
-enum RingBufferStatus {
-    Full,
-    Empty,
-    Normal,
-}
+/// Size of a page of the pipe buffer.
+const PAGE_SIZE: usize = PAGE_SIZE_4K;
+/// Default size of a pipe, 64 KiB as on Linux.
+const DEFAULT_PIPE_SIZE: usize = 16 * PAGE_SIZE;
+/// Maximum size of a pipe, the default of `fs.pipe-max-size` on Linux.
+const MAX_PIPE_SIZE: usize = 1024 * 1024;
+/// Writes of at most this many bytes are not interleaved with other writes.
+const PIPE_BUF: usize = linux_raw_sys::general::PIPE_BUF as usize;
 
-const RING_BUFFER_SIZE: usize = 256;
+/// Raise `SIGPIPE` for a write to a pipe whose read end is closed, and get
+/// the error the write fails with.
+fn broken_pipe() -> LinuxError {
+    send_sigpipe();
+    LinuxError::EPIPE
+}
 
-struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
-    head: usize,
-    tail: usize,
-    status: RingBufferStatus,
+/// A page of data in a pipe.
+struct PipePage {
+    data: Box<[u8]>,
+    start: usize,
+    end: usize,
+    /// Whether the page holds a single packet written in `O_DIRECT` mode.
+    packet: bool,
 }
 
-impl PipeRingBuffer {
-    const fn new() -> Self {
+impl PipePage {
+    fn new(packet: bool) -> Self {
         Self {
-            arr: [0; RING_BUFFER_SIZE],
-            head: 0,
-            tail: 0,
-            status: RingBufferStatus::Empty,
+            data: vec![0; PAGE_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            packet,
         }
     }
 
-    fn write_byte(&mut self, byte: u8) {
-        self.status = RingBufferStatus::Normal;
-        self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::Full;
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// The buffer of a pipe, made of at most `max_pages` pages allocated as data
+/// is written.
+///
+/// Like on Linux, writes are merged into the last page unless it holds a
+/// packet, so the pipe may hold less than its size when writes come in
+/// packets or small chunks.
+struct PipeBuffer {
+    pages: VecDeque<PipePage>,
+    max_pages: usize,
+    /// Number of bytes in the buffer.
+    len: usize,
+}
+
+impl PipeBuffer {
+    fn new() -> Self {
+        Self {
+            pages: VecDeque::new(),
+            max_pages: DEFAULT_PIPE_SIZE / PAGE_SIZE,
+            len: 0,
         }
     }
 
-    fn read_byte(&mut self) -> u8 {
-        self.status = RingBufferStatus::Normal;
-        let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
-        if self.head == self.tail {
-            self.status = RingBufferStatus::Empty;
+    fn size(&self) -> usize {
+        self.max_pages * PAGE_SIZE
+    }
+
+    /// Whether all pages are in use.
+    fn is_full(&self) -> bool {
+        self.pages.len() >= self.max_pages
+    }
+
+    /// Get the number of bytes a write in `packet` mode can add.
+    fn room(&self, packet: bool) -> usize {
+        let free_pages = self.max_pages.saturating_sub(self.pages.len());
+        let tail_room = match self.pages.back() {
+            Some(page) if !packet && !page.packet => PAGE_SIZE - page.end,
+            _ => 0,
+        };
+        free_pages * PAGE_SIZE + tail_room
+    }
+
+    /// Write as much of `buf` as fits, returning the written length.
+    ///
+    /// In `packet` mode, each page written holds a packet of at most
+    /// `PIPE_BUF` bytes.
+    fn write(&mut self, buf: &[u8], packet: bool) -> usize {
+        let mut written = 0;
+        while written < buf.len() {
+            let page = match self.pages.back_mut() {
+                Some(page) if !packet && !page.packet && page.end < PAGE_SIZE => page,
+                _ if self.is_full() => break,
+                _ => {
+                    self.pages.push_back(PipePage::new(packet));
+                    self.pages.back_mut().unwrap()
+                }
+            };
+            let len = (PAGE_SIZE - page.end).min(buf.len() - written);
+            page.data[page.end..page.end + len].copy_from_slice(&buf[written..written + len]);
+            page.end += len;
+            written += len;
         }
-        c
+        self.len += written;
+        written
     }
 
-    /// Get the length of remaining data in the buffer
-    const fn available_read(&self) -> usize {
-        if matches!(self.status, RingBufferStatus::Empty) {
-            0
-        } else if self.tail > self.head {
-            self.tail - self.head
-        } else {
-            self.tail + RING_BUFFER_SIZE - self.head
+    /// Copy the data at the head of the buffer into `buf`, up to the end of
+    /// the first packet, and consume it if `consume` is set.
+    ///
+    /// Reading part of a packet discards the rest of it.
+    fn read(&mut self, buf: &mut [u8], consume: bool) -> usize {
+        let mut read = 0;
+        let mut index = 0;
+        while read < buf.len() {
+            let Some(page) = self.pages.get(index) else {
+                break;
+            };
+            let len = page.len().min(buf.len() - read);
+            buf[read..read + len].copy_from_slice(&page.data[page.start..page.start + len]);
+            read += len;
+            let packet = page.packet;
+            if consume {
+                let page = &mut self.pages[index];
+                if packet || len == page.len() {
+                    self.len -= page.len();
+                    self.pages.pop_front();
+                } else {
+                    page.start += len;
+                    self.len -= len;
+                }
+            } else {
+                index += 1;
+            }
+            if packet {
+                break;
+            }
         }
+        read
     }
 
-    /// Get the length of remaining space in the buffer
-    const fn available_write(&self) -> usize {
-        if matches!(self.status, RingBufferStatus::Full) {
-            0
-        } else {
-            RING_BUFFER_SIZE - self.available_read()
+    /// Discard `len` bytes at the head of the buffer.
+    fn consume(&mut self, mut len: usize) {
+        while len > 0 {
+            let Some(page) = self.pages.front_mut() else {
+                break;
+            };
+            let consumed = len.min(page.len());
+            page.start += consumed;
+            self.len -= consumed;
+            len -= consumed;
+            if page.start == page.end {
+                self.pages.pop_front();
+            }
         }
     }
 }
 
+/// The state shared by both ends of a pipe.
+struct PipeShared {
+    buffer: Mutex<PipeBuffer>,
+    read_closed: AtomicBool,
+    write_closed: AtomicBool,
+    /// Waiters for data to read.
+    readers: PollSet,
+    /// Waiters for room to write.
+    writers: PollSet,
+}
+
+/// An end of a pipe.
 pub struct Pipe {
     readable: bool,
-    buffer: Arc<Mutex<PipeRingBuffer>>,
+    shared: Arc<PipeShared>,
+    nonblocking: AtomicBool,
+    /// Whether writes are made in packets, as with `O_DIRECT`.
+    packet: AtomicBool,
 }
 
 impl Pipe {
     pub fn new() -> (Pipe, Pipe) {
-        let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
+        let shared = Arc::new(PipeShared {
+            buffer: Mutex::new(PipeBuffer::new()),
+            read_closed: AtomicBool::new(false),
+            write_closed: AtomicBool::new(false),
+            readers: PollSet::new(),
+            writers: PollSet::new(),
+        });
         let read_end = Pipe {
             readable: true,
-            buffer: buffer.clone(),
+            shared: shared.clone(),
+            nonblocking: AtomicBool::new(false),
+            packet: AtomicBool::new(false),
         };
         let write_end = Pipe {
             readable: false,
-            buffer,
+            shared,
+            nonblocking: AtomicBool::new(false),
+            packet: AtomicBool::new(false),
         };
         (read_end, write_end)
     }
@@ -101,13 +221,74 @@ impl Pipe {
         !self.readable
     }
 
+    /// Whether the other end of the pipe is closed.
     pub fn closed(&self) -> bool {
-        Arc::strong_count(&self.buffer) == 1
+        if self.readable {
+            self.shared.write_closed.load(Ordering::Acquire)
+        } else {
+            self.shared.read_closed.load(Ordering::Acquire)
+        }
     }
-}
 
-impl FileLike for Pipe {
-    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+    /// Whether this end of the pipe is in non-blocking mode.
+    pub fn is_nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    /// Set whether writes to this end are made in packets, as with
+    /// `O_DIRECT`.
+    ///
+    /// Each write of at most `PIPE_BUF` bytes is then read by a single read,
+    /// and larger writes are split into packets of `PIPE_BUF` bytes.
+    pub fn set_packet_mode(&self, packet: bool) {
+        self.packet.store(packet, Ordering::Release);
+    }
+
+    fn is_packet_mode(&self) -> bool {
+        self.packet.load(Ordering::Acquire)
+    }
+
+    /// Get the number of bytes that can be read from the pipe.
+    pub fn available_read(&self) -> usize {
+        self.shared.buffer.lock().len
+    }
+
+    /// Get the size of the pipe.
+    pub fn size(&self) -> usize {
+        self.shared.buffer.lock().size()
+    }
+
+    /// Resize the pipe to hold at least `size` bytes, returning the new
+    /// size.
+    ///
+    /// Like on Linux, the size is rounded up to a power of two number of
+    /// pages, and fails with `EBUSY` if the data in the pipe would not fit.
+    pub fn set_size(&self, size: usize) -> LinuxResult<usize> {
+        if size > MAX_PIPE_SIZE {
+            return Err(LinuxError::EPERM);
+        }
+        let max_pages = size.div_ceil(PAGE_SIZE).max(1).next_power_of_two();
+        let mut buffer = self.shared.buffer.lock();
+        if buffer.pages.len() > max_pages {
+            return Err(LinuxError::EBUSY);
+        }
+        buffer.max_pages = max_pages;
+        drop(buffer);
+        self.shared.writers.wake();
+        Ok(max_pages * PAGE_SIZE)
+    }
+
+    /// Whether `other` is an end of the same pipe.
+    pub fn same_pipe(&self, other: &Pipe) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    /// Read data into `buf`, or only copy it without consuming it if `peek`
+    /// is set.
+    ///
+    /// Waits for data unless `nonblocking` is set, and returns 0 once the
+    /// write end is closed. A signal interrupts the wait with `EINTR`.
+    pub fn read_data(&self, buf: &mut [u8], peek: bool, nonblocking: bool) -> LinuxResult<usize> {
         if !self.readable() {
             return Err(LinuxError::EPERM);
         }
@@ -115,60 +296,153 @@ impl FileLike for Pipe {
             return Ok(0);
         }
 
-        loop {
-            let mut ring_buffer = self.buffer.lock();
-            let read_size = ring_buffer.available_read().min(buf.len());
-            if read_size == 0 {
+        let read = wait_event(None, true, |waiter| {
+            self.shared.readers.register(waiter);
+            let mut buffer = self.shared.buffer.lock();
+            if buffer.len == 0 {
                 if self.closed() {
-                    return Ok(0);
+                    return Ok(Some(0));
+                }
+                if nonblocking {
+                    return Err(LinuxError::EAGAIN);
                 }
-                drop(ring_buffer);
                 // Data not ready, wait for write end
-                axtask::yield_now(); // TODO: use synconize primitive
-                continue;
-            }
-            for c in buf.iter_mut().take(read_size) {
-                *c = ring_buffer.read_byte();
+                return Ok(None);
             }
-            return Ok(read_size);
+            Ok(Some(buffer.read(buf, !peek)))
+        })?;
+        if !peek {
+            self.shared.writers.wake();
         }
+        Ok(read.unwrap_or_default())
     }
 
-    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+    /// Discard `len` bytes of data previously peeked by
+    /// [`read_data`](Self::read_data).
+    pub fn consume(&self, len: usize) {
+        self.shared.buffer.lock().consume(len);
+        self.shared.writers.wake();
+    }
+
+    /// Write data from `buf`.
+    ///
+    /// Waits until everything is written unless `nonblocking` is set, in
+    /// which case only what fits is written, and `EAGAIN` is returned if
+    /// nothing does. Writes of at most `PIPE_BUF` bytes are atomic: they wait
+    /// until the whole data fits. A signal interrupts the wait with `EINTR`,
+    /// or with the length written so far if part of the data is written.
+    ///
+    /// Writing once the read end is closed fails with `EPIPE` and raises
+    /// `SIGPIPE`, unless part of the data has been written already.
+    pub fn write_data(&self, buf: &[u8], nonblocking: bool) -> LinuxResult<usize> {
         if !self.writable() {
             return Err(LinuxError::EPERM);
         }
-        if self.closed() {
-            return Err(LinuxError::EPIPE);
-        }
+        // Empty writes succeed even without readers, as on Linux.
         if buf.is_empty() {
             return Ok(0);
         }
+        if self.closed() {
+            return Err(broken_pipe());
+        }
 
-        let mut write_size = 0usize;
-        let total_len = buf.len();
-        loop {
-            let mut ring_buffer = self.buffer.lock();
-            let loop_write = ring_buffer.available_write();
-            if loop_write == 0 {
-                if self.closed() {
-                    return Ok(write_size);
+        let packet = self.is_packet_mode();
+        let atomic = buf.len() <= PIPE_BUF;
+        let mut written = 0;
+        let res = wait_event(None, true, |waiter| {
+            self.shared.writers.register(waiter);
+            if self.closed() {
+                return if written > 0 {
+                    Ok(Some(written))
+                } else {
+                    Err(broken_pipe())
+                };
+            }
+            let mut buffer = self.shared.buffer.lock();
+            let room = buffer.room(packet);
+            if room == 0 || (atomic && room < buf.len()) {
+                if nonblocking {
+                    return if written > 0 {
+                        Ok(Some(written))
+                    } else {
+                        Err(LinuxError::EAGAIN)
+                    };
                 }
-                drop(ring_buffer);
                 // Buffer is full, wait for read end to consume
-                axtask::yield_now(); // TODO: use synconize primitive
-                continue;
+                return Ok(None);
             }
-            for _ in 0..loop_write {
-                if write_size == total_len {
-                    return Ok(write_size);
+            if packet {
+                // Split the data into packets of at most `PIPE_BUF` bytes.
+                for chunk in buf[written..].chunks(PIPE_BUF) {
+                    let len = buffer.write(chunk, true);
+                    written += len;
+                    if len < chunk.len() {
+                        break;
+                    }
                 }
-                ring_buffer.write_byte(buf[write_size]);
-                write_size += 1;
+            } else {
+                written += buffer.write(&buf[written..], false);
             }
+            drop(buffer);
+            self.shared.readers.wake();
+            Ok((written == buf.len() || nonblocking).then_some(written))
+        });
+        match res {
+            // A signal only cuts the write short once part of it is done.
+            Err(LinuxError::EINTR) if written > 0 => Ok(written),
+            res => Ok(res?.unwrap_or_default()),
         }
     }
 
+    /// Wait until there is room in the pipe and return its length.
+    ///
+    /// Fails with `EPIPE` and raises `SIGPIPE` once the read end is closed,
+    /// and fails with `EAGAIN` if the pipe is full and `nonblocking` is set.
+    pub fn wait_room(&self, nonblocking: bool) -> LinuxResult<usize> {
+        if !self.writable() {
+            return Err(LinuxError::EPERM);
+        }
+        let room = wait_event(None, true, |waiter| {
+            self.shared.writers.register(waiter);
+            if self.closed() {
+                return Err(broken_pipe());
+            }
+            let room = self.shared.buffer.lock().room(self.is_packet_mode());
+            if room > 0 {
+                return Ok(Some(room));
+            }
+            if nonblocking {
+                return Err(LinuxError::EAGAIN);
+            }
+            // Buffer is full, wait for read end to consume
+            Ok(None)
+        })?;
+        Ok(room.unwrap_or_default())
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let closed = if self.readable {
+            &self.shared.read_closed
+        } else {
+            &self.shared.write_closed
+        };
+        closed.store(true, Ordering::Release);
+        self.shared.readers.wake();
+        self.shared.writers.wake();
+    }
+}
+
+impl FileLike for Pipe {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        self.read_data(buf, false, self.is_nonblocking())
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.write_data(buf, self.is_nonblocking())
+    }
+
     fn stat(&self) -> LinuxResult<Kstat> {
         Ok(Kstat {
             mode: S_IFIFO | 0o600u32, // rw-------
@@ -181,14 +455,33 @@ impl FileLike for Pipe {
     }
 
     fn poll(&self) -> LinuxResult<PollState> {
-        let buf = self.buffer.lock();
+        let buffer = self.shared.buffer.lock();
         Ok(PollState {
-            readable: self.readable() && buf.available_read() > 0,
-            writable: self.writable() && buf.available_write() > 0,
+            readable: self.readable() && buffer.len > 0,
+            writable: self.writable() && !buffer.is_full(),
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
         Ok(())
     }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, _events: u32) -> bool {
+        // Closing either end wakes both sets.
+        if self.readable {
+            self.shared.readers.register(waiter);
+        } else {
+            self.shared.writers.register(waiter);
+        }
+        true
+    }
+
+    fn event_seq(&self, _events: u32) -> Option<u64> {
+        Some(if self.readable {
+            self.shared.readers.seq()
+        } else {
+            self.shared.writers.seq()
+        })
+    }
 }
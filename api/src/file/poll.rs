@@ -0,0 +1,237 @@
+use core::{
+    mem,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axsync::Mutex;
+use axtask::{WaitQueue, current};
+
+use super::FileLike;
+use crate::signal::{has_pending_signal, with_signal_waiter};
+
+/// Interval at which files that cannot notify waiters, like the console, are
+/// checked again, and at which the network stack is processed while sockets
+/// are waited for.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A thread waiting for files to become ready.
+///
+/// Files wake the waiter registered with them through
+/// [`FileLike::register`] when their state changes.
+pub struct PollWaiter {
+    woken: AtomicBool,
+    /// Some of the registered files cannot notify the waiter, and have to be
+    /// polled.
+    polling: AtomicBool,
+    /// The time on the monotonic clock in nanoseconds at which some of the
+    /// registered files become ready, like armed timers.
+    wake_at: AtomicU64,
+    wq: WaitQueue,
+}
+
+impl PollWaiter {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            woken: AtomicBool::new(false),
+            polling: AtomicBool::new(false),
+            wake_at: AtomicU64::new(u64::MAX),
+            wq: WaitQueue::new(),
+        })
+    }
+
+    /// Register the waiter with `file` for the `POLL*` events in `events`.
+    pub fn watch(self: &Arc<Self>, file: &dyn FileLike, events: u32) {
+        if !file.register(self, events) {
+            self.polling.store(true, Ordering::Release);
+        }
+    }
+
+    /// Wake up the waiter at `deadline` on the monotonic clock at the latest,
+    /// for files that become ready at a known time.
+    pub fn wake_at(&self, deadline: Duration) {
+        let nanos = deadline.as_nanos().min(u64::MAX as u128) as u64;
+        self.wake_at.fetch_min(nanos, Ordering::AcqRel);
+    }
+
+    /// Wake up the waiting thread.
+    pub fn wake(&self) {
+        self.woken.store(true, Ordering::Release);
+        self.wq.notify_all(false);
+    }
+
+    /// Forget earlier wakeups before checking the files again.
+    fn reset(&self) {
+        self.woken.store(false, Ordering::Release);
+        self.polling.store(false, Ordering::Release);
+        self.wake_at.store(u64::MAX, Ordering::Release);
+    }
+
+    /// Sleep until woken up, or `timeout` has elapsed.
+    fn wait(&self, timeout: Option<Duration>) {
+        let woken = || self.woken.load(Ordering::Acquire);
+        match timeout {
+            Some(timeout) => {
+                self.wq.wait_timeout_until(timeout, woken);
+            }
+            None => self.wq.wait_until(woken),
+        }
+    }
+}
+
+/// The waiters registered with a file for some event.
+///
+/// Waking the set wakes and removes all the waiters, which register again
+/// before they check the file the next time.
+pub struct PollSet {
+    waiters: Mutex<Vec<Weak<PollWaiter>>>,
+    /// The number of times the set has been woken up.
+    seq: AtomicU64,
+}
+
+impl PollSet {
+    pub const fn new() -> Self {
+        Self {
+            waiters: Mutex::new(Vec::new()),
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    pub fn register(&self, waiter: &Arc<PollWaiter>) {
+        let mut waiters = self.waiters.lock();
+        waiters.retain(|waiter| waiter.strong_count() > 0);
+        if !waiters.iter().any(|w| w.as_ptr() == Arc::as_ptr(waiter)) {
+            waiters.push(Arc::downgrade(waiter));
+        }
+    }
+
+    pub fn wake(&self) {
+        self.seq.fetch_add(1, Ordering::AcqRel);
+        let waiters = mem::take(&mut *self.waiters.lock());
+        for waiter in waiters.iter().filter_map(Weak::upgrade) {
+            waiter.wake();
+        }
+    }
+
+    /// Get the number of times the set has been woken up, which moves each
+    /// time the file may have become ready.
+    pub fn seq(&self) -> u64 {
+        self.seq.load(Ordering::Acquire)
+    }
+}
+
+impl Default for PollSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Block the current thread until `poll` returns a result.
+///
+/// `poll` registers the waiter with the files it checks before checking
+/// them, and is called again each time one of them wakes it up, at the time
+/// passed to [`PollWaiter::wake_at`], or every [`POLL_INTERVAL`] if one of
+/// them cannot notify it. `None` is returned once `deadline` is reached. If
+/// `interruptible` is set, the wait fails with `EINTR` when a signal arrives.
+/// Waits of kernel workers fail with `ECANCELED` once their [`Canceler`] is
+/// canceled, and are never interrupted by the signals of the thread they act
+/// for.
+pub fn wait_event<T>(
+    deadline: Option<Duration>,
+    interruptible: bool,
+    mut poll: impl FnMut(&Arc<PollWaiter>) -> LinuxResult<Option<T>>,
+) -> LinuxResult<Option<T>> {
+    let worker = current_worker();
+    let interruptible = interruptible && worker.is_none();
+    let waiter = PollWaiter::new();
+    let mut wait = || {
+        loop {
+            waiter.reset();
+            if let Some(canceler) = &worker {
+                canceler.waiters.register(&waiter);
+                if canceler.is_canceled() {
+                    return Err(LinuxError::ECANCELED);
+                }
+            }
+            if let Some(res) = poll(&waiter)? {
+                return Ok(Some(res));
+            }
+            if interruptible && has_pending_signal() {
+                return Err(LinuxError::EINTR);
+            }
+            let now = monotonic_time();
+            let mut timeout = deadline.map(|deadline| deadline.saturating_sub(now));
+            if timeout == Some(Duration::ZERO) {
+                return Ok(None);
+            }
+            let mut limit = |t: Duration| {
+                timeout = Some(timeout.map_or(t, |timeout| timeout.min(t)));
+            };
+            if waiter.polling.load(Ordering::Acquire) {
+                limit(POLL_INTERVAL);
+            }
+            let wake_at = waiter.wake_at.load(Ordering::Acquire);
+            if wake_at != u64::MAX {
+                limit(Duration::from_nanos(wake_at).saturating_sub(now));
+            }
+            waiter.wait(timeout);
+        }
+    };
+    if interruptible {
+        with_signal_waiter(&waiter, wait)
+    } else {
+        wait()
+    }
+}
+
+/// Kernel workers running on behalf of a user thread, by task ID.
+static WORKERS: Mutex<BTreeMap<u64, Arc<Canceler>>> = Mutex::new(BTreeMap::new());
+
+/// Cancellation of the waits of kernel workers, like those of io_uring.
+///
+/// Workers share the thread they act for, so they neither take nor raise its
+/// signals. Instead their waits fail with `ECANCELED` once canceled.
+pub struct Canceler {
+    canceled: AtomicBool,
+    waiters: PollSet,
+}
+
+impl Canceler {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            canceled: AtomicBool::new(false),
+            waiters: PollSet::new(),
+        })
+    }
+
+    /// Cancel the current and future waits of the workers.
+    pub fn cancel(&self) {
+        self.canceled.store(true, Ordering::Release);
+        self.waiters.wake();
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::Acquire)
+    }
+}
+
+/// Run `f` as a kernel worker whose waits are canceled by `canceler`.
+pub fn run_as_worker<T>(canceler: &Arc<Canceler>, f: impl FnOnce() -> T) -> T {
+    let id = current().id().as_u64();
+    WORKERS.lock().insert(id, canceler.clone());
+    let res = f();
+    WORKERS.lock().remove(&id);
+    res
+}
+
+/// Get the canceler of the current task if it is a kernel worker.
+pub fn current_worker() -> Option<Arc<Canceler>> {
+    WORKERS.lock().get(&current().id().as_u64()).cloned()
+}
@@ -0,0 +1,201 @@
+use core::{
+    any::Any,
+    slice,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use alloc::sync::Arc;
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsignal::{SignalInfo, SignalSet, Signo};
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::general::{SI_QUEUE, SI_TIMER, siginfo};
+
+use super::{FileLike, Kstat, PollSet, PollWaiter, wait_event};
+
+/// Waiters for signals to read from any signalfd.
+static SIGNALFD_READERS: PollSet = PollSet::new();
+
+/// Wake up the readers of signalfds, after a signal has been sent.
+pub fn notify_signalfds() {
+    SIGNALFD_READERS.wake();
+}
+
+/// A signal record read from a signalfd, as `struct signalfd_siginfo`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct SignalfdSiginfo {
+    ssi_signo: u32,
+    ssi_errno: i32,
+    ssi_code: i32,
+    ssi_pid: u32,
+    ssi_uid: u32,
+    ssi_fd: i32,
+    ssi_tid: u32,
+    ssi_band: u32,
+    ssi_overrun: u32,
+    ssi_trapno: u32,
+    ssi_status: i32,
+    ssi_int: i32,
+    ssi_ptr: u64,
+    ssi_utime: u64,
+    ssi_stime: u64,
+    ssi_addr: u64,
+    ssi_addr_lsb: u16,
+    __pad2: u16,
+    ssi_syscall: i32,
+    ssi_call_addr: u64,
+    ssi_arch: u32,
+    __pad: [u8; 28],
+}
+
+impl From<&siginfo> for SignalfdSiginfo {
+    fn from(info: &siginfo) -> Self {
+        // SAFETY: the fields read are the ones used for the signal and code.
+        unsafe {
+            let info = &info.__bindgen_anon_1.__bindgen_anon_1;
+            let fields = &info._sifields;
+            let mut ssi = Self {
+                ssi_signo: info.si_signo as _,
+                ssi_errno: info.si_errno,
+                ssi_code: info.si_code,
+                ..Default::default()
+            };
+            let fault = info.si_code > 0
+                && matches!(
+                    Signo::from_repr(info.si_signo as u8),
+                    Some(Signo::SIGSEGV | Signo::SIGBUS | Signo::SIGILL | Signo::SIGFPE)
+                );
+            if info.si_code == SI_TIMER {
+                ssi.ssi_tid = fields._timer._tid as _;
+                ssi.ssi_overrun = fields._timer._overrun as _;
+                ssi.ssi_int = fields._timer._sigval.sival_int;
+                ssi.ssi_ptr = fields._timer._sigval.sival_ptr as _;
+            } else if fault {
+                ssi.ssi_addr = fields._sigfault._addr as _;
+            } else {
+                ssi.ssi_pid = fields._kill._pid as _;
+                ssi.ssi_uid = fields._kill._uid as _;
+                if info.si_code == SI_QUEUE {
+                    ssi.ssi_int = fields._rt._sigval.sival_int;
+                    ssi.ssi_ptr = fields._rt._sigval.sival_ptr as _;
+                } else if info.si_code > 0 && info.si_signo == Signo::SIGCHLD as i32 {
+                    ssi.ssi_status = fields._sigchld._status;
+                    ssi.ssi_utime = fields._sigchld._utime as _;
+                    ssi.ssi_stime = fields._sigchld._stime as _;
+                }
+            }
+            ssi
+        }
+    }
+}
+
+/// A file to receive the signals in a mask, created by `signalfd4`.
+///
+/// Reading dequeues the pending signals of the reading thread and its
+/// process, so the signals are usually blocked to keep them from being
+/// delivered.
+pub struct SignalFd {
+    mask: Mutex<SignalSet>,
+    nonblocking: AtomicBool,
+}
+
+impl SignalFd {
+    pub fn new(mask: SignalSet) -> Self {
+        Self {
+            mask: Mutex::new(mask),
+            nonblocking: AtomicBool::new(false),
+        }
+    }
+
+    /// Replace the mask of the signals to receive.
+    pub fn set_mask(&self, mask: SignalSet) {
+        *self.mask.lock() = mask;
+        SIGNALFD_READERS.wake();
+    }
+
+    /// Dequeue a pending signal in the mask, without waiting.
+    fn dequeue(&self) -> Option<SignalInfo> {
+        let mask = *self.mask.lock();
+        current()
+            .task_ext()
+            .thread_data()
+            .signal
+            .wait_timeout(mask, Some(Duration::ZERO))
+    }
+}
+
+impl FileLike for SignalFd {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        const RECORD_SIZE: usize = size_of::<SignalfdSiginfo>();
+        if buf.len() < RECORD_SIZE {
+            return Err(LinuxError::EINVAL);
+        }
+        let nonblocking = self.nonblocking.load(Ordering::Acquire);
+        let mut read = 0;
+        for record in buf.chunks_exact_mut(RECORD_SIZE) {
+            let sig = if read == 0 {
+                wait_event(None, true, |waiter| {
+                    SIGNALFD_READERS.register(waiter);
+                    match self.dequeue() {
+                        Some(sig) => Ok(Some(sig)),
+                        None if nonblocking => Err(LinuxError::EAGAIN),
+                        None => Ok(None),
+                    }
+                })?
+            } else {
+                self.dequeue()
+            };
+            let Some(sig) = sig else {
+                break;
+            };
+            let ssi = SignalfdSiginfo::from(&sig.0);
+            // SAFETY: `SignalfdSiginfo` is plain data without padding.
+            let bytes =
+                unsafe { slice::from_raw_parts(&ssi as *const _ as *const u8, RECORD_SIZE) };
+            record.copy_from_slice(bytes);
+            read += RECORD_SIZE;
+        }
+        Ok(read)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: 0o600u32, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let mut pending = current().task_ext().thread_data().signal.pending();
+        pending &= *self.mask.lock();
+        Ok(PollState {
+            readable: pending != SignalSet::default(),
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, _events: u32) -> bool {
+        SIGNALFD_READERS.register(waiter);
+        true
+    }
+
+    fn event_seq(&self, _events: u32) -> Option<u64> {
+        Some(SIGNALFD_READERS.seq())
+    }
+}
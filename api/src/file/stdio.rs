@@ -4,9 +4,9 @@ use alloc::sync::Arc;
 use axerrno::{AxResult, LinuxError, LinuxResult};
 use axio::{BufReader, PollState, prelude::*};
 use axsync::Mutex;
-use linux_raw_sys::general::S_IFCHR;
+use linux_raw_sys::general::{POLLIN, S_IFCHR};
 
-use super::Kstat;
+use super::{Kstat, wait_event};
 
 fn console_read_bytes(buf: &mut [u8]) -> AxResult<usize> {
     let len = axhal::console::read_bytes(buf);
@@ -56,26 +56,20 @@ pub struct Stdin {
 }
 
 impl Stdin {
-    // Block until at least one byte is read.
-    fn read_blocked(&self, buf: &mut [u8]) -> AxResult<usize> {
-        let read_len = self.inner.lock().read(buf)?;
-        if buf.is_empty() || read_len > 0 {
-            return Ok(read_len);
+    // Block until at least one byte is read, or a signal arrives.
+    fn read_blocked(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
         }
-        // try again until we get something
-        loop {
-            let read_len = self.inner.lock().read(buf)?;
-            if read_len > 0 {
-                return Ok(read_len);
+        // axhal has no console interrupt to wake us, so the waiter polls.
+        let read_len = wait_event(None, true, |waiter| {
+            waiter.watch(self, POLLIN);
+            match self.inner.lock().read(buf)? {
+                0 => Ok(None),
+                read_len => Ok(Some(read_len)),
             }
-            axtask::yield_now();
-        }
-    }
-}
-
-impl Read for Stdin {
-    fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        self.read_blocked(buf)
+        })?;
+        Ok(read_len.unwrap_or_default())
     }
 }
 
@@ -107,7 +101,7 @@ pub fn stdout() -> Stdout {
 
 impl super::FileLike for Stdin {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        Ok(self.read_blocked(buf)?)
+        self.read_blocked(buf)
     }
 
     fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
@@ -126,8 +120,13 @@ impl super::FileLike for Stdin {
     }
 
     fn poll(&self) -> LinuxResult<PollState> {
+        let readable = self
+            .inner
+            .lock()
+            .fill_buf()
+            .is_ok_and(|buf| !buf.is_empty());
         Ok(PollState {
-            readable: true,
+            readable,
             writable: true,
         })
     }
@@ -0,0 +1,192 @@
+use core::{
+    any::Any,
+    mem,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use alloc::sync::Arc;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{monotonic_time, wall_time};
+use axio::PollState;
+use axsync::Mutex;
+use linux_raw_sys::general::CLOCK_REALTIME;
+
+use super::{FileLike, Kstat, PollSet, PollWaiter, wait_event};
+
+struct TimerState {
+    /// The next expiration on the clock of the timer, `None` if disarmed.
+    next: Option<Duration>,
+    /// The period of the timer, zero for a one-shot timer.
+    interval: Duration,
+    /// Expirations since the last read.
+    expirations: u64,
+    /// Expirations since the timer was created.
+    fired: u64,
+}
+
+impl TimerState {
+    /// Account for the expirations up to `now`.
+    fn update(&mut self, now: Duration) {
+        let Some(next) = self.next.filter(|next| *next <= now) else {
+            return;
+        };
+        if self.interval.is_zero() {
+            self.expirations += 1;
+            self.fired = self.fired.wrapping_add(1);
+            self.next = None;
+        } else {
+            let count = (now - next).as_nanos() / self.interval.as_nanos() + 1;
+            self.expirations = self.expirations.saturating_add(count as u64);
+            self.fired = self.fired.wrapping_add(count as u64);
+            self.next =
+                Some(next + Duration::from_nanos((self.interval.as_nanos() * count) as u64));
+        }
+    }
+}
+
+/// A timer delivering its expirations through a file, created by
+/// `timerfd_create`.
+///
+/// Expirations are accounted for lazily, when the timer is read, polled or
+/// set.
+pub struct TimerFd {
+    clock: u32,
+    state: Mutex<TimerState>,
+    nonblocking: AtomicBool,
+    /// Waiters for the timer to be set, which changes the next expiration.
+    readers: PollSet,
+}
+
+impl TimerFd {
+    /// Create a disarmed timer on `clock`, either `CLOCK_REALTIME` or
+    /// `CLOCK_MONOTONIC`.
+    pub fn new(clock: u32) -> Self {
+        Self {
+            clock,
+            state: Mutex::new(TimerState {
+                next: None,
+                interval: Duration::ZERO,
+                expirations: 0,
+                fired: 0,
+            }),
+            nonblocking: AtomicBool::new(false),
+            readers: PollSet::new(),
+        }
+    }
+
+    /// Get the current time on the clock of the timer.
+    fn now(&self) -> Duration {
+        if self.clock == CLOCK_REALTIME {
+            wall_time()
+        } else {
+            monotonic_time()
+        }
+    }
+
+    /// Get the time until the next expiration and the interval, as
+    /// `timerfd_gettime` does.
+    pub fn get(&self) -> (Duration, Duration) {
+        let now = self.now();
+        let mut state = self.state.lock();
+        state.update(now);
+        let value = state.next.map_or(Duration::ZERO, |next| next - now);
+        (value, state.interval)
+    }
+
+    /// Arm the timer to expire after `value`, or at `value` if `absolute` is
+    /// set, and then every `interval`, or disarm it if `value` is zero.
+    ///
+    /// Returns the previous setting as [`get`](Self::get) does.
+    pub fn set(&self, value: Duration, interval: Duration, absolute: bool) -> (Duration, Duration) {
+        let old = self.get();
+        let now = self.now();
+        let mut state = self.state.lock();
+        state.next = if value.is_zero() {
+            None
+        } else if absolute {
+            Some(value)
+        } else {
+            Some(now + value)
+        };
+        state.interval = interval;
+        state.expirations = 0;
+        drop(state);
+        self.readers.wake();
+        old
+    }
+
+    /// Account for the expirations so far, and let `waiter` be woken up at
+    /// the next expiration.
+    fn check(&self, waiter: &Arc<PollWaiter>) {
+        self.readers.register(waiter);
+        let now = self.now();
+        let mut state = self.state.lock();
+        state.update(now);
+        if let Some(next) = state.next {
+            // Deadlines of waiters are on the monotonic clock.
+            waiter.wake_at(monotonic_time() + (next - now));
+        }
+    }
+}
+
+impl FileLike for TimerFd {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let Some(buf) = buf.get_mut(..size_of::<u64>()) else {
+            return Err(LinuxError::EINVAL);
+        };
+        let nonblocking = self.nonblocking.load(Ordering::Acquire);
+        let expirations = wait_event(None, true, |waiter| {
+            self.check(waiter);
+            match mem::take(&mut self.state.lock().expirations) {
+                0 if nonblocking => Err(LinuxError::EAGAIN),
+                0 => Ok(None),
+                expirations => Ok(Some(expirations)),
+            }
+        })?
+        .unwrap_or_default();
+        buf.copy_from_slice(&expirations.to_ne_bytes());
+        Ok(buf.len())
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<Kstat> {
+        Ok(Kstat {
+            mode: 0o600u32, // rw-------
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let mut state = self.state.lock();
+        state.update(self.now());
+        Ok(PollState {
+            readable: state.expirations > 0,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn register(&self, waiter: &Arc<PollWaiter>, _events: u32) -> bool {
+        self.check(waiter);
+        true
+    }
+
+    fn event_seq(&self, _events: u32) -> Option<u64> {
+        // Expirations are not woken up for, setting the timer is.
+        let mut state = self.state.lock();
+        state.update(self.now());
+        Some(self.readers.seq().wrapping_add(state.fired))
+    }
+}
@@ -0,0 +1,182 @@
+use core::{
+    ffi::c_int,
+    net::{IpAddr, SocketAddr},
+    sync::atomic::{AtomicBool, AtomicU16, Ordering},
+};
+
+use alloc::{
+    collections::vec_deque::VecDeque,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+
+use super::live_entries;
+use crate::netif::interfaces;
+
+/// Capacity of the queue of multicast datagrams looped back to a socket.
+const LOOPBACK_BUF_SIZE: usize = 64 * 1024;
+
+/// UDP sockets that joined a multicast group.
+static MULTICAST_MEMBERS: Mutex<Vec<Weak<UdpState>>> = Mutex::new(Vec::new());
+
+/// A multicast group joined on an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Membership {
+    group: IpAddr,
+    ifindex: c_int,
+}
+
+/// The UDP semantics of Linux that axnet lacks.
+///
+/// axnet can neither forget the peer of a connected socket nor join
+/// multicast groups, so the default destination of a socket is kept here.
+/// Groups can only be joined on the loopback interface, where multicast
+/// datagrams are looped back to the members by their senders.
+pub struct UdpState {
+    /// The default destination set by `connect`, incoming datagrams from
+    /// other addresses are dropped.
+    peer: Mutex<Option<SocketAddr>>,
+    /// The bound local port, 0 if the socket is not bound yet.
+    port: AtomicU16,
+    memberships: Mutex<Vec<Membership>>,
+    /// The socket is in [`MULTICAST_MEMBERS`].
+    registered: AtomicBool,
+    /// Looped back multicast datagrams and their source addresses.
+    looped: Mutex<VecDeque<(Vec<u8>, SocketAddr)>>,
+}
+
+impl UdpState {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            peer: Mutex::new(None),
+            port: AtomicU16::new(0),
+            memberships: Mutex::new(Vec::new()),
+            registered: AtomicBool::new(false),
+            looped: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    pub fn peer(&self) -> Option<SocketAddr> {
+        *self.peer.lock()
+    }
+
+    /// Set the default destination, or dissolve the association if `peer`
+    /// is `None`.
+    pub fn set_peer(&self, peer: Option<SocketAddr>) {
+        *self.peer.lock() = peer;
+    }
+
+    /// Record the local port the socket is bound to.
+    pub fn set_port(&self, port: u16) {
+        self.port.store(port, Ordering::Release);
+    }
+
+    /// Whether a datagram from `src` passes the filter of the connected
+    /// peer.
+    pub fn accepts(&self, src: &SocketAddr) -> bool {
+        self.peer().is_none_or(|peer| peer == *src)
+    }
+
+    /// Join the multicast `group` on the interface with index `ifindex`.
+    pub fn join(self: &Arc<Self>, group: IpAddr, ifindex: c_int) -> LinuxResult {
+        check_membership(group, ifindex)?;
+        let membership = Membership { group, ifindex };
+        let mut memberships = self.memberships.lock();
+        if memberships.contains(&membership) {
+            return Err(LinuxError::EADDRINUSE);
+        }
+        if !self.registered.swap(true, Ordering::AcqRel) {
+            MULTICAST_MEMBERS.lock().push(Arc::downgrade(self));
+        }
+        memberships.push(membership);
+        Ok(())
+    }
+
+    /// Leave the multicast `group` joined on the interface with index
+    /// `ifindex`.
+    pub fn leave(&self, group: IpAddr, ifindex: c_int) -> LinuxResult {
+        check_membership(group, ifindex)?;
+        let mut memberships = self.memberships.lock();
+        let pos = memberships
+            .iter()
+            .position(|membership| *membership == Membership { group, ifindex })
+            .ok_or(LinuxError::EADDRNOTAVAIL)?;
+        memberships.remove(pos);
+        Ok(())
+    }
+
+    /// Queue a looped back multicast datagram sent from `src` to `dst`, if
+    /// the socket is a member of the group.
+    fn receive(&self, data: &[u8], src: SocketAddr, dst: SocketAddr) {
+        if self.port.load(Ordering::Acquire) != dst.port()
+            || !self.accepts(&src)
+            || !self
+                .memberships
+                .lock()
+                .iter()
+                .any(|membership| membership.group == dst.ip())
+        {
+            return;
+        }
+        let mut looped = self.looped.lock();
+        if looped.iter().map(|(data, _)| data.len()).sum::<usize>() + data.len()
+            <= LOOPBACK_BUF_SIZE
+        {
+            looped.push_back((Vec::from(data), src));
+        }
+    }
+
+    /// Receive a looped back multicast datagram into `buf`, returning its
+    /// full length and source address.
+    pub fn recv_looped(&self, buf: &mut [u8], peek: bool) -> Option<(usize, SocketAddr)> {
+        let mut looped = self.looped.lock();
+        let (data, src) = looped.front()?;
+        let (len, src) = (data.len(), *src);
+        let copied = len.min(buf.len());
+        buf[..copied].copy_from_slice(&data[..copied]);
+        if !peek {
+            looped.pop_front();
+        }
+        Some((len, src))
+    }
+
+    pub fn has_looped(&self) -> bool {
+        !self.looped.lock().is_empty()
+    }
+}
+
+impl Drop for UdpState {
+    fn drop(&mut self) {
+        if *self.registered.get_mut() {
+            MULTICAST_MEMBERS
+                .lock()
+                .retain(|member| member.strong_count() > 0);
+        }
+    }
+}
+
+fn check_membership(group: IpAddr, ifindex: c_int) -> LinuxResult {
+    if !group.is_multicast() {
+        return Err(LinuxError::EINVAL);
+    }
+    let iface = interfaces()
+        .into_iter()
+        .find(|iface| iface.index == ifindex)
+        .ok_or(LinuxError::ENODEV)?;
+    // Joining a group on a NIC requires smoltcp to send IGMP or MLD reports,
+    // which axnet does not expose.
+    if !iface.is_loopback() {
+        return Err(LinuxError::EOPNOTSUPP);
+    }
+    Ok(())
+}
+
+/// Deliver a multicast datagram sent from `src` to `dst` to the members of
+/// the group on this host.
+pub fn loop_multicast(data: &[u8], src: SocketAddr, dst: SocketAddr) {
+    for member in live_entries(&MULTICAST_MEMBERS) {
+        member.receive(data, src, dst);
+    }
+}
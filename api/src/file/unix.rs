@@ -0,0 +1,728 @@
+use core::{
+    mem,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    format,
+    string::ToString,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use linux_raw_sys::{general::AT_FDCWD, net::ucred};
+
+use super::{FileLike, PollSet, PollWaiter, Socket};
+use crate::{path::handle_file_path, socket::UnixSocketAddr};
+
+/// Capacity of the receive queue of a Unix socket.
+const UNIX_BUF_SIZE: usize = 64 * 1024;
+
+/// The type of a Unix socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnixSocketType {
+    /// `SOCK_STREAM`
+    Stream,
+    /// `SOCK_DGRAM`
+    Datagram,
+}
+
+/// Ancillary data passed along with the data of a Unix socket.
+#[derive(Default)]
+pub struct UnixAncillary {
+    /// Files passed by `SCM_RIGHTS`.
+    pub rights: Vec<Arc<dyn FileLike>>,
+    /// Credentials of the sender, given by `SCM_CREDENTIALS` or filled in
+    /// automatically.
+    pub cred: Option<ucred>,
+}
+
+/// Get the credentials of the calling process.
+///
+/// There are no users yet, every process runs as root.
+pub fn current_cred() -> ucred {
+    ucred {
+        pid: current().task_ext().thread.process().pid(),
+        uid: 0,
+        gid: 0,
+    }
+}
+
+fn same_cred(a: &ucred, b: &ucred) -> bool {
+    (a.pid, a.uid, a.gid) == (b.pid, b.uid, b.gid)
+}
+
+struct UnixMessage {
+    data: Vec<u8>,
+    /// Bytes of `data` already consumed by stream reads.
+    pos: usize,
+    from: UnixSocketAddr,
+    cred: ucred,
+    rights: Vec<Arc<dyn FileLike>>,
+}
+
+/// The receive queue of a Unix socket, shared with the peers sending to it.
+struct UnixQueue {
+    messages: VecDeque<UnixMessage>,
+    /// Total number of unread bytes in `messages`.
+    len: usize,
+    /// The receiving side has gone away, further sends fail.
+    rx_closed: bool,
+    /// The sending side has gone away, reads return end-of-file once the
+    /// queue is drained.
+    tx_closed: bool,
+    /// Waiters for messages, or for connections to accept on a listening
+    /// socket.
+    readers: PollSet,
+    /// Waiters for room in the queue.
+    writers: PollSet,
+}
+
+impl UnixQueue {
+    fn new() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            messages: VecDeque::new(),
+            len: 0,
+            rx_closed: false,
+            tx_closed: false,
+            readers: PollSet::new(),
+            writers: PollSet::new(),
+        }))
+    }
+
+    fn available(&self) -> usize {
+        UNIX_BUF_SIZE.saturating_sub(self.len)
+    }
+
+    fn push(&mut self, data: Vec<u8>, from: UnixSocketAddr, ancillary: UnixAncillary) {
+        self.len += data.len();
+        self.messages.push_back(UnixMessage {
+            data,
+            pos: 0,
+            from,
+            cred: ancillary.cred.unwrap_or_else(current_cred),
+            rights: ancillary.rights,
+        });
+        self.readers.wake();
+    }
+
+    /// Close the receiving side if `rx` is set, and the sending side if `tx`
+    /// is set.
+    fn close(&mut self, rx: bool, tx: bool) {
+        self.rx_closed |= rx;
+        self.tx_closed |= tx;
+        self.readers.wake();
+        self.writers.wake();
+    }
+}
+
+/// Get the Unix socket `file` is, if it is one.
+fn as_unix_socket(file: &Arc<dyn FileLike>) -> Option<Arc<UnixSocket>> {
+    let socket = file.clone().into_any().downcast::<Socket>().ok()?;
+    socket.unix_socket().cloned()
+}
+
+/// Whether queueing `file` in `target` would make `target` hold a reference
+/// to itself, through `file` or through the sockets in flight in the queues
+/// `file` refers to.
+///
+/// Nothing collects such cycles, so passing the file is refused.
+fn refers_to(file: &Arc<dyn FileLike>, target: &Arc<Mutex<UnixQueue>>) -> bool {
+    let mut pending: Vec<_> = as_unix_socket(file).into_iter().collect();
+    let mut visited: Vec<Arc<Mutex<UnixQueue>>> = Vec::new();
+    while let Some(socket) = pending.pop() {
+        for queue in socket.queues() {
+            if Arc::ptr_eq(&queue, target) {
+                return true;
+            }
+            if visited.iter().any(|visited| Arc::ptr_eq(visited, &queue)) {
+                continue;
+            }
+            pending.extend(
+                queue
+                    .lock()
+                    .messages
+                    .iter()
+                    .flat_map(|msg| &msg.rights)
+                    .filter_map(as_unix_socket),
+            );
+            visited.push(queue);
+        }
+    }
+    false
+}
+
+enum UnixState {
+    Unconnected,
+    Listening {
+        /// Connected server-side sockets waiting to be accepted.
+        backlog: VecDeque<Arc<UnixSocket>>,
+        max_backlog: usize,
+    },
+    Connected {
+        peer: Arc<Mutex<UnixQueue>>,
+        peer_addr: UnixSocketAddr,
+        /// Credentials reported by `SO_PEERCRED`.
+        peer_cred: ucred,
+    },
+}
+
+/// Bound Unix sockets, keyed by canonical path or abstract name.
+///
+/// Entries of path names are kept after the socket is closed, like the
+/// socket file is, so that connecting to a stale socket is refused.
+static UNIX_TABLE: Mutex<BTreeMap<UnixSocketAddr, Weak<UnixSocket>>> = Mutex::new(BTreeMap::new());
+
+/// Get the key of `addr` in [`UNIX_TABLE`].
+fn table_key(addr: &UnixSocketAddr) -> LinuxResult<UnixSocketAddr> {
+    match addr {
+        UnixSocketAddr::Path(path) => Ok(UnixSocketAddr::Path(
+            handle_file_path(AT_FDCWD, path)?.to_string(),
+        )),
+        addr => Ok(addr.clone()),
+    }
+}
+
+/// Find the socket bound to `addr`.
+fn lookup(addr: &UnixSocketAddr) -> LinuxResult<Arc<UnixSocket>> {
+    let key = table_key(addr)?;
+    if let UnixSocketAddr::Path(path) = &key
+        && !axfs::api::absolute_path_exists(path)
+    {
+        return Err(LinuxError::ENOENT);
+    }
+    UNIX_TABLE
+        .lock()
+        .get(&key)
+        .and_then(Weak::upgrade)
+        .ok_or(LinuxError::ECONNREFUSED)
+}
+
+/// Whether the file at the canonical `path` was created by binding a Unix
+/// socket.
+pub fn is_unix_socket_node(path: &str) -> bool {
+    UNIX_TABLE
+        .lock()
+        .contains_key(&UnixSocketAddr::Path(path.into()))
+}
+
+/// An in-kernel Unix domain socket.
+pub struct UnixSocket {
+    ty: UnixSocketType,
+    /// Credentials of the creator of the socket.
+    cred: ucred,
+    local_addr: Mutex<UnixSocketAddr>,
+    state: Mutex<UnixState>,
+    queue: Arc<Mutex<UnixQueue>>,
+}
+
+impl UnixSocket {
+    /// Create a new unbound Unix socket.
+    pub fn new(ty: UnixSocketType) -> Self {
+        Self {
+            ty,
+            cred: current_cred(),
+            local_addr: Mutex::new(UnixSocketAddr::Unnamed),
+            state: Mutex::new(UnixState::Unconnected),
+            queue: UnixQueue::new(),
+        }
+    }
+
+    /// Create a pair of unbound Unix sockets connected to each other.
+    pub fn new_pair(ty: UnixSocketType) -> (Self, Self) {
+        let (queue1, queue2) = (UnixQueue::new(), UnixQueue::new());
+        let cred = current_cred();
+        let connected = |peer: &Arc<Mutex<UnixQueue>>| {
+            Mutex::new(UnixState::Connected {
+                peer: peer.clone(),
+                peer_addr: UnixSocketAddr::Unnamed,
+                peer_cred: cred,
+            })
+        };
+        let socket1 = Self {
+            ty,
+            cred,
+            local_addr: Mutex::new(UnixSocketAddr::Unnamed),
+            state: connected(&queue2),
+            queue: queue1.clone(),
+        };
+        let socket2 = Self {
+            ty,
+            cred,
+            local_addr: Mutex::new(UnixSocketAddr::Unnamed),
+            state: connected(&queue1),
+            queue: queue2,
+        };
+        (socket1, socket2)
+    }
+
+    /// Get the type of the socket.
+    pub fn socket_type(&self) -> UnixSocketType {
+        self.ty
+    }
+
+    /// Get the queues the socket refers to: its own receive queue, the one
+    /// of its peer and those of the connections waiting to be accepted.
+    fn queues(&self) -> Vec<Arc<Mutex<UnixQueue>>> {
+        let mut queues = vec![self.queue.clone()];
+        match &*self.state.lock() {
+            UnixState::Unconnected => {}
+            UnixState::Listening { backlog, .. } => {
+                queues.extend(backlog.iter().flat_map(|socket| socket.queues()));
+            }
+            UnixState::Connected { peer, .. } => queues.push(peer.clone()),
+        }
+        queues
+    }
+
+    pub fn local_addr(&self) -> UnixSocketAddr {
+        self.local_addr.lock().clone()
+    }
+
+    pub fn peer_addr(&self) -> LinuxResult<UnixSocketAddr> {
+        match &*self.state.lock() {
+            UnixState::Connected { peer_addr, .. } => Ok(peer_addr.clone()),
+            _ => Err(LinuxError::ENOTCONN),
+        }
+    }
+
+    /// Get the credentials of the peer, captured when the connection was
+    /// established.
+    pub fn peer_cred(&self) -> Option<ucred> {
+        match &*self.state.lock() {
+            UnixState::Connected { peer_cred, .. } => Some(*peer_cred),
+            _ => None,
+        }
+    }
+
+    /// Bind the socket to `addr`.
+    ///
+    /// Binding to a path name creates the socket file, binding to an unnamed
+    /// address picks an unused abstract name.
+    pub fn bind(self: &Arc<Self>, addr: UnixSocketAddr) -> LinuxResult {
+        static NEXT_AUTOBIND: AtomicU32 = AtomicU32::new(0);
+
+        let mut local_addr = self.local_addr.lock();
+        if *local_addr != UnixSocketAddr::Unnamed {
+            return Err(LinuxError::EINVAL);
+        }
+
+        let mut table = UNIX_TABLE.lock();
+        let in_use = |table: &BTreeMap<UnixSocketAddr, Weak<UnixSocket>>, key: &UnixSocketAddr| {
+            table.get(key).is_some_and(|sock| sock.strong_count() > 0)
+        };
+        let (addr, key) = match addr {
+            UnixSocketAddr::Unnamed => loop {
+                let id = NEXT_AUTOBIND.fetch_add(1, Ordering::Relaxed) & 0xfffff;
+                let addr = UnixSocketAddr::Abstract(format!("{:05x}", id).into_bytes());
+                if !in_use(&table, &addr) {
+                    break (addr.clone(), addr);
+                }
+            },
+            UnixSocketAddr::Path(_) => {
+                let key = table_key(&addr)?;
+                let UnixSocketAddr::Path(path) = &key else {
+                    unreachable!()
+                };
+                if axfs::api::absolute_path_exists(path) {
+                    return Err(LinuxError::EADDRINUSE);
+                }
+                axfs::api::write(path, b"")?;
+                (addr, key)
+            }
+            UnixSocketAddr::Abstract(_) => {
+                if in_use(&table, &addr) {
+                    return Err(LinuxError::EADDRINUSE);
+                }
+                (addr.clone(), addr)
+            }
+        };
+        table.insert(key, Arc::downgrade(self));
+        *local_addr = addr;
+        Ok(())
+    }
+
+    /// Mark the socket as accepting connections.
+    pub fn listen(&self, backlog: usize) -> LinuxResult {
+        if self.ty != UnixSocketType::Stream {
+            return Err(LinuxError::EOPNOTSUPP);
+        }
+        if *self.local_addr.lock() == UnixSocketAddr::Unnamed {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut state = self.state.lock();
+        match &mut *state {
+            UnixState::Unconnected => {
+                *state = UnixState::Listening {
+                    backlog: VecDeque::new(),
+                    max_backlog: backlog,
+                };
+            }
+            UnixState::Listening { max_backlog, .. } => *max_backlog = backlog,
+            UnixState::Connected { .. } => return Err(LinuxError::EINVAL),
+        }
+        Ok(())
+    }
+
+    /// Accept a pending connection, or fail with `EAGAIN` if there is none.
+    pub fn accept(&self) -> LinuxResult<Arc<UnixSocket>> {
+        match &mut *self.state.lock() {
+            UnixState::Listening { backlog, .. } => backlog.pop_front().ok_or(LinuxError::EAGAIN),
+            _ => Err(LinuxError::EINVAL),
+        }
+    }
+
+    /// Connect the socket to the socket bound to `addr`.
+    ///
+    /// For stream sockets this fails with `EAGAIN` while the backlog of the
+    /// listening socket is full. For datagram sockets it only sets the
+    /// default destination.
+    pub fn connect(&self, addr: &UnixSocketAddr) -> LinuxResult {
+        let target = lookup(addr)?;
+        if target.ty != self.ty {
+            return Err(LinuxError::EPROTOTYPE);
+        }
+
+        let mut state = self.state.lock();
+        if self.ty == UnixSocketType::Datagram {
+            *state = UnixState::Connected {
+                peer: target.queue.clone(),
+                peer_addr: target.local_addr(),
+                peer_cred: target.cred,
+            };
+            return Ok(());
+        }
+
+        match &*state {
+            UnixState::Unconnected => {}
+            UnixState::Listening { .. } => return Err(LinuxError::EINVAL),
+            UnixState::Connected { .. } => return Err(LinuxError::EISCONN),
+        }
+        let target_addr = target.local_addr();
+        let mut target_state = target.state.lock();
+        let UnixState::Listening {
+            backlog,
+            max_backlog,
+        } = &mut *target_state
+        else {
+            return Err(LinuxError::ECONNREFUSED);
+        };
+        if backlog.len() > *max_backlog {
+            return Err(LinuxError::EAGAIN);
+        }
+
+        let server = Arc::new(UnixSocket {
+            ty: UnixSocketType::Stream,
+            cred: target.cred,
+            local_addr: Mutex::new(target_addr.clone()),
+            state: Mutex::new(UnixState::Connected {
+                peer: self.queue.clone(),
+                peer_addr: self.local_addr(),
+                peer_cred: current_cred(),
+            }),
+            queue: UnixQueue::new(),
+        });
+        *state = UnixState::Connected {
+            peer: server.queue.clone(),
+            peer_addr: target_addr,
+            peer_cred: target.cred,
+        };
+        backlog.push_back(server);
+        target.queue.lock().readers.wake();
+        Ok(())
+    }
+
+    /// Dissolve the association of a datagram socket, as `connect` with
+    /// `AF_UNSPEC` does.
+    pub fn disconnect(&self) -> LinuxResult {
+        if self.ty != UnixSocketType::Datagram {
+            return Err(LinuxError::EINVAL);
+        }
+        *self.state.lock() = UnixState::Unconnected;
+        Ok(())
+    }
+
+    /// Send data to the connected peer, or to the socket bound to `addr`
+    /// for datagram sockets.
+    ///
+    /// `ancillary` is taken and attached to the sent data on success. Fails
+    /// with `EAGAIN` if the receive queue of the peer is full, and with
+    /// `ETOOMANYREFS` if a passed socket refers to that queue.
+    pub fn send(
+        &self,
+        buf: &[u8],
+        addr: Option<&UnixSocketAddr>,
+        ancillary: &mut UnixAncillary,
+    ) -> LinuxResult<usize> {
+        let peer = match (self.ty, addr) {
+            (UnixSocketType::Datagram, Some(addr)) => {
+                let target = lookup(addr)?;
+                if target.ty != UnixSocketType::Datagram {
+                    return Err(LinuxError::EPROTOTYPE);
+                }
+                target.queue.clone()
+            }
+            (UnixSocketType::Stream, Some(_)) if self.peer_addr().is_ok() => {
+                return Err(LinuxError::EISCONN);
+            }
+            _ => match &*self.state.lock() {
+                UnixState::Connected { peer, .. } => peer.clone(),
+                _ => return Err(LinuxError::ENOTCONN),
+            },
+        };
+        if ancillary.rights.iter().any(|file| refers_to(file, &peer)) {
+            return Err(LinuxError::ETOOMANYREFS);
+        }
+        let from = self.local_addr();
+
+        let mut peer = peer.lock();
+        match self.ty {
+            UnixSocketType::Stream => {
+                // Only this socket sends to the queue of its peer, so a closed
+                // sending side means it has been shut down locally.
+                if peer.rx_closed || peer.tx_closed {
+                    return Err(LinuxError::EPIPE);
+                }
+                if buf.is_empty() {
+                    return Ok(0);
+                }
+                let len = peer.available().min(buf.len());
+                if len == 0 {
+                    return Err(LinuxError::EAGAIN);
+                }
+                peer.push(buf[..len].to_vec(), from, mem::take(ancillary));
+                Ok(len)
+            }
+            UnixSocketType::Datagram => {
+                if peer.rx_closed {
+                    return Err(LinuxError::ECONNREFUSED);
+                }
+                if buf.len() > UNIX_BUF_SIZE {
+                    return Err(LinuxError::EMSGSIZE);
+                }
+                if peer.available() < buf.len() {
+                    return Err(LinuxError::EAGAIN);
+                }
+                peer.push(buf.to_vec(), from, mem::take(ancillary));
+                Ok(buf.len())
+            }
+        }
+    }
+
+    /// Receive data, returning the length of the received data, the
+    /// address of the sender and the ancillary data attached to it.
+    ///
+    /// For datagram sockets the returned length is the full length of the
+    /// message, which may exceed `buf.len()`. Stream reads never merge data
+    /// of different senders, and stop after data carrying files. With `peek`
+    /// set, the data is left in the queue. Fails with `EAGAIN` if no data is
+    /// available yet.
+    pub fn recv(
+        &self,
+        buf: &mut [u8],
+        peek: bool,
+    ) -> LinuxResult<(usize, UnixSocketAddr, UnixAncillary)> {
+        if self.ty == UnixSocketType::Stream {
+            match &*self.state.lock() {
+                UnixState::Connected { .. } => {}
+                UnixState::Listening { .. } => return Err(LinuxError::EINVAL),
+                UnixState::Unconnected => return Err(LinuxError::ENOTCONN),
+            }
+        }
+
+        let mut queue = self.queue.lock();
+        let Some(first) = queue.messages.front() else {
+            if queue.tx_closed || queue.rx_closed {
+                return Ok((0, UnixSocketAddr::Unnamed, UnixAncillary::default()));
+            }
+            return Err(LinuxError::EAGAIN);
+        };
+        let from = first.from.clone();
+        let cred = first.cred;
+        let take_rights = |msg: &mut UnixMessage| {
+            if peek {
+                msg.rights.clone()
+            } else {
+                mem::take(&mut msg.rights)
+            }
+        };
+
+        match self.ty {
+            UnixSocketType::Stream => {
+                let mut read = 0;
+                let mut consumed = 0;
+                let mut rights = Vec::new();
+                for msg in queue.messages.iter_mut() {
+                    if read == buf.len()
+                        || (read > 0 && (!msg.rights.is_empty() || !same_cred(&msg.cred, &cred)))
+                    {
+                        break;
+                    }
+                    let len = (msg.data.len() - msg.pos).min(buf.len() - read);
+                    buf[read..read + len].copy_from_slice(&msg.data[msg.pos..msg.pos + len]);
+                    read += len;
+                    let has_rights = !msg.rights.is_empty();
+                    if has_rights {
+                        rights = take_rights(msg);
+                    }
+                    if !peek {
+                        msg.pos += len;
+                        if msg.pos == msg.data.len() {
+                            consumed += 1;
+                        }
+                    }
+                    if has_rights {
+                        break;
+                    }
+                }
+                if !peek {
+                    queue.messages.drain(..consumed);
+                    queue.len -= read;
+                    queue.writers.wake();
+                }
+                let ancillary = UnixAncillary {
+                    rights,
+                    cred: Some(cred),
+                };
+                Ok((read, from, ancillary))
+            }
+            UnixSocketType::Datagram => {
+                let msg = queue.messages.front_mut().unwrap();
+                let len = msg.data.len().min(buf.len());
+                buf[..len].copy_from_slice(&msg.data[..len]);
+                let full_len = msg.data.len();
+                let ancillary = UnixAncillary {
+                    rights: take_rights(msg),
+                    cred: Some(cred),
+                };
+                if !peek {
+                    queue.messages.pop_front();
+                    queue.len -= full_len;
+                    queue.writers.wake();
+                }
+                Ok((full_len, from, ancillary))
+            }
+        }
+    }
+
+    /// Shut down the receiving side of the socket if `read` is set, and the
+    /// sending side if `write` is set.
+    ///
+    /// Stream sockets propagate the shutdown to their peer, which sees
+    /// end-of-file or fails to send with `EPIPE`.
+    pub fn shutdown(&self, read: bool, write: bool) {
+        if read {
+            self.queue.lock().close(true, false);
+        }
+        if write && self.ty == UnixSocketType::Stream {
+            if let UnixState::Connected { peer, .. } = &*self.state.lock() {
+                peer.lock().close(false, true);
+            }
+        }
+    }
+
+    /// Whether no more data will arrive, because the socket or the peer
+    /// shut it down.
+    pub fn read_closed(&self) -> bool {
+        let queue = self.queue.lock();
+        queue.rx_closed || queue.tx_closed
+    }
+
+    /// Whether the connected peer can no longer receive data.
+    pub fn write_closed(&self) -> bool {
+        let peer = match &*self.state.lock() {
+            UnixState::Connected { peer, .. } if self.ty == UnixSocketType::Stream => peer.clone(),
+            _ => return false,
+        };
+        let peer = peer.lock();
+        peer.rx_closed || peer.tx_closed
+    }
+
+    pub fn poll(&self) -> PollState {
+        let peer = match &*self.state.lock() {
+            UnixState::Listening { backlog, .. } => {
+                return PollState {
+                    readable: !backlog.is_empty(),
+                    writable: false,
+                };
+            }
+            UnixState::Connected { peer, .. } => Some(peer.clone()),
+            UnixState::Unconnected => None,
+        };
+
+        let readable = {
+            let queue = self.queue.lock();
+            !queue.messages.is_empty() || queue.tx_closed || queue.rx_closed
+        };
+        // Never lock both queues at once, the peer may be polling as well.
+        let writable = match peer {
+            Some(peer) => {
+                let peer = peer.lock();
+                peer.available() > 0 || peer.rx_closed
+            }
+            None => self.ty == UnixSocketType::Datagram,
+        };
+        PollState { readable, writable }
+    }
+
+    /// Register `waiter` to be woken up when data or connections arrive, if
+    /// `read` is set, or when the peer has room for data, if `write` is set.
+    ///
+    /// Returns `false` if the socket cannot notify the waiter about room for
+    /// data, as connecting and datagrams, which may go to any socket, need
+    /// polling.
+    pub fn register(&self, waiter: &Arc<PollWaiter>, read: bool, write: bool) -> bool {
+        if read {
+            self.queue.lock().readers.register(waiter);
+        }
+        if !write {
+            return true;
+        }
+        let peer = match &*self.state.lock() {
+            UnixState::Connected { peer, .. } if self.ty == UnixSocketType::Stream => peer.clone(),
+            _ => return false,
+        };
+        peer.lock().writers.register(waiter);
+        true
+    }
+
+    /// Get the event sequence of the sets [`register`](Self::register)
+    /// registers with.
+    pub fn event_seq(&self, read: bool, write: bool) -> Option<u64> {
+        let mut seq = 0u64;
+        if read {
+            seq = seq.wrapping_add(self.queue.lock().readers.seq());
+        }
+        if write {
+            let peer = match &*self.state.lock() {
+                UnixState::Connected { peer, .. } if self.ty == UnixSocketType::Stream => {
+                    peer.clone()
+                }
+                _ => return None,
+            };
+            seq = seq.wrapping_add(peer.lock().writers.seq());
+        }
+        Some(seq)
+    }
+}
+
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        self.shutdown(true, true);
+        let local_addr = self.local_addr.get_mut();
+        if let UnixSocketAddr::Abstract(_) = local_addr {
+            let mut table = UNIX_TABLE.lock();
+            if table
+                .get(local_addr)
+                .is_some_and(|sock| sock.strong_count() == 0)
+            {
+                table.remove(local_addr);
+            }
+        }
+    }
+}
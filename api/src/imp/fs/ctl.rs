@@ -8,11 +8,12 @@ use axerrno::{LinuxError, LinuxResult};
 use axfs::fops::DirEntry;
 use linux_raw_sys::general::{
     AT_FDCWD, AT_REMOVEDIR, DT_BLK, DT_CHR, DT_DIR, DT_FIFO, DT_LNK, DT_REG, DT_SOCK, DT_UNKNOWN,
-    linux_dirent64,
+    IN_CREATE, IN_DELETE, RENAME_EXCHANGE, RENAME_NOREPLACE, RENAME_WHITEOUT, linux_dirent64,
 };
 
 use crate::{
-    file::{Directory, FileLike},
+    file::{Directory, FileLike, Pipe, Socket, notify_fs_event, notify_fs_move},
+    imp::{pipe_ioctl, socket_ioctl},
     path::{HARDLINK_MANAGER, handle_file_path},
     ptr::{UserConstPtr, UserPtr, nullable},
 };
@@ -25,7 +26,13 @@ use crate::{
 /// * `op` - The request code. It is of type unsigned long in glibc and BSD,
 ///   and of type int in musl and other UNIX systems.
 /// * `argp` - The argument to the request. It is a pointer to a memory location
-pub fn sys_ioctl(_fd: i32, _op: usize, _argp: UserPtr<c_void>) -> LinuxResult<isize> {
+pub fn sys_ioctl(fd: i32, op: usize, argp: UserPtr<c_void>) -> LinuxResult<isize> {
+    if let Ok(socket) = Socket::from_fd(fd) {
+        return socket_ioctl(&socket, op as u32, argp);
+    }
+    if let Ok(pipe) = Pipe::from_fd(fd) {
+        return pipe_ioctl(&pipe, op as u32, argp);
+    }
     warn!("Unimplemented syscall: SYS_IOCTL");
     Ok(0)
 }
@@ -51,6 +58,7 @@ pub fn sys_mkdirat(dirfd: i32, path: UserConstPtr<c_char>, mode: u32) -> LinuxRe
 
     let path = handle_file_path(dirfd, path)?;
     axfs::api::create_dir(path.as_str())?;
+    notify_fs_event(path.as_str(), IN_CREATE, true);
 
     Ok(0)
 }
@@ -196,6 +204,7 @@ pub fn sys_linkat(
     let new_path = handle_file_path(new_dirfd, new_path)?;
 
     HARDLINK_MANAGER.create_link(&new_path, &old_path)?;
+    notify_fs_event(new_path.as_str(), IN_CREATE, false);
 
     Ok(0)
 }
@@ -223,6 +232,7 @@ pub fn sys_unlinkat(dirfd: c_int, path: UserConstPtr<c_char>, flags: u32) -> Lin
 
     if flags == AT_REMOVEDIR {
         axfs::api::remove_dir(path.as_str())?;
+        notify_fs_event(path.as_str(), IN_DELETE, true);
     } else {
         let metadata = axfs::api::metadata(path.as_str())?;
         if metadata.is_dir() {
@@ -232,6 +242,7 @@ pub fn sys_unlinkat(dirfd: c_int, path: UserConstPtr<c_char>, flags: u32) -> Lin
             HARDLINK_MANAGER
                 .remove_link(&path)
                 .ok_or(LinuxError::ENOENT)?;
+            notify_fs_event(path.as_str(), IN_DELETE, false);
         }
     }
     Ok(0)
@@ -241,6 +252,60 @@ pub fn sys_unlink(path: UserConstPtr<c_char>) -> LinuxResult<isize> {
     sys_unlinkat(AT_FDCWD, path, 0)
 }
 
+/// Rename the file at `old_path` to `new_path`, replacing the file there
+/// unless `RENAME_NOREPLACE` is set.
+pub fn sys_renameat2(
+    old_dirfd: c_int,
+    old_path: UserConstPtr<c_char>,
+    new_dirfd: c_int,
+    new_path: UserConstPtr<c_char>,
+    flags: u32,
+) -> LinuxResult<isize> {
+    let old_path = old_path.get_as_str()?;
+    let new_path = new_path.get_as_str()?;
+    debug!(
+        "sys_renameat2 <= old_dirfd: {}, old_path: {}, new_dirfd: {}, new_path: {}, flags: {:#x}",
+        old_dirfd, old_path, new_dirfd, new_path, flags
+    );
+
+    if flags & !(RENAME_NOREPLACE | RENAME_EXCHANGE | RENAME_WHITEOUT) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    if flags & (RENAME_EXCHANGE | RENAME_WHITEOUT) != 0 {
+        warn!("sys_renameat2: RENAME_EXCHANGE and RENAME_WHITEOUT are not supported");
+        return Err(LinuxError::EINVAL);
+    }
+
+    let old_path = handle_file_path(old_dirfd, old_path)?;
+    let new_path = handle_file_path(new_dirfd, new_path)?;
+    if flags & RENAME_NOREPLACE != 0 && new_path.exists() {
+        return Err(LinuxError::EEXIST);
+    }
+    let is_dir = axfs::api::metadata(old_path.as_str())?.is_dir();
+    axfs::api::rename(old_path.as_str(), new_path.as_str())?;
+    notify_fs_move(old_path.as_str(), new_path.as_str(), is_dir);
+
+    Ok(0)
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub fn sys_renameat(
+    old_dirfd: c_int,
+    old_path: UserConstPtr<c_char>,
+    new_dirfd: c_int,
+    new_path: UserConstPtr<c_char>,
+) -> LinuxResult<isize> {
+    sys_renameat2(old_dirfd, old_path, new_dirfd, new_path, 0)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_rename(
+    old_path: UserConstPtr<c_char>,
+    new_path: UserConstPtr<c_char>,
+) -> LinuxResult<isize> {
+    sys_renameat2(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+
 pub fn sys_getcwd(buf: UserPtr<u8>, size: usize) -> LinuxResult<isize> {
     let buf = nullable!(buf.get_as_mut_slice(size))?;
 
@@ -0,0 +1,21 @@
+use axerrno::{LinuxError, LinuxResult};
+use linux_raw_sys::general::{EFD_CLOEXEC, EFD_NONBLOCK, EFD_SEMAPHORE};
+
+use crate::file::{EventFd, FileLike};
+
+/// Create an event counter with the initial value `initval`.
+pub fn sys_eventfd2(initval: u32, flags: u32) -> LinuxResult<isize> {
+    debug!("sys_eventfd2 <= initval: {}, flags: {:#x}", initval, flags);
+    if flags & !(EFD_SEMAPHORE | EFD_NONBLOCK | EFD_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let eventfd = EventFd::new(initval as u64, flags & EFD_SEMAPHORE != 0);
+    if flags & EFD_NONBLOCK != 0 {
+        eventfd.set_nonblocking(true)?;
+    }
+    if flags & EFD_CLOEXEC != 0 {
+        // TODO: fd close-on-exec
+        warn!("sys_eventfd2: EFD_CLOEXEC is ignored");
+    }
+    Ok(eventfd.add_to_fd_table()? as _)
+}
@@ -3,16 +3,20 @@ use core::{
     panic,
 };
 
-use alloc::string::ToString;
+use alloc::{string::ToString, sync::Arc};
 use axerrno::{AxError, LinuxError, LinuxResult};
 use axfs::fops::OpenOptions;
 use linux_raw_sys::general::{
-    __kernel_mode_t, AT_FDCWD, F_DUPFD, F_DUPFD_CLOEXEC, F_SETFL, O_APPEND, O_CREAT, O_DIRECTORY,
-    O_NONBLOCK, O_PATH, O_RDONLY, O_TRUNC, O_WRONLY,
+    __kernel_mode_t, AT_FDCWD, F_DUPFD, F_DUPFD_CLOEXEC, F_GETPIPE_SZ, F_SETFL, F_SETPIPE_SZ,
+    IN_CREATE, IN_MODIFY, O_APPEND, O_CREAT, O_DIRECT, O_DIRECTORY, O_NONBLOCK, O_PATH, O_RDONLY,
+    O_TRUNC, O_WRONLY,
 };
 
 use crate::{
-    file::{Directory, FD_TABLE, File, FileLike, add_file_like, close_file_like, get_file_like},
+    file::{
+        Directory, FD_TABLE, File, FileLike, Pipe, add_file_like, close_file_like, get_file_like,
+        notify_fs_event,
+    },
     path::handle_file_path,
     ptr::UserConstPtr,
 };
@@ -72,6 +76,7 @@ pub fn sys_openat(
         Some(Directory::from_fd(dirfd)?)
     };
     let real_path = handle_file_path(dirfd, path)?;
+    let existed = real_path.exists();
 
     if !opts.has_directory() {
         match dir.as_ref().map_or_else(
@@ -80,7 +85,17 @@ pub fn sys_openat(
         ) {
             Err(AxError::IsADirectory) => {}
             r => {
-                let fd = File::new(r?, real_path.to_string()).add_to_fd_table()?;
+                let file = r?;
+                let flags = flags as u32;
+                let writable = flags & 0b11 != O_RDONLY;
+                if !existed {
+                    notify_fs_event(real_path.as_str(), IN_CREATE, false);
+                } else if writable && flags & O_TRUNC != 0 {
+                    notify_fs_event(real_path.as_str(), IN_MODIFY, false);
+                }
+                let fd = File::new(file, real_path.to_string())
+                    .notify_close(writable)
+                    .add_to_fd_table()?;
                 return Ok(fd as _);
             }
         }
@@ -144,6 +159,15 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> LinuxResult<isize> {
     Ok(new_fd as _)
 }
 
+/// Get the pipe `fd` refers to, failing with `EBADF` for other files as on
+/// Linux.
+fn pipe_from_fd(fd: c_int) -> LinuxResult<Arc<Pipe>> {
+    get_file_like(fd)?
+        .into_any()
+        .downcast::<Pipe>()
+        .map_err(|_| LinuxError::EBADF)
+}
+
 pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> LinuxResult<isize> {
     debug!("sys_fcntl <= fd: {} cmd: {} arg: {}", fd, cmd, arg);
 
@@ -157,9 +181,15 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> LinuxResult<isize> {
             if fd == 0 || fd == 1 || fd == 2 {
                 return Ok(0);
             }
-            get_file_like(fd)?.set_nonblocking(arg & (O_NONBLOCK as usize) > 0)?;
+            let file = get_file_like(fd)?;
+            file.set_nonblocking(arg & (O_NONBLOCK as usize) > 0)?;
+            if let Ok(pipe) = file.into_any().downcast::<Pipe>() {
+                pipe.set_packet_mode(arg & (O_DIRECT as usize) > 0);
+            }
             Ok(0)
         }
+        F_GETPIPE_SZ => Ok(pipe_from_fd(fd)?.size() as _),
+        F_SETPIPE_SZ => Ok(pipe_from_fd(fd)?.set_size(arg)? as _),
         _ => {
             warn!("unsupported fcntl parameters: cmd: {}", cmd);
             Ok(0)
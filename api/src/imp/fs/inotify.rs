@@ -0,0 +1,64 @@
+use core::ffi::c_char;
+
+use axerrno::{LinuxError, LinuxResult};
+use linux_raw_sys::general::{
+    AT_FDCWD, IN_ALL_EVENTS, IN_CLOEXEC, IN_MASK_ADD, IN_MASK_CREATE, IN_NONBLOCK, IN_ONLYDIR,
+};
+
+use crate::{
+    file::{FileLike, Inotify, add_file_like},
+    path::handle_file_path,
+    ptr::UserConstPtr,
+};
+
+/// Create an inotify instance.
+pub fn sys_inotify_init1(flags: u32) -> LinuxResult<isize> {
+    debug!("sys_inotify_init1 <= flags: {:#x}", flags);
+    if flags & !(IN_NONBLOCK | IN_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let inotify = Inotify::new();
+    if flags & IN_NONBLOCK != 0 {
+        inotify.set_nonblocking(true)?;
+    }
+    if flags & IN_CLOEXEC != 0 {
+        // TODO: fd close-on-exec
+        warn!("sys_inotify_init1: IN_CLOEXEC is ignored");
+    }
+    Ok(add_file_like(inotify)? as _)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_inotify_init() -> LinuxResult<isize> {
+    sys_inotify_init1(0)
+}
+
+/// Watch the file at `path` for the events in `mask`, returning the watch
+/// descriptor.
+pub fn sys_inotify_add_watch(fd: i32, path: UserConstPtr<c_char>, mask: u32) -> LinuxResult<isize> {
+    let path = path.get_as_str()?;
+    debug!(
+        "sys_inotify_add_watch <= fd: {}, path: {:?}, mask: {:#x}",
+        fd, path, mask
+    );
+    let inotify = Inotify::from_fd(fd)?;
+    if mask & IN_ALL_EVENTS == 0
+        || mask & (IN_MASK_ADD | IN_MASK_CREATE) == IN_MASK_ADD | IN_MASK_CREATE
+    {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let path = handle_file_path(AT_FDCWD, path)?;
+    let metadata = axfs::api::metadata(path.as_str())?;
+    if mask & IN_ONLYDIR != 0 && !metadata.is_dir() {
+        return Err(LinuxError::ENOTDIR);
+    }
+    Ok(inotify.add_watch(path.as_str(), mask)? as _)
+}
+
+/// Remove the watch `wd` from an inotify instance.
+pub fn sys_inotify_rm_watch(fd: i32, wd: i32) -> LinuxResult<isize> {
+    debug!("sys_inotify_rm_watch <= fd: {}, wd: {}", fd, wd);
+    Inotify::from_fd(fd)?.rm_watch(wd)?;
+    Ok(0)
+}
@@ -1,13 +1,19 @@
 mod ctl;
+mod eventfd;
 mod fd_ops;
+mod inotify;
 mod io;
 mod mount;
 mod pipe;
+mod splice;
 mod stat;
 
 pub use self::ctl::*;
+pub use self::eventfd::*;
 pub use self::fd_ops::*;
+pub use self::inotify::*;
 pub use self::io::*;
 pub use self::mount::*;
 pub use self::pipe::*;
+pub use self::splice::*;
 pub use self::stat::*;
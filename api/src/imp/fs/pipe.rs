@@ -1,6 +1,10 @@
-use core::ffi::c_int;
+use core::ffi::{c_int, c_void};
 
-use axerrno::LinuxResult;
+use axerrno::{LinuxError, LinuxResult};
+use linux_raw_sys::{
+    general::{O_DIRECT, O_NONBLOCK},
+    ioctl::{FIONBIO, FIONREAD},
+};
 
 use crate::{
     file::{FileLike, Pipe, close_file_like},
@@ -8,13 +12,20 @@ use crate::{
 };
 
 pub fn sys_pipe2(fds: UserPtr<[c_int; 2]>, flags: i32) -> LinuxResult<isize> {
-    if flags != 0 {
+    if flags as u32 & !(O_NONBLOCK | O_DIRECT) != 0 {
         warn!("sys_pipe2: unsupported flags: {}", flags);
     }
 
     let fds = fds.get_as_mut()?;
 
     let (read_end, write_end) = Pipe::new();
+    if flags as u32 & O_NONBLOCK != 0 {
+        read_end.set_nonblocking(true)?;
+        write_end.set_nonblocking(true)?;
+    }
+    if flags as u32 & O_DIRECT != 0 {
+        write_end.set_packet_mode(true);
+    }
     let read_fd = read_end.add_to_fd_table()?;
     let write_fd = write_end
         .add_to_fd_table()
@@ -26,3 +37,21 @@ pub fn sys_pipe2(fds: UserPtr<[c_int; 2]>, flags: i32) -> LinuxResult<isize> {
     info!("sys_pipe2 <= fds: {:?}", fds);
     Ok(0)
 }
+
+/// Handle an `ioctl` on a pipe file descriptor.
+///
+/// `FIONREAD` reports the number of bytes that can be read from the pipe.
+pub(crate) fn pipe_ioctl(pipe: &Pipe, op: u32, argp: UserPtr<c_void>) -> LinuxResult<isize> {
+    let arg = UserPtr::<c_int>::from(argp.address().as_usize());
+    match op {
+        FIONREAD => {
+            *arg.get_as_mut()? = pipe.available_read() as _;
+            Ok(0)
+        }
+        FIONBIO => {
+            pipe.set_nonblocking(*arg.get_as_mut()? != 0)?;
+            Ok(0)
+        }
+        _ => Err(LinuxError::ENOTTY),
+    }
+}
@@ -0,0 +1,410 @@
+use core::ffi::c_int;
+
+use alloc::{sync::Arc, vec};
+use axerrno::{LinuxError, LinuxResult};
+use axio::SeekFrom;
+use linux_raw_sys::{
+    general::{__kernel_loff_t, IN_MODIFY, POLLOUT, SPLICE_F_NONBLOCK, iovec},
+    net::MSG_DONTWAIT,
+};
+
+use crate::{
+    file::{
+        Directory, File, FileLike, Pipe, Socket, UnixAncillary, get_file_like, notify_fs_event,
+        wait_event,
+    },
+    ptr::{UserConstPtr, UserPtr},
+};
+
+/// Maximum length moved by one call, as on Linux.
+const MAX_RW_COUNT: usize = i32::MAX as usize & !4095;
+/// Size of the kernel buffer data is moved through.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A file descriptor data is moved from or to inside the kernel.
+enum Endpoint {
+    /// A regular file, accessed at the offset if given, or at the file
+    /// position otherwise.
+    File(Arc<File>, Option<u64>),
+    Pipe(Arc<Pipe>),
+    Socket(Arc<Socket>),
+    Other(Arc<dyn FileLike>),
+}
+
+impl Endpoint {
+    /// Get the endpoint for `fd`, only regular files can be accessed at an
+    /// `offset`.
+    fn new(fd: c_int, offset: Option<u64>) -> LinuxResult<Self> {
+        let f = get_file_like(fd)?;
+        let any = f.clone().into_any();
+        if any.is::<Directory>() {
+            return Err(LinuxError::EISDIR);
+        }
+        if let Ok(file) = any.clone().downcast::<File>() {
+            return Ok(Self::File(file, offset));
+        }
+        if offset.is_some() {
+            return Err(LinuxError::ESPIPE);
+        }
+        Ok(if let Ok(pipe) = any.clone().downcast::<Pipe>() {
+            Self::Pipe(pipe)
+        } else if let Ok(socket) = any.downcast::<Socket>() {
+            Self::Socket(socket)
+        } else {
+            Self::Other(f)
+        })
+    }
+
+    fn file_like(&self) -> &dyn FileLike {
+        match self {
+            Self::File(file, _) => file.as_ref(),
+            Self::Pipe(pipe) => pipe.as_ref(),
+            Self::Socket(socket) => socket.as_ref(),
+            Self::Other(f) => f.as_ref(),
+        }
+    }
+
+    fn pipe(&self) -> Option<&Pipe> {
+        match self {
+            Self::Pipe(pipe) => Some(pipe),
+            _ => None,
+        }
+    }
+
+    fn offset(&self) -> Option<u64> {
+        match self {
+            Self::File(_, offset) => *offset,
+            _ => None,
+        }
+    }
+
+    /// Whether data read by [`peek`](Self::peek) stays in place until it is
+    /// consumed.
+    fn can_peek(&self) -> bool {
+        matches!(self, Self::File(..) | Self::Pipe(_))
+    }
+
+    /// Read data into `buf`, without consuming it if the endpoint can peek.
+    fn peek(&self, buf: &mut [u8], nonblocking: bool) -> LinuxResult<usize> {
+        match self {
+            Self::File(file, offset) => {
+                let mut file = file.inner();
+                let pos = match offset {
+                    Some(offset) => *offset,
+                    None => file.seek(SeekFrom::Current(0))?,
+                };
+                Ok(file.read_at(pos, buf)?)
+            }
+            Self::Pipe(pipe) => pipe.read_data(buf, true, nonblocking || pipe.is_nonblocking()),
+            Self::Socket(socket) => {
+                let flags = if nonblocking { MSG_DONTWAIT } else { 0 };
+                let (len, ..) = socket.recv_msg(buf, flags)?;
+                Ok(len.min(buf.len()))
+            }
+            Self::Other(f) => f.read(buf),
+        }
+    }
+
+    /// Consume `len` bytes of the data returned by [`peek`](Self::peek).
+    fn consume(&mut self, len: usize) -> LinuxResult {
+        match self {
+            Self::File(_, Some(offset)) => *offset += len as u64,
+            Self::File(file, None) => {
+                file.inner().seek(SeekFrom::Current(len as i64))?;
+            }
+            Self::Pipe(pipe) => pipe.consume(len),
+            Self::Socket(_) | Self::Other(_) => {}
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8], nonblocking: bool) -> LinuxResult<usize> {
+        match self {
+            Self::File(file, Some(offset)) => {
+                let written = file.inner().write_at(*offset, buf)?;
+                if written > 0 {
+                    notify_fs_event(file.path(), IN_MODIFY, false);
+                }
+                *offset += written as u64;
+                Ok(written)
+            }
+            Self::File(file, None) => file.write(buf),
+            Self::Pipe(pipe) => pipe.write_data(buf, nonblocking || pipe.is_nonblocking()),
+            Self::Socket(socket) => {
+                let flags = if nonblocking { MSG_DONTWAIT } else { 0 };
+                socket.send_msg(buf, None, UnixAncillary::default(), flags)
+            }
+            Self::Other(f) => f.write(buf),
+        }
+    }
+}
+
+/// Move one chunk of at most `buf.len()` bytes from `src` to `dst`.
+///
+/// Reading waits for data only if `wait` is set, writing waits for room
+/// unless `nonblocking` is set.
+fn transfer_chunk(
+    src: &mut Endpoint,
+    dst: &mut Endpoint,
+    mut buf: &mut [u8],
+    wait: bool,
+    nonblocking: bool,
+) -> LinuxResult<usize> {
+    if !src.can_peek() {
+        // Data read from the source cannot be put back, so only read what
+        // fits into the pipe.
+        if let Some(pipe) = dst.pipe() {
+            let room = pipe.wait_room(!wait || nonblocking)?;
+            buf = &mut buf[..room.min(buf.len())];
+        }
+    }
+    let read = src.peek(buf, !wait || nonblocking)?;
+    if read == 0 {
+        return Ok(0);
+    }
+    if !src.can_peek() {
+        return write_all(dst, &buf[..read]);
+    }
+    let written = dst.write(&buf[..read], nonblocking)?;
+    src.consume(written)?;
+    Ok(written)
+}
+
+/// Write all of `buf`, which has already been taken from its source, to
+/// `dst`.
+///
+/// The data cannot be given back, so room is waited for even if the transfer
+/// is nonblocking, and the wait is not interrupted by signals.
+fn write_all(dst: &mut Endpoint, buf: &[u8]) -> LinuxResult<usize> {
+    let mut written = 0;
+    while written < buf.len() {
+        match dst.write(&buf[written..], true) {
+            Ok(0) => break,
+            Ok(len) => written += len,
+            Err(LinuxError::EAGAIN) => {
+                let dst = dst.file_like();
+                wait_event(None, false, |waiter| {
+                    waiter.watch(dst, POLLOUT);
+                    Ok(dst.poll()?.writable.then_some(()))
+                })?;
+            }
+            Err(_) if written > 0 => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(written)
+}
+
+/// Move up to `len` bytes from `src` to `dst` inside the kernel.
+///
+/// Like on Linux, reading only waits until some data has been moved, and
+/// errors after that end the transfer early.
+fn transfer(
+    src: &mut Endpoint,
+    dst: &mut Endpoint,
+    len: usize,
+    nonblocking: bool,
+) -> LinuxResult<usize> {
+    let len = len.min(MAX_RW_COUNT);
+    let mut buf = vec![0; len.min(CHUNK_SIZE)];
+    let mut moved = 0;
+    while moved < len {
+        let chunk = (len - moved).min(buf.len());
+        match transfer_chunk(src, dst, &mut buf[..chunk], moved == 0, nonblocking) {
+            Ok(0) => break,
+            Ok(written) => moved += written,
+            Err(_) if moved > 0 => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(moved)
+}
+
+/// Read an optional file offset from user space.
+fn read_offset(offset: UserPtr<__kernel_loff_t>) -> LinuxResult<Option<u64>> {
+    if offset.is_null() {
+        return Ok(None);
+    }
+    let offset = *offset.get_as_mut()?;
+    if offset < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Some(offset as u64))
+}
+
+/// Store the offset an endpoint has advanced to back to user space.
+fn write_offset(offset: UserPtr<__kernel_loff_t>, endpoint: &Endpoint) -> LinuxResult {
+    if let Some(value) = endpoint.offset() {
+        *offset.get_as_mut()? = value as _;
+    }
+    Ok(())
+}
+
+/// Copy data from `in_fd` to `out_fd` inside the kernel.
+///
+/// If `offset` is not null, `in_fd` is read from there and the offset is
+/// advanced instead of the file position.
+pub fn sys_sendfile(
+    out_fd: c_int,
+    in_fd: c_int,
+    offset: UserPtr<__kernel_loff_t>,
+    count: usize,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_sendfile <= out_fd: {}, in_fd: {}, count: {}",
+        out_fd, in_fd, count
+    );
+    let mut src = Endpoint::new(in_fd, read_offset(offset)?)?;
+    let mut dst = Endpoint::new(out_fd, None)?;
+    if matches!(src, Endpoint::Socket(_)) {
+        return Err(LinuxError::EINVAL);
+    }
+    let moved = transfer(&mut src, &mut dst, count, false)?;
+    write_offset(offset, &src)?;
+    Ok(moved as _)
+}
+
+/// Move data between a pipe and another file descriptor inside the kernel.
+pub fn sys_splice(
+    fd_in: c_int,
+    off_in: UserPtr<__kernel_loff_t>,
+    fd_out: c_int,
+    off_out: UserPtr<__kernel_loff_t>,
+    len: usize,
+    flags: u32,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_splice <= fd_in: {}, fd_out: {}, len: {}, flags: {:#x}",
+        fd_in, fd_out, len, flags
+    );
+    let mut src = Endpoint::new(fd_in, read_offset(off_in)?)?;
+    let mut dst = Endpoint::new(fd_out, read_offset(off_out)?)?;
+    match (src.pipe(), dst.pipe()) {
+        (None, None) => return Err(LinuxError::EINVAL),
+        (Some(src), Some(dst)) if src.same_pipe(dst) => return Err(LinuxError::EINVAL),
+        _ => {}
+    }
+    if src.pipe().is_some_and(|pipe| !pipe.readable())
+        || dst.pipe().is_some_and(|pipe| !pipe.writable())
+    {
+        return Err(LinuxError::EBADF);
+    }
+    let nonblocking = flags & SPLICE_F_NONBLOCK != 0;
+    let moved = transfer(&mut src, &mut dst, len, nonblocking)?;
+    write_offset(off_in, &src)?;
+    write_offset(off_out, &dst)?;
+    Ok(moved as _)
+}
+
+/// Duplicate data from one pipe to another without consuming it.
+pub fn sys_tee(fd_in: c_int, fd_out: c_int, len: usize, flags: u32) -> LinuxResult<isize> {
+    debug!(
+        "sys_tee <= fd_in: {}, fd_out: {}, len: {}, flags: {:#x}",
+        fd_in, fd_out, len, flags
+    );
+    let (Endpoint::Pipe(src), Endpoint::Pipe(dst)) =
+        (Endpoint::new(fd_in, None)?, Endpoint::new(fd_out, None)?)
+    else {
+        return Err(LinuxError::EINVAL);
+    };
+    if src.same_pipe(&dst) || !src.readable() || !dst.writable() {
+        return Err(LinuxError::EINVAL);
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+    let nonblocking = flags & SPLICE_F_NONBLOCK != 0;
+    let room = dst.wait_room(nonblocking || dst.is_nonblocking())?;
+    let mut buf = vec![0; len.min(room).min(CHUNK_SIZE)];
+    let read = src.read_data(&mut buf, true, nonblocking || src.is_nonblocking())?;
+    Ok(dst.write_data(&buf[..read], true)? as _)
+}
+
+/// Move user memory into a pipe, or data from a pipe into user memory.
+pub fn sys_vmsplice(
+    fd: c_int,
+    iov: UserConstPtr<iovec>,
+    nr_segs: usize,
+    flags: u32,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_vmsplice <= fd: {}, nr_segs: {}, flags: {:#x}",
+        fd, nr_segs, flags
+    );
+    if nr_segs > 1024 {
+        return Err(LinuxError::EINVAL);
+    }
+    let Endpoint::Pipe(pipe) = Endpoint::new(fd, None)? else {
+        return Err(LinuxError::EBADF);
+    };
+    let nonblocking = flags & SPLICE_F_NONBLOCK != 0 || pipe.is_nonblocking();
+
+    let mut moved = 0;
+    for iov in iov.get_as_slice(nr_segs)? {
+        if iov.iov_len == 0 {
+            continue;
+        }
+        // Like transfers, only wait until some data has been moved.
+        let nonblocking = nonblocking || moved > 0;
+        let res = if pipe.writable() {
+            let buf = UserConstPtr::<u8>::from(iov.iov_base as usize);
+            pipe.write_data(buf.get_as_slice(iov.iov_len as _)?, nonblocking)
+        } else {
+            let buf = UserPtr::<u8>::from(iov.iov_base as usize);
+            pipe.read_data(buf.get_as_mut_slice(iov.iov_len as _)?, false, nonblocking)
+        };
+        match res {
+            Ok(len) => {
+                moved += len;
+                if len < iov.iov_len as usize {
+                    break;
+                }
+            }
+            Err(_) if moved > 0 => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(moved as _)
+}
+
+/// Copy a range of data from one regular file to another inside the kernel.
+pub fn sys_copy_file_range(
+    fd_in: c_int,
+    off_in: UserPtr<__kernel_loff_t>,
+    fd_out: c_int,
+    off_out: UserPtr<__kernel_loff_t>,
+    len: usize,
+    flags: u32,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_copy_file_range <= fd_in: {}, fd_out: {}, len: {}, flags: {:#x}",
+        fd_in, fd_out, len, flags
+    );
+    if flags != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut src = Endpoint::new(fd_in, read_offset(off_in)?)?;
+    let mut dst = Endpoint::new(fd_out, read_offset(off_out)?)?;
+    let (Endpoint::File(src_file, _), Endpoint::File(dst_file, _)) = (&src, &dst) else {
+        return Err(LinuxError::EINVAL);
+    };
+    if src_file.path() == dst_file.path() {
+        // Copying within a file must not overlap.
+        let src_pos = match src.offset() {
+            Some(offset) => offset,
+            None => src_file.inner().seek(SeekFrom::Current(0))?,
+        };
+        let dst_pos = match dst.offset() {
+            Some(offset) => offset,
+            None => dst_file.inner().seek(SeekFrom::Current(0))?,
+        };
+        let len = len as u64;
+        if src_pos < dst_pos.saturating_add(len) && dst_pos < src_pos.saturating_add(len) {
+            return Err(LinuxError::EINVAL);
+        }
+    }
+    let moved = transfer(&mut src, &mut dst, len, false)?;
+    write_offset(off_in, &src)?;
+    write_offset(off_out, &dst)?;
+    Ok(moved as _)
+}
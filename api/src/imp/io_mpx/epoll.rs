@@ -0,0 +1,152 @@
+use core::{ffi::c_int, time::Duration};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axsignal::SignalSet;
+use linux_raw_sys::general::{
+    EPOLL_CLOEXEC, EPOLL_CTL_ADD, EPOLL_CTL_DEL, EPOLL_CTL_MOD, POLLIN, epoll_event, timespec,
+};
+
+use crate::{
+    file::{Epoll, FileLike, get_file_like, wait_event},
+    ptr::{UserConstPtr, UserPtr, nullable},
+    signal::with_sigmask,
+    time::TimeValueLike,
+};
+
+/// Maximum number of events returned by one wait, as on Linux.
+const EP_MAX_EVENTS: usize = i32::MAX as usize / size_of::<epoll_event>();
+
+/// Create an epoll instance.
+pub fn sys_epoll_create1(flags: u32) -> LinuxResult<isize> {
+    debug!("sys_epoll_create1 <= flags: {:#x}", flags);
+    if flags & !EPOLL_CLOEXEC != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Epoll::new().add_to_fd_table()? as _)
+}
+
+/// Create an epoll instance, `size` is only checked to be positive.
+#[cfg(target_arch = "x86_64")]
+pub fn sys_epoll_create(size: i32) -> LinuxResult<isize> {
+    if size <= 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    sys_epoll_create1(0)
+}
+
+/// Add, modify or remove an entry of the interest list of `epfd`.
+pub fn sys_epoll_ctl(
+    epfd: c_int,
+    op: u32,
+    fd: c_int,
+    event: UserConstPtr<epoll_event>,
+) -> LinuxResult<isize> {
+    debug!("sys_epoll_ctl <= epfd: {}, op: {}, fd: {}", epfd, op, fd);
+    let epoll = Epoll::from_fd(epfd)?;
+    let file = get_file_like(fd)?;
+    match op {
+        EPOLL_CTL_ADD => {
+            let event = *event.get_as_ref()?;
+            epoll.add(fd, file, event.events, event.data)?;
+        }
+        EPOLL_CTL_MOD => {
+            let event = *event.get_as_ref()?;
+            epoll.modify(fd, &file, event.events, event.data)?;
+        }
+        EPOLL_CTL_DEL => epoll.remove(fd, &file)?,
+        _ => return Err(LinuxError::EINVAL),
+    }
+    Ok(0)
+}
+
+/// Wait until events are ready on `epfd`, `timeout` has elapsed or a signal
+/// arrives, with the signal mask replaced by `sigmask` while waiting.
+fn do_epoll_wait(
+    epfd: c_int,
+    events: UserPtr<epoll_event>,
+    maxevents: i32,
+    timeout: Option<Duration>,
+    sigmask: UserConstPtr<SignalSet>,
+    sigsetsize: usize,
+) -> LinuxResult<isize> {
+    if maxevents <= 0 || maxevents as usize > EP_MAX_EVENTS {
+        return Err(LinuxError::EINVAL);
+    }
+    let events = events.get_as_mut_slice(maxevents as usize)?;
+    let epoll = Epoll::from_fd(epfd)?;
+    let sigmask = nullable!(sigmask.get_as_ref())?.copied();
+    if sigmask.is_some() && sigsetsize != size_of::<SignalSet>() {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+    let count = with_sigmask(sigmask, || {
+        wait_event(deadline, true, |waiter| {
+            waiter.watch(&*epoll, POLLIN);
+            let count = epoll.collect(events);
+            Ok((count > 0).then_some(count))
+        })
+    })?;
+    Ok(count.unwrap_or_default() as _)
+}
+
+/// Get a timeout in milliseconds, negative meaning forever.
+fn millis_timeout(timeout: i32) -> Option<Duration> {
+    (timeout >= 0).then(|| Duration::from_millis(timeout as u64))
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_epoll_wait(
+    epfd: c_int,
+    events: UserPtr<epoll_event>,
+    maxevents: i32,
+    timeout: i32,
+) -> LinuxResult<isize> {
+    do_epoll_wait(
+        epfd,
+        events,
+        maxevents,
+        millis_timeout(timeout),
+        UserConstPtr::from(0),
+        0,
+    )
+}
+
+pub fn sys_epoll_pwait(
+    epfd: c_int,
+    events: UserPtr<epoll_event>,
+    maxevents: i32,
+    timeout: i32,
+    sigmask: UserConstPtr<SignalSet>,
+    sigsetsize: usize,
+) -> LinuxResult<isize> {
+    do_epoll_wait(
+        epfd,
+        events,
+        maxevents,
+        millis_timeout(timeout),
+        sigmask,
+        sigsetsize,
+    )
+}
+
+/// Like [`sys_epoll_pwait`], with the timeout given as a `timespec`, null
+/// meaning forever.
+pub fn sys_epoll_pwait2(
+    epfd: c_int,
+    events: UserPtr<epoll_event>,
+    maxevents: i32,
+    timeout: UserConstPtr<timespec>,
+    sigmask: UserConstPtr<SignalSet>,
+    sigsetsize: usize,
+) -> LinuxResult<isize> {
+    let timeout = match nullable!(timeout.get_as_ref())? {
+        Some(ts) if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) => {
+            return Err(LinuxError::EINVAL);
+        }
+        Some(ts) => Some(ts.to_time_value()),
+        None => None,
+    };
+    do_epoll_wait(epfd, events, maxevents, timeout, sigmask, sigsetsize)
+}
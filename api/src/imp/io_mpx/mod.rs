@@ -0,0 +1,13 @@
+//! I/O multiplexing: `poll`, `select` and `epoll`, built on [`FileLike::poll`].
+//!
+//! Waiting threads sleep until woken up by the files they registered with
+//! through [`FileLike::register`].
+//!
+//! [`FileLike::poll`]: crate::file::FileLike::poll
+//! [`FileLike::register`]: crate::file::FileLike::register
+
+mod epoll;
+mod poll;
+mod select;
+
+pub use self::{epoll::*, poll::*, select::*};
@@ -0,0 +1,90 @@
+use core::time::Duration;
+
+use alloc::sync::Arc;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axsignal::SignalSet;
+use linux_raw_sys::general::{POLLERR, POLLHUP, POLLNVAL, pollfd, timespec};
+
+use crate::{
+    file::{AX_FILE_LIMIT, PollWaiter, get_file_like, poll_events, wait_event},
+    ptr::{UserConstPtr, UserPtr, nullable},
+    signal::with_sigmask,
+    time::TimeValueLike,
+};
+
+/// Fill in the `revents` of `fds`, returning the number of ready entries.
+///
+/// `waiter` is registered with the files before they are checked.
+fn poll_once(fds: &mut [pollfd], waiter: &Arc<PollWaiter>) -> usize {
+    let mut ready = 0;
+    for fd in fds {
+        fd.revents = 0;
+        if fd.fd < 0 {
+            continue;
+        }
+        // Errors and hangups are reported even if not requested.
+        let events = fd.events as u16 as u32 | POLLERR | POLLHUP;
+        let file = get_file_like(fd.fd).inspect(|file| waiter.watch(&**file, events));
+        let revents = match file.and_then(poll_events) {
+            Ok(ready) => ready & events,
+            Err(_) => POLLNVAL,
+        };
+        fd.revents = revents as _;
+        ready += (revents != 0) as usize;
+    }
+    ready
+}
+
+/// Wait until some of `fds` are ready, `timeout` has elapsed or a signal
+/// arrives, and return the number of ready entries.
+fn do_poll(fds: &mut [pollfd], timeout: Option<Duration>) -> LinuxResult<usize> {
+    let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+    let ready = wait_event(deadline, true, |waiter| {
+        let ready = poll_once(fds, waiter);
+        Ok((ready > 0).then_some(ready))
+    })?;
+    Ok(ready.unwrap_or_default())
+}
+
+fn get_fds(fds: UserPtr<pollfd>, nfds: usize) -> LinuxResult<&'static mut [pollfd]> {
+    if nfds > AX_FILE_LIMIT {
+        return Err(LinuxError::EINVAL);
+    }
+    fds.get_as_mut_slice(nfds)
+}
+
+/// Wait for events on the file descriptors in `fds`, for at most `timeout`
+/// milliseconds, or forever if it is negative.
+#[cfg(target_arch = "x86_64")]
+pub fn sys_poll(fds: UserPtr<pollfd>, nfds: usize, timeout: i32) -> LinuxResult<isize> {
+    debug!("sys_poll <= nfds: {}, timeout: {}", nfds, timeout);
+    let fds = get_fds(fds, nfds)?;
+    let timeout = (timeout >= 0).then(|| Duration::from_millis(timeout as u64));
+    Ok(do_poll(fds, timeout)? as _)
+}
+
+/// Like [`sys_poll`], with the timeout given as a `timespec` and the signal
+/// mask replaced by `sigmask` while waiting.
+pub fn sys_ppoll(
+    fds: UserPtr<pollfd>,
+    nfds: usize,
+    timeout: UserConstPtr<timespec>,
+    sigmask: UserConstPtr<SignalSet>,
+    sigsetsize: usize,
+) -> LinuxResult<isize> {
+    debug!("sys_ppoll <= nfds: {}", nfds);
+    let fds = get_fds(fds, nfds)?;
+    let timeout = match nullable!(timeout.get_as_ref())? {
+        Some(ts) if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) => {
+            return Err(LinuxError::EINVAL);
+        }
+        Some(ts) => Some(ts.to_time_value()),
+        None => None,
+    };
+    let sigmask = nullable!(sigmask.get_as_ref())?.copied();
+    if sigmask.is_some() && sigsetsize != size_of::<SignalSet>() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(with_sigmask(sigmask, || do_poll(fds, timeout))? as _)
+}
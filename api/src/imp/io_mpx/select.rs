@@ -0,0 +1,200 @@
+use core::time::Duration;
+
+use alloc::vec::Vec;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axsignal::SignalSet;
+use linux_raw_sys::general::{
+    POLLERR, POLLHUP, POLLIN, POLLOUT, POLLPRI, POLLRDNORM, POLLWRNORM, timespec, timeval,
+};
+
+use crate::{
+    file::{AX_FILE_LIMIT, get_file_like, poll_events, wait_event},
+    ptr::{UserConstPtr, UserPtr, nullable},
+    signal::with_sigmask,
+    time::TimeValueLike,
+};
+
+/// Bits in one word of a `fd_set`.
+const FD_SET_BITS: usize = usize::BITS as usize;
+
+/// Events that make a file ready for reading, writing and for exceptional
+/// conditions, as on Linux.
+const POLLIN_SET: u32 = POLLIN | POLLRDNORM | POLLHUP | POLLERR;
+const POLLOUT_SET: u32 = POLLOUT | POLLWRNORM | POLLERR;
+const POLLEX_SET: u32 = POLLPRI;
+
+/// A `fd_set` in user space, `None` for a null pointer.
+struct FdSet(Option<&'static mut [usize]>);
+
+impl FdSet {
+    fn new(set: UserPtr<usize>, nfds: usize) -> LinuxResult<Self> {
+        Ok(Self(nullable!(
+            set.get_as_mut_slice(nfds.div_ceil(FD_SET_BITS))
+        )?))
+    }
+
+    fn contains(&self, fd: usize) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|set| set[fd / FD_SET_BITS] & (1 << (fd % FD_SET_BITS)) != 0)
+    }
+
+    /// Replace the set by the file descriptors in `fds`.
+    fn set(&mut self, fds: &[usize]) {
+        let Some(set) = &mut self.0 else {
+            return;
+        };
+        set.fill(0);
+        for fd in fds {
+            set[fd / FD_SET_BITS] |= 1 << (fd % FD_SET_BITS);
+        }
+    }
+}
+
+/// Wait until some of the file descriptors below `nfds` in the sets are
+/// ready, `timeout` has elapsed or a signal arrives.
+///
+/// The sets are replaced by the ready file descriptors, and the total number
+/// of them is returned.
+fn do_select(nfds: usize, sets: &mut [FdSet; 3], timeout: Option<Duration>) -> LinuxResult<usize> {
+    let mut fds = Vec::new();
+    for fd in 0..nfds {
+        if sets.iter().any(|set| set.contains(fd)) {
+            fds.push((fd, get_file_like(fd as _)?));
+        }
+    }
+
+    let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+    let set_events = [POLLIN_SET, POLLOUT_SET, POLLEX_SET];
+    let ready = wait_event(deadline, true, |waiter| {
+        let mut ready: [Vec<usize>; 3] = Default::default();
+        for (fd, file) in &fds {
+            let wanted = (0..3)
+                .filter(|&i| sets[i].contains(*fd))
+                .fold(0, |wanted, i| wanted | set_events[i]);
+            waiter.watch(&**file, wanted);
+            let events = poll_events(file.clone())?;
+            for (i, mask) in set_events.iter().enumerate() {
+                if sets[i].contains(*fd) && events & mask != 0 {
+                    ready[i].push(*fd);
+                }
+            }
+        }
+        Ok(ready.iter().any(|ready| !ready.is_empty()).then_some(ready))
+    })?
+    .unwrap_or_default();
+    for (set, ready) in sets.iter_mut().zip(&ready) {
+        set.set(ready);
+    }
+    Ok(ready.iter().map(Vec::len).sum())
+}
+
+fn get_sets(
+    nfds: i32,
+    readfds: UserPtr<usize>,
+    writefds: UserPtr<usize>,
+    exceptfds: UserPtr<usize>,
+) -> LinuxResult<(usize, [FdSet; 3])> {
+    if nfds < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    // Like on Linux, file descriptors beyond the limit are ignored.
+    let nfds = (nfds as usize).min(AX_FILE_LIMIT);
+    Ok((
+        nfds,
+        [
+            FdSet::new(readfds, nfds)?,
+            FdSet::new(writefds, nfds)?,
+            FdSet::new(exceptfds, nfds)?,
+        ],
+    ))
+}
+
+/// Store the time left of `timeout` after waiting since `start` back, like
+/// Linux does.
+fn update_timeout<T: TimeValueLike + Copy>(timeout: Option<&mut T>, start: Duration) {
+    if let Some(timeout) = timeout {
+        let elapsed = monotonic_time() - start;
+        *timeout = T::from_time_value(timeout.to_time_value().saturating_sub(elapsed));
+    }
+}
+
+/// Wait until some of the file descriptors in `readfds`, `writefds` and
+/// `exceptfds` are ready.
+#[cfg(target_arch = "x86_64")]
+pub fn sys_select(
+    nfds: i32,
+    readfds: UserPtr<usize>,
+    writefds: UserPtr<usize>,
+    exceptfds: UserPtr<usize>,
+    timeout: UserPtr<timeval>,
+) -> LinuxResult<isize> {
+    debug!("sys_select <= nfds: {}", nfds);
+    let (nfds, mut sets) = get_sets(nfds, readfds, writefds, exceptfds)?;
+    let timeout = nullable!(timeout.get_as_mut())?;
+    if timeout
+        .as_ref()
+        .is_some_and(|tv| tv.tv_sec < 0 || !(0..1_000_000).contains(&tv.tv_usec))
+    {
+        return Err(LinuxError::EINVAL);
+    }
+    let start = monotonic_time();
+    let res = do_select(
+        nfds,
+        &mut sets,
+        timeout.as_deref().map(|tv| tv.to_time_value()),
+    );
+    update_timeout(timeout, start);
+    Ok(res? as _)
+}
+
+/// The sixth argument of `pselect6`, holding the signal mask and its size.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PselectSigmask {
+    sigmask: UserConstPtr<SignalSet>,
+    sigsetsize: usize,
+}
+
+/// Like [`sys_select`], with the timeout given as a `timespec` and the
+/// signal mask replaced while waiting.
+pub fn sys_pselect6(
+    nfds: i32,
+    readfds: UserPtr<usize>,
+    writefds: UserPtr<usize>,
+    exceptfds: UserPtr<usize>,
+    timeout: UserPtr<timespec>,
+    sigmask: UserConstPtr<PselectSigmask>,
+) -> LinuxResult<isize> {
+    debug!("sys_pselect6 <= nfds: {}", nfds);
+    let (nfds, mut sets) = get_sets(nfds, readfds, writefds, exceptfds)?;
+    let timeout = nullable!(timeout.get_as_mut())?;
+    if timeout
+        .as_ref()
+        .is_some_and(|ts| ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec))
+    {
+        return Err(LinuxError::EINVAL);
+    }
+    let sigmask = match nullable!(sigmask.get_as_ref())? {
+        Some(arg) => {
+            let ptr = arg.sigmask;
+            let sigmask = nullable!(ptr.get_as_ref())?.copied();
+            if sigmask.is_some() && arg.sigsetsize != size_of::<SignalSet>() {
+                return Err(LinuxError::EINVAL);
+            }
+            sigmask
+        }
+        None => None,
+    };
+    let start = monotonic_time();
+    let res = with_sigmask(sigmask, || {
+        do_select(
+            nfds,
+            &mut sets,
+            timeout.as_deref().map(|ts| ts.to_time_value()),
+        )
+    });
+    update_timeout(timeout, start);
+    Ok(res? as _)
+}
@@ -0,0 +1,507 @@
+use core::{ffi::c_int, mem};
+
+use alloc::{sync::Arc, vec::Vec};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{monotonic_time, wall_time};
+use axsignal::SignalSet;
+use axtask::{TaskExtRef, TaskInner, current};
+use linux_raw_sys::{
+    general::{__kernel_timespec, IN_MODIFY, iovec},
+    io_uring::{
+        IORING_ENTER_GETEVENTS, IORING_ENTER_SQ_WAIT, IORING_ENTER_SQ_WAKEUP, IORING_FEAT_CQE_SKIP,
+        IORING_FEAT_NATIVE_WORKERS, IORING_FEAT_RW_CUR_POS, IORING_FEAT_SUBMIT_STABLE,
+        IORING_POLL_ADD_MULTI, IORING_SETUP_CLAMP, IORING_SETUP_COOP_TASKRUN, IORING_SETUP_CQSIZE,
+        IORING_SETUP_SINGLE_ISSUER, IORING_SETUP_SUBMIT_ALL, IORING_TIMEOUT_ABS,
+        IORING_TIMEOUT_REALTIME, io_uring_op, io_uring_params, io_uring_register_op,
+        io_uring_sqe_flags_bit,
+    },
+    net::{sockaddr, socklen_t},
+};
+use num_enum::TryFromPrimitive;
+use starry_core::task::TaskExt;
+
+use crate::{
+    file::{
+        AX_FILE_LIMIT, EventFd, File, FileLike, IoUring, IoUringShared, Socket, Submission,
+        UnixAncillary, close_file_like, get_file_like, notify_fs_event, poll_events, run_as_worker,
+        wait_event,
+    },
+    imp::accept_socket,
+    ptr::{UserConstPtr, UserPtr, nullable},
+    signal::with_sigmask,
+    socket::{SocketAddrExt, SocketAddress},
+    time::TimeValueLike,
+};
+
+/// Maximum number of submission queue entries, as on Linux.
+const MAX_ENTRIES: u32 = 32768;
+/// Maximum number of completion queue entries, as on Linux.
+const MAX_CQ_ENTRIES: u32 = 2 * MAX_ENTRIES;
+
+const IOSQE_FIXED_FILE: u8 = 1 << io_uring_sqe_flags_bit::IOSQE_FIXED_FILE_BIT as u8;
+const IOSQE_IO_LINK: u8 = 1 << io_uring_sqe_flags_bit::IOSQE_IO_LINK_BIT as u8;
+const IOSQE_IO_HARDLINK: u8 = 1 << io_uring_sqe_flags_bit::IOSQE_IO_HARDLINK_BIT as u8;
+const IOSQE_ASYNC: u8 = 1 << io_uring_sqe_flags_bit::IOSQE_ASYNC_BIT as u8;
+const IOSQE_CQE_SKIP_SUCCESS: u8 = 1 << io_uring_sqe_flags_bit::IOSQE_CQE_SKIP_SUCCESS_BIT as u8;
+
+/// The request flags that are supported. Requests are always executed
+/// asynchronously, so `IOSQE_ASYNC` makes no difference.
+const SQE_FLAGS: u8 =
+    IOSQE_FIXED_FILE | IOSQE_IO_LINK | IOSQE_IO_HARDLINK | IOSQE_ASYNC | IOSQE_CQE_SKIP_SUCCESS;
+
+/// The supported request opcodes.
+#[derive(Debug, Clone, Copy, TryFromPrimitive)]
+#[repr(u8)]
+enum Opcode {
+    Nop = io_uring_op::IORING_OP_NOP as u8,
+    Readv = io_uring_op::IORING_OP_READV as u8,
+    Writev = io_uring_op::IORING_OP_WRITEV as u8,
+    Fsync = io_uring_op::IORING_OP_FSYNC as u8,
+    ReadFixed = io_uring_op::IORING_OP_READ_FIXED as u8,
+    WriteFixed = io_uring_op::IORING_OP_WRITE_FIXED as u8,
+    PollAdd = io_uring_op::IORING_OP_POLL_ADD as u8,
+    Timeout = io_uring_op::IORING_OP_TIMEOUT as u8,
+    Accept = io_uring_op::IORING_OP_ACCEPT as u8,
+    Connect = io_uring_op::IORING_OP_CONNECT as u8,
+    Close = io_uring_op::IORING_OP_CLOSE as u8,
+    Read = io_uring_op::IORING_OP_READ as u8,
+    Write = io_uring_op::IORING_OP_WRITE as u8,
+    Send = io_uring_op::IORING_OP_SEND as u8,
+    Recv = io_uring_op::IORING_OP_RECV as u8,
+}
+
+/// Get the file a request operates on.
+fn request_file(ring: &IoUringShared, sub: &Submission) -> LinuxResult<Arc<dyn FileLike>> {
+    if sub.flags & IOSQE_FIXED_FILE != 0 {
+        ring.fixed_file(sub.fd as _)
+    } else {
+        get_file_like(sub.fd)
+    }
+}
+
+fn request_socket(ring: &IoUringShared, sub: &Submission) -> LinuxResult<Arc<Socket>> {
+    request_file(ring, sub)?
+        .into_any()
+        .downcast::<Socket>()
+        .map_err(|_| LinuxError::ENOTSOCK)
+}
+
+/// Read into `buf` at `off`, or at the file position if `off` is `-1`.
+fn read_at(file: Arc<dyn FileLike>, off: u64, buf: &mut [u8]) -> LinuxResult<usize> {
+    match file.clone().into_any().downcast::<File>() {
+        Ok(file) if off != u64::MAX => Ok(file.inner().read_at(off, buf)?),
+        Err(_) if off != 0 && off != u64::MAX => Err(LinuxError::ESPIPE),
+        _ => file.read(buf),
+    }
+}
+
+/// Write `buf` at `off`, or at the file position if `off` is `-1`.
+fn write_at(file: Arc<dyn FileLike>, off: u64, buf: &[u8]) -> LinuxResult<usize> {
+    match file.clone().into_any().downcast::<File>() {
+        Ok(file) if off != u64::MAX => {
+            let written = file.inner().write_at(off, buf)?;
+            if written > 0 {
+                notify_fs_event(file.path(), IN_MODIFY, false);
+            }
+            Ok(written)
+        }
+        Err(_) if off != 0 && off != u64::MAX => Err(LinuxError::ESPIPE),
+        _ => file.write(buf),
+    }
+}
+
+/// Wait for `sub`, a `IORING_OP_TIMEOUT` request, to expire or for `count`
+/// completions to be posted since `completions`.
+fn timeout(ring: &IoUringShared, sub: &Submission, completions: u64) -> LinuxResult<isize> {
+    if sub.len != 1 || sub.op_flags & !(IORING_TIMEOUT_ABS | IORING_TIMEOUT_REALTIME) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let ts = UserConstPtr::<__kernel_timespec>::from(sub.addr as usize).get_as_ref()?;
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    let ts = ts.to_time_value();
+    let deadline = if sub.op_flags & IORING_TIMEOUT_ABS == 0 {
+        monotonic_time() + ts
+    } else if sub.op_flags & IORING_TIMEOUT_REALTIME != 0 {
+        monotonic_time() + ts.saturating_sub(wall_time())
+    } else {
+        ts
+    };
+
+    let target = (sub.off != 0).then(|| completions + sub.off);
+    let expired = wait_event(Some(deadline), false, |waiter| {
+        ring.register_waiter(waiter);
+        Ok(target
+            .is_some_and(|target| ring.completions() >= target)
+            .then_some(()))
+    })?;
+    match expired {
+        Some(()) => Ok(0),
+        None => Err(LinuxError::ETIME),
+    }
+}
+
+/// Execute a request, returning the result posted in its completion.
+///
+/// `completions` is the number of completions when the request was submitted.
+fn execute(ring: &IoUringShared, sub: &Submission, completions: u64) -> LinuxResult<isize> {
+    let opcode = Opcode::try_from(sub.opcode).map_err(|_| LinuxError::EINVAL)?;
+    debug!("io_uring: {:?} <= fd: {}", opcode, sub.fd);
+
+    match opcode {
+        Opcode::Nop => Ok(0),
+        Opcode::Read | Opcode::ReadFixed => {
+            if let Opcode::ReadFixed = opcode {
+                ring.check_fixed_buffer(sub.buf_index, sub.addr as _, sub.len as _)?;
+            }
+            let buf = UserPtr::<u8>::from(sub.addr as usize).get_as_mut_slice(sub.len as _)?;
+            Ok(read_at(request_file(ring, sub)?, sub.off, buf)? as _)
+        }
+        Opcode::Write | Opcode::WriteFixed => {
+            if let Opcode::WriteFixed = opcode {
+                ring.check_fixed_buffer(sub.buf_index, sub.addr as _, sub.len as _)?;
+            }
+            let buf = UserConstPtr::<u8>::from(sub.addr as usize).get_as_slice(sub.len as _)?;
+            Ok(write_at(request_file(ring, sub)?, sub.off, buf)? as _)
+        }
+        Opcode::Readv => {
+            let file = request_file(ring, sub)?;
+            let iovs = UserConstPtr::<iovec>::from(sub.addr as usize).get_as_slice(sub.len as _)?;
+            let mut off = sub.off;
+            let mut ret = 0;
+            for iov in iovs.iter().filter(|iov| iov.iov_len > 0) {
+                let buf = UserPtr::<u8>::from(iov.iov_base as usize)
+                    .get_as_mut_slice(iov.iov_len as _)?;
+                let read = read_at(file.clone(), off, buf)?;
+                ret += read;
+                if off != u64::MAX {
+                    off += read as u64;
+                }
+                if read < buf.len() {
+                    break;
+                }
+            }
+            Ok(ret as _)
+        }
+        Opcode::Writev => {
+            let file = request_file(ring, sub)?;
+            let iovs = UserConstPtr::<iovec>::from(sub.addr as usize).get_as_slice(sub.len as _)?;
+            let mut off = sub.off;
+            let mut ret = 0;
+            for iov in iovs.iter().filter(|iov| iov.iov_len > 0) {
+                let buf = UserConstPtr::<u8>::from(iov.iov_base as usize)
+                    .get_as_slice(iov.iov_len as _)?;
+                let written = write_at(file.clone(), off, buf)?;
+                ret += written;
+                if off != u64::MAX {
+                    off += written as u64;
+                }
+                if written < buf.len() {
+                    break;
+                }
+            }
+            Ok(ret as _)
+        }
+        Opcode::Fsync => {
+            let file = request_file(ring, sub)?
+                .into_any()
+                .downcast::<File>()
+                .map_err(|_| LinuxError::EINVAL)?;
+            file.inner().flush()?;
+            Ok(0)
+        }
+        Opcode::PollAdd => {
+            if sub.len & IORING_POLL_ADD_MULTI != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            let file = request_file(ring, sub)?;
+            let events = sub.op_flags;
+            let revents = wait_event(None, false, |waiter| {
+                waiter.watch(&*file, events);
+                let revents = poll_events(file.clone())? & events;
+                Ok((revents != 0).then_some(revents))
+            })?;
+            Ok(revents.unwrap_or_default() as _)
+        }
+        Opcode::Timeout => timeout(ring, sub, completions),
+        Opcode::Accept => accept_socket(
+            &*request_socket(ring, sub)?,
+            UserPtr::<sockaddr>::from(sub.addr as usize),
+            UserPtr::<socklen_t>::from(sub.off as usize),
+            sub.op_flags,
+        ),
+        Opcode::Connect => {
+            let addr = UserConstPtr::<sockaddr>::from(sub.addr as usize);
+            let addr = SocketAddress::read_from_user(addr, sub.off as _)?;
+            request_socket(ring, sub)?.connect(addr)?;
+            Ok(0)
+        }
+        Opcode::Send => {
+            let buf = UserConstPtr::<u8>::from(sub.addr as usize).get_as_slice(sub.len as _)?;
+            let socket = request_socket(ring, sub)?;
+            Ok(socket.send_msg(buf, None, UnixAncillary::default(), sub.op_flags)? as _)
+        }
+        Opcode::Recv => {
+            let buf = UserPtr::<u8>::from(sub.addr as usize).get_as_mut_slice(sub.len as _)?;
+            let (received, ..) = request_socket(ring, sub)?.recv_msg(buf, sub.op_flags)?;
+            Ok(received.min(buf.len()) as _)
+        }
+        Opcode::Close => {
+            if sub.flags & IOSQE_FIXED_FILE != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            close_file_like(sub.fd as c_int)?;
+            Ok(0)
+        }
+    }
+}
+
+/// Execute a chain of linked requests in order, posting their completions.
+///
+/// After a request of the chain fails, the rest is canceled unless the
+/// request is hard linked. All of it is canceled once the instance is closed.
+fn execute_chain(ring: &IoUringShared, chain: &[Submission], completions: u64) {
+    let mut canceled = false;
+    for sub in chain {
+        let res = if canceled || ring.canceler().is_canceled() {
+            Err(LinuxError::ECANCELED)
+        } else {
+            execute(ring, sub, completions)
+        };
+        if res.is_err() && sub.flags & IOSQE_IO_HARDLINK == 0 {
+            canceled = true;
+        }
+        match res {
+            Ok(_) if sub.flags & IOSQE_CQE_SKIP_SUCCESS != 0 => {}
+            Ok(res) => ring.post_completion(sub.user_data, res as _, 0),
+            Err(err) => ring.post_completion(sub.user_data, -err.code(), 0),
+        }
+    }
+}
+
+/// Queue `chain` for the workers of `ring`, spawning a kernel worker on
+/// behalf of the current thread if the ring has room for one.
+///
+/// The worker runs in the address space and with the file descriptor table of
+/// the current process, so that it can access the buffers and files of the
+/// requests. It executes queued chains until there are none left.
+fn queue_chain(ring: &Arc<IoUringShared>, chain: Vec<Submission>, completions: u64) {
+    if !ring.queue_chain(chain, completions) {
+        return;
+    }
+    let curr = current();
+    let ring = ring.clone();
+    let mut task = TaskInner::new(
+        move || {
+            run_as_worker(ring.canceler(), || {
+                while let Some((chain, completions)) = ring.next_chain() {
+                    execute_chain(&ring, &chain, completions);
+                }
+            })
+        },
+        "io_uring-worker".into(),
+        axconfig::plat::KERNEL_STACK_SIZE,
+    );
+    task.ctx_mut().set_page_table_root(
+        curr.task_ext()
+            .process_data()
+            .aspace
+            .lock()
+            .page_table_root(),
+    );
+    task.init_task_ext(TaskExt::new(curr.task_ext().thread.clone()));
+    axtask::spawn_task(task);
+}
+
+/// Submit at most `to_submit` requests from the submission ring, returning the
+/// number of requests consumed.
+fn submit(ring: &IoUring, to_submit: u32) -> LinuxResult<usize> {
+    let submissions = ring.pop_submissions(to_submit)?;
+    let completions = ring.completions();
+    let mut chain = Vec::new();
+    for sub in &submissions {
+        if sub.flags & !SQE_FLAGS != 0 {
+            ring.post_completion(sub.user_data, -LinuxError::EINVAL.code(), 0);
+            continue;
+        }
+        chain.push(*sub);
+        if sub.flags & (IOSQE_IO_LINK | IOSQE_IO_HARDLINK) == 0 {
+            queue_chain(ring.shared(), mem::take(&mut chain), completions);
+        }
+    }
+    // A link from the last submitted request ends the chain.
+    if !chain.is_empty() {
+        queue_chain(ring.shared(), chain, completions);
+    }
+    Ok(submissions.len())
+}
+
+/// Set up an io_uring instance with at least `entries` submission queue
+/// entries, returning its file descriptor.
+///
+/// The rings are mapped by `mmap` of the file descriptor at the offsets
+/// reported in `params`.
+pub fn sys_io_uring_setup(entries: u32, params: UserPtr<io_uring_params>) -> LinuxResult<isize> {
+    let params = params.get_as_mut()?;
+    debug!(
+        "sys_io_uring_setup <= entries: {}, flags: {:#x}",
+        entries, params.flags
+    );
+
+    const SETUP_FLAGS: u32 = IORING_SETUP_CQSIZE
+        | IORING_SETUP_CLAMP
+        | IORING_SETUP_SUBMIT_ALL
+        | IORING_SETUP_COOP_TASKRUN
+        | IORING_SETUP_SINGLE_ISSUER;
+    if params.resv.iter().any(|&resv| resv != 0) {
+        return Err(LinuxError::EINVAL);
+    }
+    if params.flags & !SETUP_FLAGS != 0 {
+        warn!(
+            "sys_io_uring_setup: unsupported flags: {:#x}",
+            params.flags & !SETUP_FLAGS
+        );
+        return Err(LinuxError::EINVAL);
+    }
+    let clamp = params.flags & IORING_SETUP_CLAMP != 0;
+
+    if entries == 0 || (entries > MAX_ENTRIES && !clamp) {
+        return Err(LinuxError::EINVAL);
+    }
+    let sq_entries = entries.min(MAX_ENTRIES).next_power_of_two();
+    let cq_entries = if params.flags & IORING_SETUP_CQSIZE != 0 {
+        let cq_entries = params.cq_entries;
+        if cq_entries == 0 || (cq_entries > MAX_CQ_ENTRIES && !clamp) {
+            return Err(LinuxError::EINVAL);
+        }
+        let cq_entries = cq_entries.min(MAX_CQ_ENTRIES).next_power_of_two();
+        if cq_entries < sq_entries {
+            return Err(LinuxError::EINVAL);
+        }
+        cq_entries
+    } else {
+        2 * sq_entries
+    };
+
+    let ring = IoUring::new(sq_entries, cq_entries);
+    let (sq_off, cq_off) = IoUring::ring_offsets();
+    params.sq_entries = sq_entries;
+    params.cq_entries = cq_entries;
+    params.features = IORING_FEAT_SUBMIT_STABLE
+        | IORING_FEAT_RW_CUR_POS
+        | IORING_FEAT_NATIVE_WORKERS
+        | IORING_FEAT_CQE_SKIP;
+    params.sq_off = sq_off;
+    params.cq_off = cq_off;
+    Ok(ring.add_to_fd_table()? as _)
+}
+
+/// Submit `to_submit` requests, and with `IORING_ENTER_GETEVENTS`, wait for
+/// `min_complete` completions with the signal mask replaced by `sig`.
+///
+/// Return the number of requests submitted.
+pub fn sys_io_uring_enter(
+    fd: c_int,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    sig: UserConstPtr<SignalSet>,
+    sigsz: usize,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_io_uring_enter <= fd: {}, to_submit: {}, min_complete: {}, flags: {:#x}",
+        fd, to_submit, min_complete, flags
+    );
+    if flags & !(IORING_ENTER_GETEVENTS | IORING_ENTER_SQ_WAKEUP | IORING_ENTER_SQ_WAIT) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let ring = IoUring::from_fd(fd)?;
+
+    let submitted = submit(&ring, to_submit)?;
+    if flags & IORING_ENTER_GETEVENTS != 0 {
+        let sigmask = nullable!(sig.get_as_ref())?.copied();
+        if sigmask.is_some() && sigsz != size_of::<SignalSet>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let min_complete = min_complete.min(ring.cq_entries());
+        with_sigmask(sigmask, || {
+            wait_event(None, true, |waiter| {
+                ring.register(waiter, 0);
+                Ok((ring.pending_completions()? >= min_complete).then_some(()))
+            })
+        })?;
+    }
+    Ok(submitted as _)
+}
+
+/// Register resources with an io_uring instance, or unregister them.
+///
+/// Buffers, files and an eventfd signaled on completions can be registered.
+pub fn sys_io_uring_register(
+    fd: c_int,
+    opcode: u32,
+    arg: usize,
+    nr_args: u32,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_io_uring_register <= fd: {}, opcode: {}, nr_args: {}",
+        fd, opcode, nr_args
+    );
+    let ring = IoUring::from_fd(fd)?;
+
+    const REGISTER_BUFFERS: u32 = io_uring_register_op::IORING_REGISTER_BUFFERS as u32;
+    const UNREGISTER_BUFFERS: u32 = io_uring_register_op::IORING_UNREGISTER_BUFFERS as u32;
+    const REGISTER_FILES: u32 = io_uring_register_op::IORING_REGISTER_FILES as u32;
+    const UNREGISTER_FILES: u32 = io_uring_register_op::IORING_UNREGISTER_FILES as u32;
+    const REGISTER_EVENTFD: u32 = io_uring_register_op::IORING_REGISTER_EVENTFD as u32;
+    const UNREGISTER_EVENTFD: u32 = io_uring_register_op::IORING_UNREGISTER_EVENTFD as u32;
+
+    match opcode {
+        REGISTER_BUFFERS => {
+            if nr_args == 0 || nr_args > u16::MAX as u32 + 1 {
+                return Err(LinuxError::EINVAL);
+            }
+            let iovs = UserConstPtr::<iovec>::from(arg).get_as_slice(nr_args as _)?;
+            let buffers = iovs
+                .iter()
+                .map(|iov| {
+                    UserConstPtr::<u8>::from(iov.iov_base as usize)
+                        .get_as_slice(iov.iov_len as _)?;
+                    Ok((iov.iov_base as usize, iov.iov_len as usize))
+                })
+                .collect::<LinuxResult<_>>()?;
+            ring.register_buffers(buffers)?;
+        }
+        UNREGISTER_BUFFERS => ring.unregister_buffers()?,
+        REGISTER_FILES => {
+            if nr_args == 0 || nr_args > AX_FILE_LIMIT as u32 {
+                return Err(LinuxError::EINVAL);
+            }
+            let fds = UserConstPtr::<c_int>::from(arg).get_as_slice(nr_args as _)?;
+            // `-1` leaves a slot empty.
+            let files = fds
+                .iter()
+                .map(|&fd| match fd {
+                    -1 => Ok(None),
+                    fd => get_file_like(fd).map(Some),
+                })
+                .collect::<LinuxResult<_>>()?;
+            ring.register_files(files)?;
+        }
+        UNREGISTER_FILES => ring.unregister_files()?,
+        REGISTER_EVENTFD => {
+            if nr_args != 1 {
+                return Err(LinuxError::EINVAL);
+            }
+            let fd = *UserConstPtr::<c_int>::from(arg).get_as_ref()?;
+            ring.set_eventfd(Some(EventFd::from_fd(fd)?))?;
+        }
+        UNREGISTER_EVENTFD => ring.set_eventfd(None)?,
+        _ => {
+            warn!("sys_io_uring_register: unsupported opcode: {}", opcode);
+            return Err(LinuxError::EINVAL);
+        }
+    }
+    Ok(0)
+}
@@ -8,7 +8,7 @@ use linux_raw_sys::general::{
 };
 use memory_addr::{VirtAddr, VirtAddrRange};
 
-use crate::file::{File, FileLike};
+use crate::file::{File, FileLike, IoUring};
 
 bitflags::bitflags! {
     /// `PROT_*` flags for use with [`sys_mmap`].
@@ -131,6 +131,10 @@ pub fn sys_mmap(
     )?;
 
     if populate {
+        if let Ok(ring) = IoUring::from_fd(fd) {
+            ring.mmap(&aspace, offset as _, start_addr, length)?;
+            return Ok(start_addr.as_usize() as _);
+        }
         let file = File::from_fd(fd)?;
         let file = file.inner();
         let file_size = file.get_attr()?.size() as usize;
@@ -1,9 +1,14 @@
 mod fs;
 mod futex;
+mod io_mpx;
+mod io_uring;
 mod mm;
+mod net;
 mod signal;
 mod sys;
 mod task;
 mod time;
 
-pub use self::{fs::*, futex::*, mm::*, signal::*, sys::*, task::*, time::*};
+pub use self::{
+    fs::*, futex::*, io_mpx::*, io_uring::*, mm::*, net::*, signal::*, sys::*, task::*, time::*,
+};
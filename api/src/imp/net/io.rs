@@ -0,0 +1,99 @@
+use core::ffi::c_int;
+
+use axerrno::LinuxResult;
+use linux_raw_sys::net::{
+    MSG_CMSG_CLOEXEC, MSG_CONFIRM, MSG_DONTWAIT, MSG_EOR, MSG_MORE, MSG_NOSIGNAL, MSG_PEEK,
+    MSG_TRUNC, MSG_WAITALL, sockaddr, socklen_t,
+};
+
+use crate::{
+    file::{FileLike, Socket, UnixAncillary},
+    ptr::{UserConstPtr, UserPtr},
+    socket::{SocketAddrExt, SocketAddress},
+};
+
+/// Flags of the `send*` family that are supported or safe to ignore.
+pub(super) const SEND_FLAGS: u32 = MSG_DONTWAIT | MSG_NOSIGNAL | MSG_MORE | MSG_EOR | MSG_CONFIRM;
+/// Flags of the `recv*` family that are supported or safe to ignore.
+pub(super) const RECV_FLAGS: u32 =
+    MSG_DONTWAIT | MSG_PEEK | MSG_WAITALL | MSG_TRUNC | MSG_CMSG_CLOEXEC;
+
+/// Send a message on a socket.
+///
+/// If `addr` is null, the socket must be connected and the message is sent
+/// to its peer.
+///
+/// Return the number of bytes sent if success.
+pub fn sys_sendto(
+    fd: c_int,
+    buf: UserConstPtr<u8>,
+    len: usize,
+    flags: u32,
+    addr: UserConstPtr<sockaddr>,
+    addrlen: socklen_t,
+) -> LinuxResult<isize> {
+    let buf = buf.get_as_slice(len)?;
+    debug!(
+        "sys_sendto <= fd: {}, buf: {:p}, len: {}, flags: {:#x}",
+        fd,
+        buf.as_ptr(),
+        buf.len(),
+        flags
+    );
+    if flags & !SEND_FLAGS != 0 {
+        warn!("sys_sendto: unsupported flags: {:#x}", flags & !SEND_FLAGS);
+    }
+
+    let socket = Socket::from_fd(fd)?;
+    let addr = if addr.is_null() {
+        None
+    } else {
+        Some(SocketAddress::read_from_user(addr, addrlen)?)
+    };
+    Ok(socket.send_msg(buf, addr, UnixAncillary::default(), flags)? as _)
+}
+
+/// Receive a message from a socket.
+///
+/// If `addr` is not null and the underlying protocol provides the source
+/// address, it is written to `addr` and its length to `addrlen`.
+///
+/// With `MSG_TRUNC`, the real length of a truncated datagram is returned.
+///
+/// Return the number of bytes received if success.
+pub fn sys_recvfrom(
+    fd: c_int,
+    buf: UserPtr<u8>,
+    len: usize,
+    flags: u32,
+    addr: UserPtr<sockaddr>,
+    addrlen: UserPtr<socklen_t>,
+) -> LinuxResult<isize> {
+    let buf = buf.get_as_mut_slice(len)?;
+    debug!(
+        "sys_recvfrom <= fd: {}, buf: {:p}, len: {}, flags: {:#x}",
+        fd,
+        buf.as_ptr(),
+        buf.len(),
+        flags
+    );
+    if flags & !RECV_FLAGS != 0 {
+        warn!(
+            "sys_recvfrom: unsupported flags: {:#x}",
+            flags & !RECV_FLAGS
+        );
+    }
+
+    let socket = Socket::from_fd(fd)?;
+    let (received, src, _) = socket.recv_msg(buf, flags)?;
+    if let Some(src) = src
+        && !addr.is_null()
+    {
+        src.write_to_user(addr, addrlen.get_as_mut()?)?;
+    }
+    if flags & MSG_TRUNC != 0 {
+        Ok(received as _)
+    } else {
+        Ok(received.min(buf.len()) as _)
+    }
+}
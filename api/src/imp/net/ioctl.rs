@@ -0,0 +1,162 @@
+use core::{
+    ffi::{c_int, c_short, c_void},
+    mem::size_of,
+    net::Ipv4Addr,
+};
+
+use axerrno::{LinuxError, LinuxResult};
+use linux_raw_sys::{
+    ioctl::{
+        FIONBIO, SIOCGIFADDR, SIOCGIFBRDADDR, SIOCGIFCONF, SIOCGIFFLAGS, SIOCGIFHWADDR,
+        SIOCGIFINDEX, SIOCGIFMTU, SIOCGIFNAME, SIOCGIFNETMASK, SIOCSIFADDR, SIOCSIFBRDADDR,
+        SIOCSIFFLAGS, SIOCSIFHWADDR, SIOCSIFMTU, SIOCSIFNETMASK,
+    },
+    net::{AF_INET, IFNAMSIZ, ifconf},
+};
+
+use crate::{
+    file::{FileLike, Socket},
+    netif::{NetInterface, interfaces},
+    ptr::UserPtr,
+};
+
+/// `struct sockaddr` as laid out in `struct ifreq`.
+///
+/// linux-raw-sys gives `sockaddr` the size of `sockaddr_storage`, so its
+/// `ifreq` does not match the one of user space.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IfAddr {
+    family: u16,
+    data: [u8; 14],
+}
+
+/// The union of request data in `struct ifreq`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+union IfReqData {
+    addr: IfAddr,
+    flags: c_short,
+    ivalue: c_int,
+    mtu: c_int,
+    /// `struct ifmap`, the largest member.
+    _map: [usize; 3],
+}
+
+/// `struct ifreq`, the argument of the `SIOC*IF*` requests.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IfReq {
+    name: [u8; IFNAMSIZ as usize],
+    data: IfReqData,
+}
+
+fn inet_addr(addr: Ipv4Addr) -> IfAddr {
+    // `sin_port` comes first, followed by `sin_addr`.
+    let mut data = [0; 14];
+    data[2..6].copy_from_slice(&addr.octets());
+    IfAddr {
+        family: AF_INET as _,
+        data,
+    }
+}
+
+fn new_ifreq(iface: &NetInterface) -> IfReq {
+    let mut name = [0; IFNAMSIZ as usize];
+    name[..iface.name.len()].copy_from_slice(iface.name.as_bytes());
+    IfReq {
+        name,
+        data: IfReqData {
+            addr: inet_addr(iface.addr),
+        },
+    }
+}
+
+/// Fill the interface list of `SIOCGIFCONF`.
+fn get_ifconf(argp: UserPtr<c_void>) -> LinuxResult {
+    let conf = UserPtr::<ifconf>::from(argp.address().as_usize()).get_as_mut()?;
+    let ifaces = interfaces();
+    // SAFETY: both members of the union are pointers.
+    let buf = unsafe { conf.ifc_ifcu.ifcu_buf };
+    if buf.is_null() {
+        // Only report the size of the list.
+        conf.ifc_len = (ifaces.len() * size_of::<IfReq>()) as _;
+        return Ok(());
+    }
+
+    let count = (conf.ifc_len.max(0) as usize / size_of::<IfReq>()).min(ifaces.len());
+    let reqs = UserPtr::<IfReq>::from(buf as usize).get_as_mut_slice(count)?;
+    for (req, iface) in reqs.iter_mut().zip(&ifaces) {
+        *req = new_ifreq(iface);
+    }
+    conf.ifc_len = (count * size_of::<IfReq>()) as _;
+    Ok(())
+}
+
+/// Handle an `ioctl` on a socket file descriptor.
+///
+/// This covers the `SIOC*IF*` requests that query network interfaces, and
+/// `FIONBIO`. axnet cannot reconfigure interfaces, so setting requests fail
+/// with `EOPNOTSUPP`.
+pub(crate) fn socket_ioctl(socket: &Socket, op: u32, argp: UserPtr<c_void>) -> LinuxResult<isize> {
+    match op {
+        FIONBIO => {
+            let nonblocking = UserPtr::<c_int>::from(argp.address().as_usize()).get_as_mut()?;
+            socket.set_nonblocking(*nonblocking != 0)?;
+            return Ok(0);
+        }
+        SIOCGIFCONF => {
+            get_ifconf(argp)?;
+            return Ok(0);
+        }
+        SIOCGIFNAME | SIOCGIFINDEX | SIOCGIFFLAGS | SIOCGIFADDR | SIOCGIFNETMASK
+        | SIOCGIFBRDADDR | SIOCGIFMTU | SIOCGIFHWADDR | SIOCSIFFLAGS | SIOCSIFMTU | SIOCSIFADDR
+        | SIOCSIFNETMASK | SIOCSIFBRDADDR | SIOCSIFHWADDR => {}
+        _ => {
+            warn!("socket_ioctl: unsupported request: {:#x}", op);
+            return Err(LinuxError::ENOTTY);
+        }
+    }
+
+    let req = UserPtr::<IfReq>::from(argp.address().as_usize()).get_as_mut()?;
+    let ifaces = interfaces();
+    let iface = if op == SIOCGIFNAME {
+        // SAFETY: `ifr_ifindex` is an int in the union.
+        let index = unsafe { req.data.ivalue };
+        ifaces.iter().find(|iface| iface.index == index)
+    } else {
+        let len = req
+            .name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(IFNAMSIZ as usize);
+        ifaces
+            .iter()
+            .find(|iface| iface.name.as_bytes() == &req.name[..len])
+    }
+    .ok_or(LinuxError::ENODEV)?;
+    debug!("socket_ioctl <= op: {:#x}, interface: {}", op, iface.name);
+
+    match op {
+        SIOCGIFNAME => *req = new_ifreq(iface),
+        SIOCGIFINDEX => req.data.ivalue = iface.index,
+        SIOCGIFFLAGS => req.data.flags = iface.flags as _,
+        SIOCGIFADDR => req.data.addr = inet_addr(iface.addr),
+        SIOCGIFNETMASK => req.data.addr = inet_addr(iface.netmask()),
+        SIOCGIFBRDADDR => req.data.addr = inet_addr(iface.broadcast()),
+        SIOCGIFMTU => req.data.mtu = iface.mtu,
+        SIOCGIFHWADDR => {
+            let mut data = [0; 14];
+            data[..6].copy_from_slice(&iface.hw_addr);
+            req.data.addr = IfAddr {
+                family: iface.hw_type,
+                data,
+            };
+        }
+        _ => {
+            warn!("socket_ioctl: cannot reconfigure {}", iface.name);
+            return Err(LinuxError::EOPNOTSUPP);
+        }
+    }
+    Ok(0)
+}
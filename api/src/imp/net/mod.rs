@@ -0,0 +1,13 @@
+mod io;
+mod ioctl;
+mod msg;
+mod name;
+mod opt;
+mod socket;
+
+pub use self::io::*;
+pub use self::ioctl::*;
+pub use self::msg::*;
+pub use self::name::*;
+pub use self::opt::*;
+pub use self::socket::*;
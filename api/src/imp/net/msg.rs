@@ -0,0 +1,458 @@
+use core::{
+    ffi::c_int,
+    mem::{align_of, size_of},
+    ptr,
+    time::Duration,
+};
+
+use alloc::{vec, vec::Vec};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use linux_raw_sys::{
+    general::{UIO_MAXIOV, iovec, timespec},
+    net::{
+        MSG_CTRUNC, MSG_DONTWAIT, MSG_PEEK, MSG_TRUNC, MSG_WAITALL, SCM_CREDENTIALS, SCM_RIGHTS,
+        SOCK_STREAM, SOL_SOCKET, cmsghdr, mmsghdr, msghdr, socklen_t, ucred,
+    },
+};
+
+use super::io::{RECV_FLAGS, SEND_FLAGS};
+use crate::{
+    file::{FileLike, Socket, UnixAncillary, add_file_like, close_file_like, get_file_like},
+    ptr::{UserConstPtr, UserPtr, nullable},
+    socket::{SocketAddrExt, SocketAddress},
+    time::TimeValueLike,
+};
+
+/// Turn on `MSG_DONTWAIT` after the first message has been received.
+const MSG_WAITFORONE: u32 = 0x10000;
+
+/// Maximum number of files passed by a single `SCM_RIGHTS` message.
+const SCM_MAX_FD: usize = 253;
+
+/// Size of the largest datagram any socket type queues.
+const MAX_DGRAM_SIZE: usize = 64 * 1024;
+
+/// Round `len` up to the alignment of control messages (`CMSG_ALIGN`).
+const fn cmsg_align(len: usize) -> usize {
+    (len + align_of::<usize>() - 1) & !(align_of::<usize>() - 1)
+}
+
+/// Length of a control message with `len` bytes of data (`CMSG_LEN`).
+const fn cmsg_len(len: usize) -> usize {
+    cmsg_align(size_of::<cmsghdr>()) + len
+}
+
+/// Parse the control messages of `msg` into ancillary data.
+fn read_control(msg: &msghdr) -> LinuxResult<UnixAncillary> {
+    let mut ancillary = UnixAncillary::default();
+    if msg.msg_control.is_null() || msg.msg_controllen == 0 {
+        return Ok(ancillary);
+    }
+    let control =
+        UserConstPtr::<u8>::from(msg.msg_control as usize).get_as_slice(msg.msg_controllen)?;
+
+    let mut offset = 0;
+    while offset + size_of::<cmsghdr>() <= control.len() {
+        // SAFETY: the header lies within `control`.
+        let hdr = unsafe { ptr::read_unaligned(control[offset..].as_ptr() as *const cmsghdr) };
+        if hdr.cmsg_len < cmsg_len(0) || hdr.cmsg_len > control.len() - offset {
+            return Err(LinuxError::EINVAL);
+        }
+        let data = &control[offset + cmsg_len(0)..offset + hdr.cmsg_len];
+        match (hdr.cmsg_level as u32, hdr.cmsg_type as u32) {
+            (SOL_SOCKET, SCM_RIGHTS) => {
+                let fds = data.chunks_exact(size_of::<c_int>());
+                if ancillary.rights.len() + fds.len() > SCM_MAX_FD {
+                    return Err(LinuxError::EINVAL);
+                }
+                for fd in fds {
+                    let fd = c_int::from_ne_bytes(fd.try_into().unwrap());
+                    ancillary.rights.push(get_file_like(fd)?);
+                }
+            }
+            (SOL_SOCKET, SCM_CREDENTIALS) => {
+                if data.len() != size_of::<ucred>() {
+                    return Err(LinuxError::EINVAL);
+                }
+                // Every process runs as root, so any credentials may be
+                // passed.
+                // SAFETY: `data` has the size of `ucred`.
+                ancillary.cred =
+                    Some(unsafe { ptr::read_unaligned(data.as_ptr() as *const ucred) });
+            }
+            (level, ty) => {
+                warn!(
+                    "sendmsg: unsupported control message: level {}, type {}",
+                    level, ty
+                );
+                return Err(LinuxError::EINVAL);
+            }
+        }
+        offset += cmsg_align(hdr.cmsg_len);
+    }
+    Ok(ancillary)
+}
+
+/// Append a control message to `control` if it fits in `space` bytes.
+fn push_cmsg(control: &mut Vec<u8>, space: usize, ty: u32, data: &[u8]) -> bool {
+    let len = cmsg_len(data.len());
+    if control.len() + len > space {
+        return false;
+    }
+    let hdr = cmsghdr {
+        cmsg_len: len,
+        cmsg_level: SOL_SOCKET as _,
+        cmsg_type: ty as _,
+    };
+    // SAFETY: `cmsghdr` is plain old data.
+    let hdr = unsafe {
+        core::slice::from_raw_parts(&hdr as *const cmsghdr as *const u8, size_of::<cmsghdr>())
+    };
+    control.extend_from_slice(hdr);
+    control.resize(control.len() + cmsg_len(0) - size_of::<cmsghdr>(), 0);
+    control.extend_from_slice(data);
+    control.resize(
+        control
+            .len()
+            .next_multiple_of(align_of::<usize>())
+            .min(space),
+        0,
+    );
+    true
+}
+
+/// Write the received ancillary data as control messages of `msg`.
+///
+/// Files that do not fit in the control buffer are closed, and
+/// `MSG_CTRUNC` is set in `msg_flags`. On failure no file is left installed.
+fn write_control(msg: &mut msghdr, ancillary: UnixAncillary, pass_cred: bool) -> LinuxResult {
+    let space = if msg.msg_control.is_null() {
+        0
+    } else {
+        msg.msg_controllen
+    };
+    let mut control = Vec::new();
+    let mut fds = Vec::new();
+
+    if pass_cred && let Some(cred) = ancillary.cred {
+        // SAFETY: `ucred` is plain old data.
+        let data = unsafe {
+            core::slice::from_raw_parts(&cred as *const ucred as *const u8, size_of::<ucred>())
+        };
+        if !push_cmsg(&mut control, space, SCM_CREDENTIALS, data) {
+            msg.msg_flags |= MSG_CTRUNC;
+        }
+    }
+
+    if !ancillary.rights.is_empty() {
+        let room = space.saturating_sub(control.len() + cmsg_len(0)) / size_of::<c_int>();
+        let count = ancillary.rights.len().min(room);
+        if count < ancillary.rights.len() {
+            msg.msg_flags |= MSG_CTRUNC;
+        }
+        for file in ancillary.rights.into_iter().take(count) {
+            // TODO: fd close-on-exec for `MSG_CMSG_CLOEXEC`
+            match add_file_like(file) {
+                Ok(fd) => fds.push(fd),
+                Err(err) => {
+                    close_fds(&fds);
+                    return Err(err);
+                }
+            }
+        }
+        if count > 0 {
+            let data = fds
+                .iter()
+                .flat_map(|fd| fd.to_ne_bytes())
+                .collect::<Vec<_>>();
+            push_cmsg(&mut control, space, SCM_RIGHTS, &data);
+        }
+    }
+
+    if !control.is_empty() {
+        let res = UserPtr::<u8>::from(msg.msg_control as usize).get_as_mut_slice(control.len());
+        match res {
+            Ok(buf) => buf.copy_from_slice(&control),
+            Err(err) => {
+                close_fds(&fds);
+                return Err(err);
+            }
+        }
+    }
+    msg.msg_controllen = control.len();
+    Ok(())
+}
+
+/// Close the files installed for a message that could not be delivered.
+fn close_fds(fds: &[c_int]) {
+    for fd in fds {
+        let _ = close_file_like(*fd);
+    }
+}
+
+/// Get the `iovec` array of `msg`.
+fn iovecs(msg: &msghdr) -> LinuxResult<&'static [iovec]> {
+    if msg.msg_iovlen > UIO_MAXIOV as usize {
+        return Err(LinuxError::EMSGSIZE);
+    }
+    if msg.msg_iovlen == 0 {
+        return Ok(&[]);
+    }
+    UserConstPtr::<iovec>::from(msg.msg_iov as usize).get_as_slice(msg.msg_iovlen)
+}
+
+/// Gather the data described by `iovs` into a single buffer, so that a
+/// datagram is sent as one message.
+fn gather(iovs: &[iovec]) -> LinuxResult<Vec<u8>> {
+    let mut data = Vec::new();
+    for iov in iovs {
+        if iov.iov_len == 0 {
+            continue;
+        }
+        let buf = UserConstPtr::<u8>::from(iov.iov_base as usize);
+        data.extend_from_slice(buf.get_as_slice(iov.iov_len as _)?);
+    }
+    Ok(data)
+}
+
+/// Get the buffers described by `iovs`, checking that they are all mapped.
+fn iov_buffers(iovs: &[iovec]) -> LinuxResult<Vec<&'static mut [u8]>> {
+    iovs.iter()
+        .try_fold(0usize, |total, iov| total.checked_add(iov.iov_len as usize))
+        .filter(|total| *total <= isize::MAX as usize)
+        .ok_or(LinuxError::EINVAL)?;
+    iovs.iter()
+        .filter(|iov| iov.iov_len != 0)
+        .map(|iov| UserPtr::<u8>::from(iov.iov_base as usize).get_as_mut_slice(iov.iov_len as _))
+        .collect()
+}
+
+/// Scatter `data` into `bufs`.
+fn scatter(bufs: &mut [&mut [u8]], mut data: &[u8]) {
+    for buf in bufs {
+        if data.is_empty() {
+            break;
+        }
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        data = &data[len..];
+    }
+}
+
+/// Receive from a stream socket directly into `bufs`, one after another.
+///
+/// Only the first buffer waits for data, unless `MSG_WAITALL` is set.
+fn recv_stream(
+    socket: &Socket,
+    bufs: &mut [&mut [u8]],
+    flags: u32,
+) -> LinuxResult<(usize, Option<SocketAddress>, UnixAncillary)> {
+    let mut received = 0;
+    let mut src = None;
+    let mut ancillary = UnixAncillary::default();
+    for buf in bufs {
+        let flags = if received == 0 || flags & MSG_WAITALL != 0 {
+            flags
+        } else {
+            flags | MSG_DONTWAIT
+        };
+        let (len, addr, mut more) = match socket.recv_msg(buf, flags) {
+            Ok(res) => res,
+            Err(err) if received == 0 => return Err(err),
+            Err(_) => break,
+        };
+        received += len;
+        src = src.or(addr);
+        ancillary.rights.append(&mut more.rights);
+        ancillary.cred = ancillary.cred.or(more.cred);
+        if len < buf.len() {
+            break;
+        }
+    }
+    Ok((received, src, ancillary))
+}
+
+fn send_msghdr(socket: &Socket, msg: &msghdr, flags: u32) -> LinuxResult<usize> {
+    let addr = if msg.msg_name.is_null() {
+        None
+    } else {
+        let addr = UserConstPtr::from(msg.msg_name as usize);
+        Some(SocketAddress::read_from_user(addr, msg.msg_namelen as _)?)
+    };
+    let ancillary = read_control(msg)?;
+    let data = gather(iovecs(msg)?)?;
+    socket.send_msg(&data, addr, ancillary, flags)
+}
+
+fn recv_msghdr(socket: &Socket, msg: &mut msghdr, flags: u32) -> LinuxResult<usize> {
+    let mut bufs = iov_buffers(iovecs(msg)?)?;
+    let total = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+
+    let (received, src, ancillary) = match bufs.as_mut_slice() {
+        [] => socket.recv_msg(&mut [], flags)?,
+        [buf] => socket.recv_msg(buf, flags)?,
+        bufs if socket.socket_type() == SOCK_STREAM && flags & MSG_PEEK == 0 => {
+            recv_stream(socket, bufs, flags)?
+        }
+        // A datagram has to be received at once, and peeking again would
+        // return the same data, so go through a buffer. It never needs to
+        // be larger than a datagram or the receive buffer of a stream.
+        bufs => {
+            let mut data = vec![0; total.min(MAX_DGRAM_SIZE)];
+            let res = socket.recv_msg(&mut data, flags)?;
+            scatter(bufs, &data[..res.0.min(data.len())]);
+            res
+        }
+    };
+    let copied = received.min(total);
+
+    msg.msg_flags = 0;
+    if socket.socket_type() != SOCK_STREAM && received > total {
+        msg.msg_flags |= MSG_TRUNC;
+    }
+    if !msg.msg_name.is_null() {
+        let mut namelen = msg.msg_namelen as socklen_t;
+        match src {
+            Some(src) => src.write_to_user(UserPtr::from(msg.msg_name as usize), &mut namelen)?,
+            None => namelen = 0,
+        }
+        msg.msg_namelen = namelen as _;
+    }
+    let pass_cred = socket.options().pass_cred;
+    write_control(msg, ancillary, pass_cred)?;
+
+    if flags & MSG_TRUNC != 0 {
+        Ok(received)
+    } else {
+        Ok(copied)
+    }
+}
+
+/// Send a message on a socket, gathering the data from the `iovec` array of
+/// `msg`.
+///
+/// Unix sockets accept `SCM_RIGHTS` and `SCM_CREDENTIALS` control messages.
+///
+/// Return the number of bytes sent if success.
+pub fn sys_sendmsg(fd: c_int, msg: UserConstPtr<msghdr>, flags: u32) -> LinuxResult<isize> {
+    debug!("sys_sendmsg <= fd: {}, flags: {:#x}", fd, flags);
+    if flags & !SEND_FLAGS != 0 {
+        warn!("sys_sendmsg: unsupported flags: {:#x}", flags & !SEND_FLAGS);
+    }
+
+    let socket = Socket::from_fd(fd)?;
+    Ok(send_msghdr(&socket, msg.get_as_ref()?, flags)? as _)
+}
+
+/// Receive a message from a socket, scattering the data into the `iovec`
+/// array of `msg`.
+///
+/// `msg_name`, `msg_namelen`, `msg_controllen` and `msg_flags` of `msg` are
+/// updated. Files passed by `SCM_RIGHTS` are installed into the fd table.
+/// With `MSG_TRUNC`, the real length of a truncated datagram is returned.
+///
+/// Return the number of bytes received if success.
+pub fn sys_recvmsg(fd: c_int, msg: UserPtr<msghdr>, flags: u32) -> LinuxResult<isize> {
+    debug!("sys_recvmsg <= fd: {}, flags: {:#x}", fd, flags);
+    if flags & !RECV_FLAGS != 0 {
+        warn!("sys_recvmsg: unsupported flags: {:#x}", flags & !RECV_FLAGS);
+    }
+
+    let socket = Socket::from_fd(fd)?;
+    Ok(recv_msghdr(&socket, msg.get_as_mut()?, flags)? as _)
+}
+
+/// Send multiple messages on a socket.
+///
+/// Return the number of messages sent if success. An error is only reported
+/// if no message could be sent.
+pub fn sys_sendmmsg(
+    fd: c_int,
+    msgvec: UserPtr<mmsghdr>,
+    vlen: u32,
+    flags: u32,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_sendmmsg <= fd: {}, vlen: {}, flags: {:#x}",
+        fd, vlen, flags
+    );
+    if flags & !SEND_FLAGS != 0 {
+        warn!(
+            "sys_sendmmsg: unsupported flags: {:#x}",
+            flags & !SEND_FLAGS
+        );
+    }
+
+    let socket = Socket::from_fd(fd)?;
+    let vlen = vlen.min(UIO_MAXIOV) as usize;
+    if vlen == 0 {
+        return Ok(0);
+    }
+    let msgvec = msgvec.get_as_mut_slice(vlen)?;
+
+    let mut sent = 0;
+    for msg in msgvec {
+        match send_msghdr(&socket, &msg.msg_hdr, flags) {
+            Ok(len) => msg.msg_len = len as _,
+            Err(err) if sent == 0 => return Err(err),
+            Err(_) => break,
+        }
+        sent += 1;
+    }
+    Ok(sent as _)
+}
+
+/// Receive multiple messages from a socket.
+///
+/// With `MSG_WAITFORONE`, only the first message is waited for. `timeout`
+/// is only checked after each received message, as Linux does.
+///
+/// Return the number of messages received if success. An error is only
+/// reported if no message could be received.
+pub fn sys_recvmmsg(
+    fd: c_int,
+    msgvec: UserPtr<mmsghdr>,
+    vlen: u32,
+    flags: u32,
+    timeout: UserConstPtr<timespec>,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_recvmmsg <= fd: {}, vlen: {}, flags: {:#x}",
+        fd, vlen, flags
+    );
+    if flags & !(RECV_FLAGS | MSG_WAITFORONE) != 0 {
+        warn!(
+            "sys_recvmmsg: unsupported flags: {:#x}",
+            flags & !(RECV_FLAGS | MSG_WAITFORONE)
+        );
+    }
+
+    let socket = Socket::from_fd(fd)?;
+    let timeout: Option<Duration> = nullable!(timeout.get_as_ref())?.map(|ts| ts.to_time_value());
+    let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+    let vlen = vlen.min(UIO_MAXIOV) as usize;
+    if vlen == 0 {
+        return Ok(0);
+    }
+    let msgvec = msgvec.get_as_mut_slice(vlen)?;
+
+    let mut received = 0;
+    let wait_for_one = flags & MSG_WAITFORONE != 0;
+    let mut flags = flags & !MSG_WAITFORONE;
+    for msg in msgvec {
+        match recv_msghdr(&socket, &mut msg.msg_hdr, flags) {
+            Ok(len) => msg.msg_len = len as _,
+            Err(err) if received == 0 => return Err(err),
+            Err(_) => break,
+        }
+        received += 1;
+        if wait_for_one {
+            flags |= MSG_DONTWAIT;
+        }
+        if deadline.is_some_and(|deadline| monotonic_time() >= deadline) {
+            break;
+        }
+    }
+    Ok(received as _)
+}
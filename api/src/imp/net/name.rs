@@ -0,0 +1,36 @@
+use core::ffi::c_int;
+
+use axerrno::LinuxResult;
+use linux_raw_sys::net::{sockaddr, socklen_t};
+
+use crate::{
+    file::{FileLike, Socket},
+    ptr::UserPtr,
+    socket::SocketAddrExt,
+};
+
+/// Get the address to which the socket is bound.
+pub fn sys_getsockname(
+    fd: c_int,
+    addr: UserPtr<sockaddr>,
+    addrlen: UserPtr<socklen_t>,
+) -> LinuxResult<isize> {
+    let local_addr = Socket::from_fd(fd)?.local_addr()?;
+    debug!("sys_getsockname <= fd: {}, addr: {:?}", fd, local_addr);
+
+    local_addr.write_to_user(addr, addrlen.get_as_mut()?)?;
+    Ok(0)
+}
+
+/// Get the address of the peer connected to the socket.
+pub fn sys_getpeername(
+    fd: c_int,
+    addr: UserPtr<sockaddr>,
+    addrlen: UserPtr<socklen_t>,
+) -> LinuxResult<isize> {
+    let peer_addr = Socket::from_fd(fd)?.peer_addr()?;
+    debug!("sys_getpeername <= fd: {}, addr: {:?}", fd, peer_addr);
+
+    peer_addr.write_to_user(addr, addrlen.get_as_mut()?)?;
+    Ok(0)
+}
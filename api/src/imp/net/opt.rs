@@ -0,0 +1,286 @@
+use core::{
+    ffi::c_int,
+    mem::size_of,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ptr, slice,
+    time::Duration,
+};
+
+use axerrno::{LinuxError, LinuxResult};
+use linux_raw_sys::{
+    general::timeval,
+    net::{
+        IP_ADD_MEMBERSHIP, IP_DROP_MEMBERSHIP, IP_MULTICAST_LOOP, IPV6_ADD_MEMBERSHIP,
+        IPV6_DROP_MEMBERSHIP, IPV6_MULTICAST_LOOP, SO_BROADCAST, SO_ERROR, SO_KEEPALIVE, SO_LINGER,
+        SO_PASSCRED, SO_PEERCRED, SO_RCVBUF, SO_RCVTIMEO_NEW, SO_RCVTIMEO_OLD, SO_REUSEADDR,
+        SO_REUSEPORT, SO_SNDBUF, SO_SNDTIMEO_NEW, SO_SNDTIMEO_OLD, SO_TYPE, SOCK_STREAM, SOL_IP,
+        SOL_IPV6, SOL_SOCKET, SOL_TCP, TCP_NODELAY, ip_mreq, ip_mreqn, ipv6_mreq, linger,
+        socklen_t, ucred,
+    },
+};
+
+use crate::{
+    file::{FileLike, Socket, SocketOptions},
+    netif::interfaces,
+    ptr::{UserConstPtr, UserPtr},
+    time::TimeValueLike,
+};
+
+/// Read an option value of type `T` from user space.
+fn read_optval<T: Copy>(optval: UserConstPtr<u8>, optlen: socklen_t) -> LinuxResult<T> {
+    if (optlen as usize) < size_of::<T>() {
+        return Err(LinuxError::EINVAL);
+    }
+    let bytes = optval.get_as_slice(size_of::<T>())?;
+    // SAFETY: `bytes` holds `size_of::<T>()` bytes, and `T` is plain data.
+    Ok(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+/// Write an option value of type `T` to user space, truncated to the buffer
+/// length given in `optlen`, and store the written length back.
+fn write_optval<T>(optval: UserPtr<u8>, optlen: UserPtr<socklen_t>, value: T) -> LinuxResult {
+    let optlen = optlen.get_as_mut()?;
+    let len = (*optlen as usize).min(size_of::<T>());
+    // SAFETY: `value` is plain data and at least `len` bytes long.
+    let bytes = unsafe { slice::from_raw_parts(&value as *const T as *const u8, len) };
+    optval.get_as_mut_slice(len)?.copy_from_slice(bytes);
+    *optlen = len as _;
+    Ok(())
+}
+
+fn read_bool(optval: UserConstPtr<u8>, optlen: socklen_t) -> LinuxResult<bool> {
+    read_optval::<c_int>(optval, optlen).map(|val| val != 0)
+}
+
+/// Read a `SO_RCVTIMEO`/`SO_SNDTIMEO` value, a zero timeout means blocking
+/// forever.
+fn read_timeout(optval: UserConstPtr<u8>, optlen: socklen_t) -> LinuxResult<Option<Duration>> {
+    let tv = read_optval::<timeval>(optval, optlen)?;
+    if !(0..1_000_000).contains(&tv.tv_usec) {
+        return Err(LinuxError::EDOM);
+    }
+    Ok(if tv.tv_sec < 0 {
+        Some(Duration::ZERO)
+    } else if tv.tv_sec == 0 && tv.tv_usec == 0 {
+        None
+    } else {
+        Some(tv.to_time_value())
+    })
+}
+
+fn write_timeout(
+    optval: UserPtr<u8>,
+    optlen: UserPtr<socklen_t>,
+    timeout: Option<Duration>,
+) -> LinuxResult {
+    write_optval(
+        optval,
+        optlen,
+        timeval::from_time_value(timeout.unwrap_or_default()),
+    )
+}
+
+/// Get the index of the interface multicast groups are joined on if no
+/// interface is given.
+///
+/// This is the loopback interface rather than the one of the default route,
+/// as groups cannot be joined on a NIC (see `UdpState`).
+fn default_ifindex() -> c_int {
+    interfaces()
+        .iter()
+        .find(|iface| iface.is_loopback())
+        .map_or(1, |iface| iface.index)
+}
+
+/// Read an `IP_ADD_MEMBERSHIP`/`IP_DROP_MEMBERSHIP` value, either a
+/// `ip_mreqn` or a `ip_mreq`, and return the group and the index of the
+/// interface.
+fn read_ip_mreq(optval: UserConstPtr<u8>, optlen: socklen_t) -> LinuxResult<(IpAddr, c_int)> {
+    let (group, addr, ifindex) = if optlen as usize >= size_of::<ip_mreqn>() {
+        let mreq = read_optval::<ip_mreqn>(optval, optlen)?;
+        (mreq.imr_multiaddr, mreq.imr_address, mreq.imr_ifindex)
+    } else {
+        let mreq = read_optval::<ip_mreq>(optval, optlen)?;
+        (mreq.imr_multiaddr, mreq.imr_interface, 0)
+    };
+    let group = Ipv4Addr::from(u32::from_be(group.s_addr));
+    let addr = Ipv4Addr::from(u32::from_be(addr.s_addr));
+    let ifindex = if ifindex != 0 {
+        ifindex
+    } else if addr.is_unspecified() {
+        default_ifindex()
+    } else {
+        interfaces()
+            .iter()
+            .find(|iface| iface.addr == addr)
+            .ok_or(LinuxError::ENODEV)?
+            .index
+    };
+    Ok((group.into(), ifindex))
+}
+
+/// Read an `IPV6_ADD_MEMBERSHIP`/`IPV6_DROP_MEMBERSHIP` value, and return the
+/// group and the index of the interface.
+fn read_ipv6_mreq(optval: UserConstPtr<u8>, optlen: socklen_t) -> LinuxResult<(IpAddr, c_int)> {
+    let mreq = read_optval::<ipv6_mreq>(optval, optlen)?;
+    // SAFETY: all the variants of the union are plain bytes.
+    let group = Ipv6Addr::from(unsafe { mreq.ipv6mr_multiaddr.in6_u.u6_addr8 });
+    let ifindex = match mreq.ipv6mr_ifindex {
+        0 => default_ifindex(),
+        ifindex => ifindex,
+    };
+    Ok((group.into(), ifindex))
+}
+
+/// Set options on a socket.
+pub fn sys_setsockopt(
+    fd: c_int,
+    level: u32,
+    optname: u32,
+    optval: UserConstPtr<u8>,
+    optlen: socklen_t,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_setsockopt <= fd: {}, level: {}, optname: {}, optlen: {}",
+        fd, level, optname, optlen
+    );
+    let socket = Socket::from_fd(fd)?;
+
+    match (level, optname) {
+        (SOL_SOCKET, SO_REUSEADDR) => {
+            socket.options().reuse_addr = read_bool(optval, optlen)?;
+            socket.apply_options();
+        }
+        // axnet has no equivalent, the option is only recorded.
+        (SOL_SOCKET, SO_REUSEPORT) => socket.options().reuse_port = read_bool(optval, optlen)?,
+        (SOL_SOCKET, SO_KEEPALIVE) => {
+            socket.options().keep_alive = read_bool(optval, optlen)?;
+            socket.apply_options();
+        }
+        (SOL_SOCKET, SO_SNDBUF) => {
+            let size = read_optval::<c_int>(optval, optlen)?;
+            socket.options().send_buf_size = SocketOptions::buf_size_from_user(size);
+        }
+        (SOL_SOCKET, SO_RCVBUF) => {
+            let size = read_optval::<c_int>(optval, optlen)?;
+            socket.options().recv_buf_size = SocketOptions::buf_size_from_user(size);
+        }
+        (SOL_SOCKET, SO_PASSCRED) => socket.options().pass_cred = read_bool(optval, optlen)?,
+        (SOL_SOCKET, SO_LINGER) => {
+            let linger = read_optval::<linger>(optval, optlen)?;
+            socket.set_linger((linger.l_onoff != 0).then_some(linger.l_linger.max(0) as u32))?;
+        }
+        (SOL_SOCKET, SO_BROADCAST) => socket.options().broadcast = read_bool(optval, optlen)?,
+        (SOL_SOCKET, SO_SNDTIMEO_OLD | SO_SNDTIMEO_NEW) => {
+            socket.options().send_timeout = read_timeout(optval, optlen)?;
+        }
+        (SOL_SOCKET, SO_RCVTIMEO_OLD | SO_RCVTIMEO_NEW) => {
+            socket.options().recv_timeout = read_timeout(optval, optlen)?;
+        }
+        (SOL_TCP, TCP_NODELAY) => {
+            if socket.socket_type() != SOCK_STREAM {
+                return Err(LinuxError::EOPNOTSUPP);
+            }
+            socket.options().no_delay = read_bool(optval, optlen)?;
+            socket.apply_options();
+        }
+        (SOL_IP, IP_MULTICAST_LOOP) | (SOL_IPV6, IPV6_MULTICAST_LOOP) => {
+            socket.options().multicast_loop = read_bool(optval, optlen)?;
+        }
+        (SOL_IP, IP_ADD_MEMBERSHIP) => {
+            let (group, ifindex) = read_ip_mreq(optval, optlen)?;
+            socket.join_multicast(group, ifindex)?;
+        }
+        (SOL_IP, IP_DROP_MEMBERSHIP) => {
+            let (group, ifindex) = read_ip_mreq(optval, optlen)?;
+            socket.leave_multicast(group, ifindex)?;
+        }
+        (SOL_IPV6, IPV6_ADD_MEMBERSHIP) => {
+            let (group, ifindex) = read_ipv6_mreq(optval, optlen)?;
+            socket.join_multicast(group, ifindex)?;
+        }
+        (SOL_IPV6, IPV6_DROP_MEMBERSHIP) => {
+            let (group, ifindex) = read_ipv6_mreq(optval, optlen)?;
+            socket.leave_multicast(group, ifindex)?;
+        }
+        _ => {
+            warn!(
+                "sys_setsockopt: unsupported option: level {}, optname {}",
+                level, optname
+            );
+            return Err(LinuxError::ENOPROTOOPT);
+        }
+    }
+    Ok(0)
+}
+
+/// Get options on a socket.
+pub fn sys_getsockopt(
+    fd: c_int,
+    level: u32,
+    optname: u32,
+    optval: UserPtr<u8>,
+    optlen: UserPtr<socklen_t>,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_getsockopt <= fd: {}, level: {}, optname: {}",
+        fd, level, optname
+    );
+    let socket = Socket::from_fd(fd)?;
+    let opts = socket.options().clone();
+
+    match (level, optname) {
+        (SOL_SOCKET, SO_TYPE) => write_optval(optval, optlen, socket.socket_type() as c_int)?,
+        (SOL_SOCKET, SO_ERROR) => {
+            let error: c_int = socket.take_error().map_or(0, |err| err.code());
+            write_optval(optval, optlen, error)?
+        }
+        (SOL_SOCKET, SO_REUSEADDR) => write_optval(optval, optlen, opts.reuse_addr as c_int)?,
+        (SOL_SOCKET, SO_REUSEPORT) => write_optval(optval, optlen, opts.reuse_port as c_int)?,
+        (SOL_SOCKET, SO_KEEPALIVE) => write_optval(optval, optlen, opts.keep_alive as c_int)?,
+        (SOL_SOCKET, SO_SNDBUF) => write_optval(optval, optlen, opts.send_buf_size as c_int)?,
+        (SOL_SOCKET, SO_RCVBUF) => write_optval(optval, optlen, opts.recv_buf_size as c_int)?,
+        (SOL_SOCKET, SO_PASSCRED) => write_optval(optval, optlen, opts.pass_cred as c_int)?,
+        (SOL_SOCKET, SO_BROADCAST) => write_optval(optval, optlen, opts.broadcast as c_int)?,
+        (SOL_SOCKET, SO_LINGER) => {
+            let linger = linger {
+                l_onoff: opts.linger.is_some() as c_int,
+                l_linger: opts.linger.unwrap_or(0) as c_int,
+            };
+            write_optval(optval, optlen, linger)?
+        }
+        (SOL_SOCKET, SO_PEERCRED) => {
+            // Linux reports an invalid pid, uid and gid for sockets without a
+            // connected peer.
+            let cred = socket.peer_cred().unwrap_or(ucred {
+                pid: 0,
+                uid: u32::MAX,
+                gid: u32::MAX,
+            });
+            write_optval(optval, optlen, cred)?
+        }
+        (SOL_SOCKET, SO_SNDTIMEO_OLD | SO_SNDTIMEO_NEW) => {
+            write_timeout(optval, optlen, opts.send_timeout)?
+        }
+        (SOL_SOCKET, SO_RCVTIMEO_OLD | SO_RCVTIMEO_NEW) => {
+            write_timeout(optval, optlen, opts.recv_timeout)?
+        }
+        (SOL_TCP, TCP_NODELAY) => {
+            if socket.socket_type() != SOCK_STREAM {
+                return Err(LinuxError::EOPNOTSUPP);
+            }
+            write_optval(optval, optlen, opts.no_delay as c_int)?
+        }
+        (SOL_IP, IP_MULTICAST_LOOP) | (SOL_IPV6, IPV6_MULTICAST_LOOP) => {
+            write_optval(optval, optlen, opts.multicast_loop as c_int)?
+        }
+        _ => {
+            warn!(
+                "sys_getsockopt: unsupported option: level {}, optname {}",
+                level, optname
+            );
+            return Err(LinuxError::ENOPROTOOPT);
+        }
+    }
+    Ok(0)
+}
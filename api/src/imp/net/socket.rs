@@ -0,0 +1,256 @@
+use core::ffi::c_int;
+
+use axerrno::{LinuxError, LinuxResult};
+use linux_raw_sys::{
+    general::{O_CLOEXEC, O_NONBLOCK},
+    net::{
+        AF_INET, AF_INET6, AF_NETLINK, AF_UNIX, IPPROTO_ICMP, IPPROTO_ICMPV6, IPPROTO_TCP,
+        IPPROTO_UDP, SHUT_RD, SHUT_RDWR, SHUT_WR, SOCK_DGRAM, SOCK_RAW, SOCK_STREAM, sockaddr,
+        socklen_t,
+    },
+    netlink::NETLINK_ROUTE,
+};
+
+use crate::{
+    file::{FileLike, Socket, UnixSocketType, close_file_like},
+    ptr::{UserConstPtr, UserPtr},
+    socket::{SocketAddrExt, SocketAddress},
+};
+
+/// Set `O_NONBLOCK` on the new file description.
+const SOCK_NONBLOCK: u32 = O_NONBLOCK;
+/// Set `FD_CLOEXEC` on the new file descriptor.
+const SOCK_CLOEXEC: u32 = O_CLOEXEC;
+/// The socket type is stored in the low bits of the `type` argument.
+const SOCK_TYPE_MASK: u32 = 0xf;
+
+/// Create an endpoint for communication.
+///
+/// `ty` may be or'ed with `SOCK_NONBLOCK` and `SOCK_CLOEXEC`.
+///
+/// Return the new socket file descriptor if success.
+pub fn sys_socket(domain: u32, ty: u32, protocol: u32) -> LinuxResult<isize> {
+    debug!(
+        "sys_socket <= domain: {}, ty: {:#x}, protocol: {}",
+        domain, ty, protocol
+    );
+    let flags = ty & !SOCK_TYPE_MASK;
+    if flags & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let socket = match (domain, ty & SOCK_TYPE_MASK) {
+        (AF_UNIX, unix_ty) => {
+            // `PF_UNIX` is the only protocol of the family.
+            if protocol != 0 && protocol != 1 {
+                return Err(LinuxError::EPROTONOSUPPORT);
+            }
+            match unix_ty {
+                SOCK_STREAM => Socket::new_unix(UnixSocketType::Stream),
+                SOCK_DGRAM => Socket::new_unix(UnixSocketType::Datagram),
+                _ => return Err(LinuxError::ESOCKTNOSUPPORT),
+            }
+        }
+        (AF_INET | AF_INET6, SOCK_STREAM) => {
+            if protocol != 0 && protocol != IPPROTO_TCP as u32 {
+                return Err(LinuxError::EPROTONOSUPPORT);
+            }
+            Socket::new_tcp()
+        }
+        // axnet does not expose the ICMP sockets of smoltcp, so neither ping
+        // nor raw sockets can be provided.
+        (AF_INET | AF_INET6, SOCK_RAW) => {
+            warn!("sys_socket: raw sockets are not supported");
+            return Err(LinuxError::ESOCKTNOSUPPORT);
+        }
+        (AF_INET | AF_INET6, SOCK_DGRAM)
+            if protocol == IPPROTO_ICMP as u32 || protocol == IPPROTO_ICMPV6 as u32 =>
+        {
+            warn!("sys_socket: ping sockets are not supported");
+            return Err(LinuxError::EPROTONOSUPPORT);
+        }
+        (AF_INET | AF_INET6, SOCK_DGRAM) => {
+            if protocol != 0 && protocol != IPPROTO_UDP as u32 {
+                return Err(LinuxError::EPROTONOSUPPORT);
+            }
+            Socket::new_udp()
+        }
+        (AF_INET | AF_INET6, _) => return Err(LinuxError::ESOCKTNOSUPPORT),
+        (AF_NETLINK, netlink_ty @ (SOCK_RAW | SOCK_DGRAM)) => {
+            if protocol != NETLINK_ROUTE {
+                return Err(LinuxError::EPROTONOSUPPORT);
+            }
+            Socket::new_netlink(netlink_ty)
+        }
+        (AF_NETLINK, _) => return Err(LinuxError::ESOCKTNOSUPPORT),
+        _ => return Err(LinuxError::EAFNOSUPPORT),
+    };
+    if flags & SOCK_NONBLOCK != 0 {
+        socket.set_nonblocking(true)?;
+    }
+    if flags & SOCK_CLOEXEC != 0 {
+        // TODO: fd close-on-exec
+        warn!("sys_socket: SOCK_CLOEXEC is ignored");
+    }
+    Ok(socket.add_to_fd_table()? as _)
+}
+
+/// Create a pair of connected sockets.
+///
+/// Only `AF_UNIX` is supported. `ty` may be or'ed with `SOCK_NONBLOCK` and
+/// `SOCK_CLOEXEC`.
+pub fn sys_socketpair(
+    domain: u32,
+    ty: u32,
+    protocol: u32,
+    sv: UserPtr<[c_int; 2]>,
+) -> LinuxResult<isize> {
+    debug!(
+        "sys_socketpair <= domain: {}, ty: {:#x}, protocol: {}",
+        domain, ty, protocol
+    );
+    let flags = ty & !SOCK_TYPE_MASK;
+    if flags & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    match domain {
+        AF_UNIX => {}
+        AF_INET | AF_INET6 | AF_NETLINK => return Err(LinuxError::EOPNOTSUPP),
+        _ => return Err(LinuxError::EAFNOSUPPORT),
+    }
+    if protocol != 0 && protocol != 1 {
+        return Err(LinuxError::EPROTONOSUPPORT);
+    }
+    let unix_ty = match ty & SOCK_TYPE_MASK {
+        SOCK_STREAM => UnixSocketType::Stream,
+        SOCK_DGRAM => UnixSocketType::Datagram,
+        _ => return Err(LinuxError::ESOCKTNOSUPPORT),
+    };
+
+    let sv = sv.get_as_mut()?;
+
+    let (socket1, socket2) = Socket::new_unix_pair(unix_ty);
+    for socket in [&socket1, &socket2] {
+        if flags & SOCK_NONBLOCK != 0 {
+            socket.set_nonblocking(true)?;
+        }
+    }
+    if flags & SOCK_CLOEXEC != 0 {
+        // TODO: fd close-on-exec
+        warn!("sys_socketpair: SOCK_CLOEXEC is ignored");
+    }
+    let fd1 = socket1.add_to_fd_table()?;
+    let fd2 = socket2
+        .add_to_fd_table()
+        .inspect_err(|_| close_file_like(fd1).unwrap())?;
+
+    sv[0] = fd1;
+    sv[1] = fd2;
+
+    debug!("sys_socketpair => sv: {:?}", sv);
+    Ok(0)
+}
+
+/// Bind a name to a socket.
+pub fn sys_bind(fd: c_int, addr: UserConstPtr<sockaddr>, addrlen: socklen_t) -> LinuxResult<isize> {
+    let addr = SocketAddress::read_from_user(addr, addrlen)?;
+    debug!("sys_bind <= fd: {}, addr: {:?}", fd, addr);
+
+    Socket::from_fd(fd)?.bind(addr)?;
+    Ok(0)
+}
+
+/// Initiate a connection on a socket.
+pub fn sys_connect(
+    fd: c_int,
+    addr: UserConstPtr<sockaddr>,
+    addrlen: socklen_t,
+) -> LinuxResult<isize> {
+    let addr = SocketAddress::read_from_user(addr, addrlen)?;
+    debug!("sys_connect <= fd: {}, addr: {:?}", fd, addr);
+
+    Socket::from_fd(fd)?.connect(addr)?;
+    Ok(0)
+}
+
+/// Listen for connections on a socket.
+///
+/// The `backlog` hint is only honored by Unix sockets, the pending queue of
+/// TCP sockets is managed by axnet.
+pub fn sys_listen(fd: c_int, backlog: c_int) -> LinuxResult<isize> {
+    debug!("sys_listen <= fd: {}, backlog: {}", fd, backlog);
+
+    if backlog < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    Socket::from_fd(fd)?.listen(backlog as usize)?;
+    Ok(0)
+}
+
+/// Accept a connection on a socket.
+///
+/// Return the file descriptor of the accepted socket if success.
+pub fn sys_accept(
+    fd: c_int,
+    addr: UserPtr<sockaddr>,
+    addrlen: UserPtr<socklen_t>,
+) -> LinuxResult<isize> {
+    sys_accept4(fd, addr, addrlen, 0)
+}
+
+/// Accept a connection on a socket, with `SOCK_NONBLOCK` and `SOCK_CLOEXEC`
+/// applied to the new file descriptor.
+pub fn sys_accept4(
+    fd: c_int,
+    addr: UserPtr<sockaddr>,
+    addrlen: UserPtr<socklen_t>,
+    flags: u32,
+) -> LinuxResult<isize> {
+    debug!("sys_accept4 <= fd: {}, flags: {:#x}", fd, flags);
+    accept_socket(&*Socket::from_fd(fd)?, addr, addrlen, flags)
+}
+
+/// Accept a connection on `socket`, adding the new socket to the file
+/// descriptor table.
+pub(crate) fn accept_socket(
+    socket: &Socket,
+    addr: UserPtr<sockaddr>,
+    addrlen: UserPtr<socklen_t>,
+    flags: u32,
+) -> LinuxResult<isize> {
+    if flags & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+
+    let new_socket = socket.accept()?;
+    let peer_addr = new_socket.peer_addr()?;
+    if flags & SOCK_NONBLOCK != 0 {
+        new_socket.set_nonblocking(true)?;
+    }
+    if flags & SOCK_CLOEXEC != 0 {
+        // TODO: fd close-on-exec
+        warn!("sys_accept4: SOCK_CLOEXEC is ignored");
+    }
+
+    let new_fd = new_socket.add_to_fd_table()?;
+    debug!("sys_accept4 => fd: {}, peer: {:?}", new_fd, peer_addr);
+
+    if !addr.is_null() {
+        peer_addr.write_to_user(addr, addrlen.get_as_mut()?)?;
+    }
+    Ok(new_fd as _)
+}
+
+/// Shut down part of a full-duplex connection.
+pub fn sys_shutdown(fd: c_int, how: u32) -> LinuxResult<isize> {
+    debug!("sys_shutdown <= fd: {}, how: {}", fd, how);
+
+    let (read, write) = match how {
+        SHUT_RD => (true, false),
+        SHUT_WR => (false, true),
+        SHUT_RDWR => (true, true),
+        _ => return Err(LinuxError::EINVAL),
+    };
+    Socket::from_fd(fd)?.shutdown(read, write)?;
+    Ok(0)
+}
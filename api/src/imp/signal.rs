@@ -1,4 +1,4 @@
-use core::{mem, time::Duration};
+use core::{ffi::c_int, mem, time::Duration};
 
 use alloc::sync::Arc;
 use axerrno::{LinuxError, LinuxResult};
@@ -7,17 +7,22 @@ use axprocess::{Pid, Thread};
 use axsignal::{SignalInfo, SignalSet, SignalStack, Signo};
 use axtask::{TaskExtRef, current};
 use linux_raw_sys::general::{
-    MINSIGSTKSZ, SI_TKILL, SI_USER, SIG_BLOCK, SIG_SETMASK, SIG_UNBLOCK, kernel_sigaction, siginfo,
-    timespec,
+    MINSIGSTKSZ, O_CLOEXEC, O_NONBLOCK, SI_TKILL, SI_USER, SIG_BLOCK, SIG_SETMASK, SIG_UNBLOCK,
+    kernel_sigaction, siginfo, timespec,
 };
 use starry_core::task::{get_process, get_process_group, get_thread, processes};
 
 use crate::{
+    file::{FileLike, PidFd, SignalFd},
     ptr::{UserConstPtr, UserPtr, nullable},
     signal::{check_signals, send_signal_process, send_signal_process_group, send_signal_thread},
     time::TimeValueLike,
 };
 
+/// Flags of `signalfd4`, the same as the file status flags.
+const SFD_NONBLOCK: u32 = O_NONBLOCK;
+const SFD_CLOEXEC: u32 = O_CLOEXEC;
+
 fn check_sigset_size(size: usize) -> LinuxResult<()> {
     if size != size_of::<SignalSet>() {
         return Err(LinuxError::EINVAL);
@@ -206,6 +211,36 @@ pub fn sys_rt_tgsigqueueinfo(
     Ok(0)
 }
 
+/// Send a signal to the process referred to by `pidfd`, described by `sig` if
+/// not null.
+pub fn sys_pidfd_send_signal(
+    pidfd: c_int,
+    signo: u32,
+    sig: UserConstPtr<SignalInfo>,
+    flags: u32,
+) -> LinuxResult<isize> {
+    if flags != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let pidfd = PidFd::from_fd(pidfd)?;
+    let proc = pidfd.process();
+    if proc.is_zombie() {
+        return Err(LinuxError::ESRCH);
+    }
+
+    let sig = if sig.is_null() {
+        make_siginfo(signo, SI_USER as _)?
+    } else {
+        Some(make_queue_signal_info(proc.pid(), signo, sig)?)
+    };
+    let Some(sig) = sig else {
+        // TODO: should also check permissions
+        return Ok(0);
+    };
+    send_signal_process(proc, sig)?;
+    Ok(0)
+}
+
 pub fn sys_rt_sigreturn(tf: &mut TrapFrame) -> LinuxResult<isize> {
     let curr = current();
     curr.task_ext().thread_data().signal.restore(tf);
@@ -293,3 +328,43 @@ pub fn sys_sigaltstack(
             Ok(0)
         })
 }
+
+/// Create a signalfd receiving the signals in `mask`, or replace the mask of
+/// the signalfd `fd` if it is not -1.
+pub fn sys_signalfd4(
+    fd: c_int,
+    mask: UserConstPtr<SignalSet>,
+    sizemask: usize,
+    flags: u32,
+) -> LinuxResult<isize> {
+    check_sigset_size(sizemask)?;
+    if flags & !(SFD_NONBLOCK | SFD_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let mut mask = *mask.get_as_ref()?;
+    mask.remove(Signo::SIGKILL);
+    mask.remove(Signo::SIGSTOP);
+
+    if fd != -1 {
+        SignalFd::from_fd(fd)?.set_mask(mask);
+        return Ok(fd as _);
+    }
+    let signalfd = SignalFd::new(mask);
+    if flags & SFD_NONBLOCK != 0 {
+        signalfd.set_nonblocking(true)?;
+    }
+    if flags & SFD_CLOEXEC != 0 {
+        // TODO: fd close-on-exec
+        warn!("sys_signalfd4: SFD_CLOEXEC is ignored");
+    }
+    Ok(signalfd.add_to_fd_table()? as _)
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_signalfd(
+    fd: c_int,
+    mask: UserConstPtr<SignalSet>,
+    sizemask: usize,
+) -> LinuxResult<isize> {
+    sys_signalfd4(fd, mask, sizemask, 0)
+}
@@ -1,3 +1,5 @@
+use core::ffi::c_int;
+
 use alloc::sync::Arc;
 use axerrno::{LinuxError, LinuxResult};
 use axfs::{CURRENT_DIR, CURRENT_DIR_PATH};
@@ -13,7 +15,10 @@ use starry_core::{
     task::{ProcessData, TaskExt, ThreadData, add_thread_to_table, new_user_task},
 };
 
-use crate::{file::FD_TABLE, ptr::UserPtr};
+use crate::{
+    file::{FD_TABLE, FileLike, PidFd, replace_file_like},
+    ptr::UserPtr,
+};
 
 bitflags! {
     /// Options for use with [`sys_clone`].
@@ -75,6 +80,8 @@ bitflags! {
         const NEWNET = CLONE_NEWNET;
         /// The new process shares an I/O context with the calling process.
         const IO = CLONE_IO;
+        /// Store a pidfd referring to the child in the parent's memory.
+        const PIDFD = CLONE_PIDFD;
     }
 }
 
@@ -102,6 +109,13 @@ pub fn sys_clone(
     if flags.contains(CloneFlags::THREAD) && !flags.contains(CloneFlags::VM | CloneFlags::SIGHAND) {
         return Err(LinuxError::EINVAL);
     }
+    // The pidfd is stored at `parent_tid`, so it cannot be used for the
+    // thread ID as well.
+    if flags.contains(CloneFlags::PIDFD)
+        && flags.intersects(CloneFlags::THREAD | CloneFlags::PARENT_SETTID)
+    {
+        return Err(LinuxError::EINVAL);
+    }
     let exit_signal = Signo::from_repr(exit_signal as u8);
 
     let mut new_uctx = UspaceContext::from(tf);
@@ -118,6 +132,12 @@ pub fn sys_clone(
     } else {
         None
     };
+    // Checked before the child is created, nothing can fail once it is.
+    let pidfd_slot = if flags.contains(CloneFlags::PIDFD) {
+        Some(UserPtr::<c_int>::from(parent_tid).get_as_mut()?)
+    } else {
+        None
+    };
 
     let curr = current();
     let mut new_task = new_user_task(curr.name(), new_uctx, set_child_tid);
@@ -127,6 +147,7 @@ pub fn sys_clone(
         *UserPtr::<Pid>::from(parent_tid).get_as_mut()? = tid;
     }
 
+    let mut pidfd = None;
     let process = if flags.contains(CloneFlags::THREAD) {
         new_task.ctx_mut().set_page_table_root(
             curr.task_ext()
@@ -200,9 +221,22 @@ pub fn sys_clone(
                 .deref_from(&process_data.ns)
                 .init_new(CURRENT_DIR_PATH.copy_inner());
         }
+
+        // Reserve the file descriptor of the pidfd, after the child got its
+        // copy of the table, and with a pidfd of the calling process until
+        // the child exists.
+        if pidfd_slot.is_some() {
+            let parent = curr.task_ext().thread.process().clone();
+            pidfd = Some(PidFd::new(parent).add_to_fd_table()?);
+        }
         &builder.data(process_data).build()
     };
 
+    if let (Some(fd), Some(slot)) = (pidfd, pidfd_slot) {
+        replace_file_like(fd, Arc::new(PidFd::new(process.clone())));
+        *slot = fd;
+    }
+
     let thread_data = ThreadData::new(process.data().unwrap());
     if flags.contains(CloneFlags::CHILD_CLEARTID) {
         thread_data.set_clear_child_tid(child_tid);
@@ -5,7 +5,7 @@ use linux_raw_sys::general::SI_KERNEL;
 use starry_core::task::ProcessData;
 
 use crate::{
-    file::FD_TABLE,
+    file::{FD_TABLE, notify_pidfds},
     ptr::UserPtr,
     signal::{send_signal_process, send_signal_thread},
 };
@@ -42,6 +42,7 @@ pub fn do_exit(exit_code: i32, group_exit: bool) -> ! {
                 data.child_exit_wq.notify_all(false)
             }
         }
+        notify_pidfds();
 
         process.exit();
         // TODO: clear namespace resources
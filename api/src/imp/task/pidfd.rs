@@ -0,0 +1,50 @@
+use axerrno::{LinuxError, LinuxResult};
+use axprocess::Pid;
+use linux_raw_sys::general::O_NONBLOCK;
+use starry_core::task::{ProcessData, get_process};
+
+use crate::file::{FD_TABLE, FileLike, PidFd, add_file_like};
+
+/// Flags of `pidfd_open`, the same as the file status flags.
+const PIDFD_NONBLOCK: u32 = O_NONBLOCK;
+
+/// Create a pidfd referring to the process `pid`.
+pub fn sys_pidfd_open(pid: Pid, flags: u32) -> LinuxResult<isize> {
+    debug!("sys_pidfd_open <= pid: {}, flags: {:#x}", pid, flags);
+    if flags & !PIDFD_NONBLOCK != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let pidfd = PidFd::new(get_process(pid)?);
+    if flags & PIDFD_NONBLOCK != 0 {
+        pidfd.set_nonblocking(true)?;
+    }
+    Ok(pidfd.add_to_fd_table()? as _)
+}
+
+/// Duplicate the file `targetfd` of the process referred to by `pidfd` into
+/// the file descriptor table of the current process.
+pub fn sys_pidfd_getfd(pidfd: i32, targetfd: i32, flags: u32) -> LinuxResult<isize> {
+    debug!(
+        "sys_pidfd_getfd <= pidfd: {}, targetfd: {}, flags: {:#x}",
+        pidfd, targetfd, flags
+    );
+    if flags != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let pidfd = PidFd::from_fd(pidfd)?;
+    let process = pidfd.process();
+    let Some(data) = process
+        .data::<ProcessData>()
+        .filter(|_| !process.is_zombie())
+    else {
+        return Err(LinuxError::ESRCH);
+    };
+    // TODO: check ptrace access mode
+    let file = FD_TABLE
+        .deref_from(&data.ns)
+        .read()
+        .get(targetfd as usize)
+        .cloned()
+        .ok_or(LinuxError::EBADF)?;
+    Ok(add_file_like(file)? as _)
+}
@@ -1,14 +1,21 @@
+use core::mem;
+
 use alloc::{sync::Arc, vec::Vec};
 use axerrno::{LinuxError, LinuxResult};
 use axprocess::{Pid, Process};
+use axsignal::{SignalInfo, Signo};
 use axtask::{TaskExtRef, current};
 use bitflags::bitflags;
 use linux_raw_sys::general::{
-    __WALL, __WCLONE, __WNOTHREAD, WCONTINUED, WEXITED, WNOHANG, WNOWAIT, WUNTRACED,
+    __WALL, __WCLONE, __WNOTHREAD, CLD_EXITED, CLD_KILLED, P_ALL, P_PGID, P_PID, P_PIDFD,
+    WCONTINUED, WEXITED, WNOHANG, WNOWAIT, WUNTRACED, siginfo,
 };
 use starry_core::task::ProcessData;
 
-use crate::ptr::{UserPtr, nullable};
+use crate::{
+    file::{FileLike, PidFd},
+    ptr::{UserPtr, nullable},
+};
 
 bitflags! {
     #[derive(Debug)]
@@ -55,24 +62,16 @@ impl WaitPid {
     }
 }
 
-pub fn sys_waitpid(pid: i32, exit_code_ptr: UserPtr<i32>, options: u32) -> LinuxResult<isize> {
-    let options = WaitOptions::from_bits_truncate(options);
-    info!("sys_waitpid <= pid: {:?}, options: {:?}", pid, options);
-
+/// Find the children selected by `pid` and `options`, and wait for one of them
+/// to exit.
+///
+/// Returns the exited child, which is reaped unless `WNOWAIT` is set, or
+/// `None` if `WNOHANG` is set and no child has exited yet.
+fn wait_child(pid: WaitPid, options: &WaitOptions) -> LinuxResult<Option<Arc<Process>>> {
     let curr = current();
     let proc_data = curr.task_ext().process_data();
     let process = curr.task_ext().thread.process();
 
-    let pid = if pid == -1 {
-        WaitPid::Any
-    } else if pid == 0 {
-        WaitPid::Pgid(process.group().pgid())
-    } else if pid > 0 {
-        WaitPid::Pid(pid as _)
-    } else {
-        WaitPid::Pgid(-pid as _)
-    };
-
     let children = process
         .children()
         .into_iter()
@@ -87,20 +86,110 @@ pub fn sys_waitpid(pid: i32, exit_code_ptr: UserPtr<i32>, options: u32) -> Linux
         return Err(LinuxError::ECHILD);
     }
 
-    let exit_code = nullable!(exit_code_ptr.get_as_mut())?;
     loop {
         if let Some(child) = children.iter().find(|child| child.is_zombie()) {
             if !options.contains(WaitOptions::WNOWAIT) {
                 child.free();
             }
-            if let Some(exit_code) = exit_code {
-                *exit_code = child.exit_code();
-            }
-            return Ok(child.pid() as _);
+            return Ok(Some(child.clone()));
         } else if options.contains(WaitOptions::WNOHANG) {
-            return Ok(0);
+            return Ok(None);
         } else {
             proc_data.child_exit_wq.wait();
         }
     }
 }
+
+pub fn sys_waitpid(pid: i32, exit_code_ptr: UserPtr<i32>, options: u32) -> LinuxResult<isize> {
+    let options = WaitOptions::from_bits_truncate(options);
+    info!("sys_waitpid <= pid: {:?}, options: {:?}", pid, options);
+
+    let pid = if pid == -1 {
+        WaitPid::Any
+    } else if pid == 0 {
+        WaitPid::Pgid(current().task_ext().thread.process().group().pgid())
+    } else if pid > 0 {
+        WaitPid::Pid(pid as _)
+    } else {
+        WaitPid::Pgid(-pid as _)
+    };
+
+    let exit_code = nullable!(exit_code_ptr.get_as_mut())?;
+    let Some(child) = wait_child(pid, &options)? else {
+        return Ok(0);
+    };
+    if let Some(exit_code) = exit_code {
+        *exit_code = child.exit_code();
+    }
+    Ok(child.pid() as _)
+}
+
+/// Wait for a child selected by `idtype` and `id` to exit, and report it in
+/// `info` as a `SIGCHLD` signal would.
+///
+/// With `P_PIDFD`, `id` is a pidfd referring to the child.
+pub fn sys_waitid(
+    idtype: u32,
+    id: u32,
+    info: UserPtr<siginfo>,
+    options: u32,
+) -> LinuxResult<isize> {
+    let mut options = WaitOptions::from_bits_truncate(options);
+    info!(
+        "sys_waitid <= idtype: {}, id: {}, options: {:?}",
+        idtype, id, options
+    );
+
+    if !options.intersects(WaitOptions::WEXITED | WaitOptions::WUNTRACED | WaitOptions::WCONTINUED)
+    {
+        return Err(LinuxError::EINVAL);
+    }
+    // Waiting through a nonblocking pidfd fails with `EAGAIN` instead of
+    // blocking.
+    let mut nonblocking = false;
+    let pid = match idtype {
+        P_ALL => WaitPid::Any,
+        P_PID => WaitPid::Pid(id as _),
+        P_PGID if id == 0 => WaitPid::Pgid(current().task_ext().thread.process().group().pgid()),
+        P_PGID => WaitPid::Pgid(id as _),
+        P_PIDFD => {
+            let pidfd = PidFd::from_fd(id as _)?;
+            nonblocking = pidfd.is_nonblocking() && !options.contains(WaitOptions::WNOHANG);
+            if nonblocking {
+                options |= WaitOptions::WNOHANG;
+            }
+            WaitPid::Pid(pidfd.process().pid())
+        }
+        _ => return Err(LinuxError::EINVAL),
+    };
+
+    let info = nullable!(info.get_as_mut())?;
+    let child = wait_child(pid, &options)?;
+    if child.is_none() && nonblocking {
+        return Err(LinuxError::EAGAIN);
+    }
+    if let Some(info) = info {
+        *info = match child {
+            Some(child) => child_siginfo(&child),
+            // SAFETY: `siginfo` is plain data, and `si_pid` is zero if no
+            // child has exited.
+            None => unsafe { mem::zeroed() },
+        };
+    }
+    Ok(0)
+}
+
+/// Describe the exit of `child` as the `SIGCHLD` signal sent for it.
+fn child_siginfo(child: &Process) -> siginfo {
+    let exit_code = child.exit_code();
+    let (code, status) = if exit_code & 0x7f == 0 {
+        (CLD_EXITED, (exit_code >> 8) & 0xff)
+    } else {
+        (CLD_KILLED, exit_code & 0x7f)
+    };
+    let mut sig = SignalInfo::new(Signo::SIGCHLD, code as _);
+    let fields = &mut sig.0.__bindgen_anon_1.__bindgen_anon_1._sifields._sigchld;
+    fields._pid = child.pid() as _;
+    fields._status = status;
+    sig.0
+}
@@ -1,11 +1,18 @@
+use core::{ffi::c_int, time::Duration};
+
 use axerrno::{LinuxError, LinuxResult};
 use axhal::time::{monotonic_time, monotonic_time_nanos, nanos_to_ticks, wall_time};
 use linux_raw_sys::general::{
-    __kernel_clockid_t, CLOCK_MONOTONIC, CLOCK_REALTIME, timespec, timeval,
+    __kernel_clockid_t, CLOCK_MONOTONIC, CLOCK_REALTIME, TFD_CLOEXEC, TFD_NONBLOCK,
+    TFD_TIMER_ABSTIME, TFD_TIMER_CANCEL_ON_SET, itimerspec, timespec, timeval,
 };
 use starry_core::task::time_stat_output;
 
-use crate::{ptr::UserPtr, time::TimeValueLike};
+use crate::{
+    file::{FileLike, TimerFd},
+    ptr::{UserConstPtr, UserPtr, nullable},
+    time::TimeValueLike,
+};
 
 pub fn sys_clock_gettime(
     clock_id: __kernel_clockid_t,
@@ -53,3 +60,78 @@ pub fn sys_times(tms: UserPtr<Tms>) -> LinuxResult<isize> {
     };
     Ok(nanos_to_ticks(monotonic_time_nanos()) as _)
 }
+
+/// Create a timer delivering its expirations through a file.
+pub fn sys_timerfd_create(clock_id: __kernel_clockid_t, flags: u32) -> LinuxResult<isize> {
+    debug!(
+        "sys_timerfd_create <= clock_id: {}, flags: {:#x}",
+        clock_id, flags
+    );
+    let clock = clock_id as u32;
+    if !matches!(clock, CLOCK_REALTIME | CLOCK_MONOTONIC) {
+        warn!(
+            "Called sys_timerfd_create for unsupported clock {}",
+            clock_id
+        );
+        return Err(LinuxError::EINVAL);
+    }
+    if flags & !(TFD_NONBLOCK | TFD_CLOEXEC) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let timerfd = TimerFd::new(clock);
+    if flags & TFD_NONBLOCK != 0 {
+        timerfd.set_nonblocking(true)?;
+    }
+    if flags & TFD_CLOEXEC != 0 {
+        // TODO: fd close-on-exec
+        warn!("sys_timerfd_create: TFD_CLOEXEC is ignored");
+    }
+    Ok(timerfd.add_to_fd_table()? as _)
+}
+
+fn make_itimerspec((value, interval): (Duration, Duration)) -> itimerspec {
+    itimerspec {
+        it_interval: timespec::from_time_value(interval),
+        it_value: timespec::from_time_value(value),
+    }
+}
+
+/// Arm or disarm the timer `fd`, storing its previous setting in
+/// `old_value`.
+///
+/// With `TFD_TIMER_ABSTIME`, `new_value.it_value` is an absolute time on the
+/// clock of the timer. `TFD_TIMER_CANCEL_ON_SET` is accepted, but the clock
+/// is never set, so the timer is never canceled.
+pub fn sys_timerfd_settime(
+    fd: c_int,
+    flags: u32,
+    new_value: UserConstPtr<itimerspec>,
+    old_value: UserPtr<itimerspec>,
+) -> LinuxResult<isize> {
+    if flags & !(TFD_TIMER_ABSTIME | TFD_TIMER_CANCEL_ON_SET) != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let timerfd = TimerFd::from_fd(fd)?;
+    let new_value = new_value.get_as_ref()?;
+    let valid = |ts: &timespec| ts.tv_sec >= 0 && (0..1_000_000_000).contains(&ts.tv_nsec);
+    if !valid(&new_value.it_value) || !valid(&new_value.it_interval) {
+        return Err(LinuxError::EINVAL);
+    }
+    let old = timerfd.set(
+        new_value.it_value.to_time_value(),
+        new_value.it_interval.to_time_value(),
+        flags & TFD_TIMER_ABSTIME != 0,
+    );
+    if let Some(old_value) = nullable!(old_value.get_as_mut())? {
+        *old_value = make_itimerspec(old);
+    }
+    Ok(0)
+}
+
+/// Get the time until the next expiration of the timer `fd` and its
+/// interval.
+pub fn sys_timerfd_gettime(fd: c_int, curr_value: UserPtr<itimerspec>) -> LinuxResult<isize> {
+    let timerfd = TimerFd::from_fd(fd)?;
+    *curr_value.get_as_mut()? = make_itimerspec(timerfd.get());
+    Ok(0)
+}
@@ -6,6 +6,7 @@ extern crate axlog;
 extern crate alloc;
 
 pub mod file;
+pub mod netif;
 pub mod path;
 pub mod ptr;
 pub mod signal;
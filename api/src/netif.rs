@@ -0,0 +1,67 @@
+//! Description of the network interfaces managed by axnet.
+
+use core::{ffi::c_int, net::Ipv4Addr};
+
+use linux_raw_sys::net::net_device_flags;
+
+/// `ARPHRD_LOOPBACK`, the hardware type of the loopback device.
+pub const ARPHRD_LOOPBACK: u16 = 772;
+
+/// A network interface as seen by user space.
+pub struct NetInterface {
+    pub name: &'static str,
+    pub index: c_int,
+    /// `IFF_*` flags.
+    pub flags: u32,
+    pub addr: Ipv4Addr,
+    pub prefix_len: u8,
+    /// `ARPHRD_*` hardware type.
+    pub hw_type: u16,
+    pub hw_addr: [u8; 6],
+    pub mtu: c_int,
+    /// The default gateway reached through this interface.
+    pub gateway: Option<Ipv4Addr>,
+}
+
+impl NetInterface {
+    pub fn netmask(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(
+            u32::MAX
+                .checked_shl(32 - self.prefix_len as u32)
+                .unwrap_or(0),
+        )
+    }
+
+    pub fn network(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(self.addr.to_bits() & self.netmask().to_bits())
+    }
+
+    pub fn broadcast(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(self.addr.to_bits() | !self.netmask().to_bits())
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        self.hw_type == ARPHRD_LOOPBACK
+    }
+}
+
+/// Get the interfaces managed by axnet.
+///
+/// axnet does not expose whether it has found a NIC, nor the addresses it
+/// configured on it, so only the loopback interface, whose configuration is
+/// fixed, can be reported.
+pub fn interfaces() -> [NetInterface; 1] {
+    use net_device_flags::*;
+
+    [NetInterface {
+        name: "lo",
+        index: 1,
+        flags: IFF_UP as u32 | IFF_LOOPBACK as u32 | IFF_RUNNING as u32,
+        addr: Ipv4Addr::LOCALHOST,
+        prefix_len: 8,
+        hw_type: ARPHRD_LOOPBACK,
+        hw_addr: [0; 6],
+        mtu: 65536,
+        gateway: None,
+    }]
+}
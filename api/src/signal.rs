@@ -1,14 +1,25 @@
+use core::mem;
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+};
 use axerrno::{LinuxError, LinuxResult};
 use axhal::{
     arch::TrapFrame,
     trap::{POST_TRAP, register_trap_handler},
 };
-use axprocess::{Process, ProcessGroup, Thread};
-use axsignal::{SignalInfo, SignalOSAction, SignalSet};
+use axprocess::{Pid, Process, ProcessGroup, Thread};
+use axsignal::{SignalInfo, SignalOSAction, SignalSet, Signo};
+use axsync::Mutex;
 use axtask::{TaskExtRef, current};
+use linux_raw_sys::general::SI_USER;
 use starry_core::task::{ProcessData, ThreadData};
 
-use crate::do_exit;
+use crate::{
+    do_exit,
+    file::{PollWaiter, current_worker, notify_signalfds},
+};
 
 pub fn check_signals(tf: &mut TrapFrame, restore_blocked: Option<SignalSet>) -> bool {
     let Some((sig, os_action)) = current()
@@ -49,24 +60,106 @@ fn post_trap_callback(tf: &mut TrapFrame, from_user: bool) {
         return;
     }
 
-    check_signals(tf, None);
+    let restore_blocked = current()
+        .task_ext()
+        .thread_data()
+        .saved_sigmask
+        .lock()
+        .take();
+    if !check_signals(tf, restore_blocked) {
+        if let Some(blocked) = restore_blocked {
+            current()
+                .task_ext()
+                .thread_data()
+                .signal
+                .with_blocked_mut(|old| *old = blocked);
+        }
+    }
+}
+
+/// Whether the current thread has a pending signal that is not blocked,
+/// which interrupts blocking system calls.
+pub fn has_pending_signal() -> bool {
+    let signal = &current().task_ext().thread_data().signal;
+    let mut pending = signal.pending();
+    pending &= !signal.with_blocked_mut(|blocked| *blocked);
+    pending != SignalSet::default()
+}
+
+/// Run `f` with the signal mask of the current thread replaced by `mask` if
+/// given, as `ppoll` and alike do.
+///
+/// If `f` is interrupted by a signal, the old mask is only restored after the
+/// signal has been handled, so that the signals unblocked by `mask` get
+/// delivered.
+pub fn with_sigmask<T>(
+    mask: Option<SignalSet>,
+    f: impl FnOnce() -> LinuxResult<T>,
+) -> LinuxResult<T> {
+    let Some(mut mask) = mask else {
+        return f();
+    };
+    mask.remove(Signo::SIGKILL);
+    mask.remove(Signo::SIGSTOP);
+
+    let curr = current();
+    let thr_data = curr.task_ext().thread_data();
+    let old_blocked = thr_data
+        .signal
+        .with_blocked_mut(|blocked| mem::replace(blocked, mask));
+    let res = f();
+    if matches!(res, Err(LinuxError::EINTR)) {
+        *thr_data.saved_sigmask.lock() = Some(old_blocked);
+    } else {
+        thr_data
+            .signal
+            .with_blocked_mut(|blocked| *blocked = old_blocked);
+    }
+    res
+}
+
+/// Waiters of the threads blocked in interruptible waits, woken up when a
+/// signal is sent to the thread or its process.
+static SIGNAL_WAITERS: Mutex<BTreeMap<Pid, Weak<PollWaiter>>> = Mutex::new(BTreeMap::new());
+
+/// Run `f` with `waiter` woken up by the signals sent to the current thread.
+pub fn with_signal_waiter<T>(waiter: &Arc<PollWaiter>, f: impl FnOnce() -> T) -> T {
+    let tid = current().task_ext().thread.tid();
+    SIGNAL_WAITERS.lock().insert(tid, Arc::downgrade(waiter));
+    let res = f();
+    SIGNAL_WAITERS.lock().remove(&tid);
+    res
+}
+
+fn wake_signal_waiter(tid: Pid) {
+    let waiter = SIGNAL_WAITERS.lock().get(&tid).and_then(Weak::upgrade);
+    if let Some(waiter) = waiter {
+        waiter.wake();
+    }
 }
 
 pub fn send_signal_thread(thr: &Thread, sig: SignalInfo) -> LinuxResult<()> {
     info!("Send signal {:?} to thread {}", sig.signo(), thr.tid());
+    let tid = thr.tid();
     let Some(thr) = thr.data::<ThreadData>() else {
         return Err(LinuxError::EPERM);
     };
     thr.signal.send_signal(sig);
+    wake_signal_waiter(tid);
+    notify_signalfds();
     Ok(())
 }
 
 pub fn send_signal_process(proc: &Process, sig: SignalInfo) -> LinuxResult<()> {
     info!("Send signal {:?} to process {}", sig.signo(), proc.pid());
-    let Some(proc) = proc.data::<ProcessData>() else {
+    let Some(data) = proc.data::<ProcessData>() else {
         return Err(LinuxError::EPERM);
     };
-    proc.signal.send_signal(sig);
+    data.signal.send_signal(sig);
+    for thr in proc.threads() {
+        wake_signal_waiter(thr.tid());
+    }
+    notify_signalfds();
     Ok(())
 }
 
@@ -82,3 +175,14 @@ pub fn send_signal_process_group(pg: &ProcessGroup, sig: SignalInfo) -> usize {
     }
     count
 }
+
+/// Send `SIGPIPE` to the current thread, as writing to a pipe or stream
+/// socket that can no longer be written to does.
+pub fn send_sigpipe() {
+    // Kernel workers act for a thread, which is not signaled on their behalf.
+    if current_worker().is_some() {
+        return;
+    }
+    let sig = SignalInfo::new(Signo::SIGPIPE, SI_USER as _);
+    let _ = send_signal_thread(&current().task_ext().thread, sig);
+}
@@ -1,14 +1,20 @@
 //! Wrapper for [`sockaddr`]. Using trait to convert between [`SocketAddr`] and [`sockaddr`] types.
 
 use crate::ptr::{UserConstPtr, UserPtr};
+use alloc::{string::String, vec::Vec};
 use axerrno::{LinuxError, LinuxResult};
 use core::{
-    mem::{MaybeUninit, size_of},
+    ffi::c_char,
+    mem::{MaybeUninit, offset_of, size_of},
     net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    str,
 };
-use linux_raw_sys::net::{
-    __kernel_sa_family_t, AF_INET, AF_INET6, in_addr, in6_addr, sockaddr, sockaddr_in,
-    sockaddr_in6, socklen_t,
+use linux_raw_sys::{
+    net::{
+        __kernel_sa_family_t, AF_INET, AF_INET6, AF_NETLINK, AF_UNIX, AF_UNSPEC, in_addr, in6_addr,
+        sockaddr, sockaddr_in, sockaddr_in6, sockaddr_un, socklen_t,
+    },
+    netlink::sockaddr_nl,
 };
 
 /// Trait to extend [`SocketAddr`] and its variants with methods for reading from and writing to user space.
@@ -20,7 +26,11 @@ pub trait SocketAddrExt: Sized {
 
     /// This method serializes the current socket address instance into the
     /// [`sockaddr`] structure pointed to by `addr` in user space.
-    fn write_to_user(&self, addr: UserPtr<sockaddr>) -> LinuxResult<socklen_t>;
+    ///
+    /// `addrlen` is the size of the buffer at `addr`, the address is
+    /// truncated if it does not fit. It is then set to the full length of
+    /// the address.
+    fn write_to_user(&self, addr: UserPtr<sockaddr>, addrlen: &mut socklen_t) -> LinuxResult;
 
     /// Gets the address family of the socket address.
     fn family(&self) -> u16;
@@ -51,6 +61,33 @@ fn copy_sockaddr_from_user(
     Ok(storage)
 }
 
+/// Copies the socket address `raw` of `len` bytes to user space.
+///
+/// At most `*addrlen` bytes are copied to `addr`, and `*addrlen` is set to
+/// `len` afterwards, so that the caller can tell whether the address was
+/// truncated.
+#[inline]
+fn copy_sockaddr_to_user<T>(
+    raw: &T,
+    len: socklen_t,
+    addr: UserPtr<sockaddr>,
+    addrlen: &mut socklen_t,
+) -> LinuxResult {
+    if addr.is_null() || *addrlen > i32::MAX as socklen_t {
+        return Err(LinuxError::EINVAL);
+    }
+    let copied = (*addrlen).min(len) as usize;
+    if copied > 0 {
+        // SAFETY: `len` never exceeds the size of `T`.
+        let src = unsafe { core::slice::from_raw_parts(raw as *const T as *const u8, copied) };
+        UserPtr::<u8>::from(addr.address().as_usize())
+            .get_as_mut_slice(copied)?
+            .copy_from_slice(src);
+    }
+    *addrlen = len;
+    Ok(())
+}
+
 impl SocketAddrExt for SocketAddr {
     /// Reads a [`SocketAddr`] from user space.
     ///
@@ -82,17 +119,13 @@ impl SocketAddrExt for SocketAddr {
 
     /// Writes the [`SocketAddr`] to user space.
     ///
-    /// This implementation checks for a null user-space pointer. Then, it delegates
-    /// the actual writing to the specific [`SocketAddrV4`] or [`SocketAddrV6`]
-    /// `write_to_user` implementation based on the variant of `self`.
-    fn write_to_user(&self, addr: UserPtr<sockaddr>) -> LinuxResult<socklen_t> {
-        if addr.is_null() {
-            return Err(LinuxError::EINVAL);
-        }
-
+    /// This implementation delegates the actual writing to the specific
+    /// [`SocketAddrV4`] or [`SocketAddrV6`] `write_to_user` implementation
+    /// based on the variant of `self`.
+    fn write_to_user(&self, addr: UserPtr<sockaddr>, addrlen: &mut socklen_t) -> LinuxResult {
         match self {
-            SocketAddr::V4(v4) => v4.write_to_user(addr),
-            SocketAddr::V6(v6) => v6.write_to_user(addr),
+            SocketAddr::V4(v4) => v4.write_to_user(addr, addrlen),
+            SocketAddr::V6(v6) => v6.write_to_user(addr, addrlen),
         }
     }
 
@@ -137,12 +170,7 @@ impl SocketAddrExt for SocketAddrV4 {
     }
 
     /// Writes the `SocketAddrV4` to user space.
-    fn write_to_user(&self, addr: UserPtr<sockaddr>) -> LinuxResult<socklen_t> {
-        if addr.is_null() {
-            return Err(LinuxError::EINVAL);
-        }
-        let dst_addr = addr.get_as_mut()?;
-        let len = size_of::<sockaddr_in>() as socklen_t;
+    fn write_to_user(&self, addr: UserPtr<sockaddr>, addrlen: &mut socklen_t) -> LinuxResult {
         let sockin_addr = sockaddr_in {
             sin_family: AF_INET as _,
             sin_port: self.port().to_be(),
@@ -151,15 +179,7 @@ impl SocketAddrExt for SocketAddrV4 {
             },
             __pad: [0_u8; 8],
         };
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                &sockin_addr as *const sockaddr_in as *const u8,
-                dst_addr as *mut sockaddr as *mut u8,
-                len as usize,
-            )
-        };
-
-        Ok(len)
+        copy_sockaddr_to_user(&sockin_addr, self.addr_len(), addr, addrlen)
     }
 
     /// Gets the address family for [`SocketAddrV4`].
@@ -193,12 +213,7 @@ impl SocketAddrExt for SocketAddrV6 {
         ))
     }
     /// Writes the `SocketAddrV6` to user space.
-    fn write_to_user(&self, addr: UserPtr<sockaddr>) -> LinuxResult<socklen_t> {
-        if addr.is_null() {
-            return Err(LinuxError::EINVAL);
-        }
-        let dst_addr = addr.get_as_mut()?;
-        let len = size_of::<sockaddr_in6>() as socklen_t;
+    fn write_to_user(&self, addr: UserPtr<sockaddr>, addrlen: &mut socklen_t) -> LinuxResult {
         let sockin_addr = sockaddr_in6 {
             sin6_family: AF_INET6 as _,
             sin6_port: self.port().to_be(),
@@ -210,16 +225,7 @@ impl SocketAddrExt for SocketAddrV6 {
             },
             sin6_scope_id: self.scope_id(),
         };
-
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                &sockin_addr as *const sockaddr_in6 as *const u8,
-                dst_addr as *mut sockaddr as *mut u8,
-                len as usize,
-            )
-        };
-
-        Ok(len)
+        copy_sockaddr_to_user(&sockin_addr, self.addr_len(), addr, addrlen)
     }
 
     /// Gets the address family for [`SocketAddrV6`].
@@ -232,3 +238,225 @@ impl SocketAddrExt for SocketAddrV6 {
         size_of::<sockaddr_in6>() as socklen_t
     }
 }
+
+/// The address of a Unix domain socket.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixSocketAddr {
+    /// A socket that is not bound to a name.
+    Unnamed,
+    /// A socket bound to a filesystem path name.
+    Path(String),
+    /// A socket bound to a name in the Linux abstract namespace, without the
+    /// leading null byte.
+    Abstract(Vec<u8>),
+}
+
+impl SocketAddrExt for UnixSocketAddr {
+    /// Reads an [`UnixSocketAddr`] from user space.
+    ///
+    /// A `sun_path` starting with a null byte names an abstract socket, whose
+    /// name spans the rest of `addrlen` bytes. Otherwise `sun_path` is a
+    /// null-terminated path name. An address that only contains
+    /// `sun_family` is unnamed.
+    fn read_from_user(addr: UserConstPtr<sockaddr>, addrlen: socklen_t) -> LinuxResult<Self> {
+        const PATH_OFFSET: usize = offset_of!(sockaddr_un, sun_path);
+        if (addrlen as usize) < PATH_OFFSET || addrlen as usize > size_of::<sockaddr_un>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let storage = copy_sockaddr_from_user(addr, addrlen)?;
+        let addr_un = unsafe { &*(storage.as_ptr() as *const sockaddr_un) };
+        if addr_un.sun_family as u32 != AF_UNIX {
+            return Err(LinuxError::EINVAL);
+        }
+
+        let path = &addr_un.sun_path[..addrlen as usize - PATH_OFFSET];
+        // SAFETY: c_char is u8
+        let path = unsafe { &*(path as *const [c_char] as *const [u8]) };
+        match path {
+            [] => Ok(UnixSocketAddr::Unnamed),
+            [0, name @ ..] => Ok(UnixSocketAddr::Abstract(name.to_vec())),
+            path => {
+                let len = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+                let path = str::from_utf8(&path[..len]).map_err(|_| LinuxError::EINVAL)?;
+                Ok(UnixSocketAddr::Path(path.into()))
+            }
+        }
+    }
+
+    /// Writes the [`UnixSocketAddr`] to user space.
+    ///
+    /// At most `addr_len()` bytes are written.
+    fn write_to_user(&self, addr: UserPtr<sockaddr>, addrlen: &mut socklen_t) -> LinuxResult {
+        // SAFETY: `sockaddr_un` is plain old data.
+        let mut addr_un: sockaddr_un = unsafe { core::mem::zeroed() };
+        addr_un.sun_family = AF_UNIX as _;
+        let (offset, name) = match self {
+            UnixSocketAddr::Unnamed => (0, &[][..]),
+            UnixSocketAddr::Path(name) => (0, name.as_bytes()),
+            // The name follows the leading null byte.
+            UnixSocketAddr::Abstract(name) => (1, &name[..]),
+        };
+        for (dst, src) in addr_un.sun_path[offset..].iter_mut().zip(name) {
+            *dst = *src as _;
+        }
+        copy_sockaddr_to_user(&addr_un, self.addr_len(), addr, addrlen)
+    }
+
+    /// Gets the address family for [`UnixSocketAddr`].
+    fn family(&self) -> u16 {
+        AF_UNIX as u16
+    }
+
+    /// Gets the encoded length of [`UnixSocketAddr`], which covers
+    /// `sun_family` and the used part of `sun_path`.
+    ///
+    /// A path name that fills `sun_path` has no terminating null byte.
+    fn addr_len(&self) -> socklen_t {
+        let path_len = match self {
+            UnixSocketAddr::Unnamed => 0,
+            UnixSocketAddr::Path(name) => name.len() + 1,
+            UnixSocketAddr::Abstract(name) => name.len() + 1,
+        };
+        (offset_of!(sockaddr_un, sun_path) + path_len).min(size_of::<sockaddr_un>()) as socklen_t
+    }
+}
+
+/// The address of a netlink socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetlinkSocketAddr {
+    /// The port ID, 0 for the kernel.
+    pub pid: u32,
+    /// The bitmask of multicast groups.
+    pub groups: u32,
+}
+
+impl SocketAddrExt for NetlinkSocketAddr {
+    /// Reads a [`NetlinkSocketAddr`] from user space.
+    fn read_from_user(addr: UserConstPtr<sockaddr>, addrlen: socklen_t) -> LinuxResult<Self> {
+        if (addrlen as usize) < size_of::<sockaddr_nl>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let storage = copy_sockaddr_from_user(addr, addrlen)?;
+        let addr_nl = unsafe { &*(storage.as_ptr() as *const sockaddr_nl) };
+        if addr_nl.nl_family as u32 != AF_NETLINK {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(Self {
+            pid: addr_nl.nl_pid,
+            groups: addr_nl.nl_groups,
+        })
+    }
+
+    /// Writes the [`NetlinkSocketAddr`] to user space.
+    fn write_to_user(&self, addr: UserPtr<sockaddr>, addrlen: &mut socklen_t) -> LinuxResult {
+        let addr_nl = sockaddr_nl {
+            nl_family: AF_NETLINK as _,
+            nl_pad: 0,
+            nl_pid: self.pid,
+            nl_groups: self.groups,
+        };
+        copy_sockaddr_to_user(&addr_nl, self.addr_len(), addr, addrlen)
+    }
+
+    /// Gets the address family for [`NetlinkSocketAddr`].
+    fn family(&self) -> u16 {
+        AF_NETLINK as u16
+    }
+
+    /// Gets the encoded length of [`NetlinkSocketAddr`].
+    fn addr_len(&self) -> socklen_t {
+        size_of::<sockaddr_nl>() as socklen_t
+    }
+}
+
+/// A socket address of any of the supported families.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketAddress {
+    /// An `AF_INET` or `AF_INET6` address.
+    Inet(SocketAddr),
+    /// An `AF_UNIX` address.
+    Unix(UnixSocketAddr),
+    /// An `AF_NETLINK` address.
+    Netlink(NetlinkSocketAddr),
+    /// An `AF_UNSPEC` address, which dissolves the association of a socket
+    /// when passed to `connect`.
+    Unspec,
+}
+
+impl SocketAddrExt for SocketAddress {
+    /// Reads a [`SocketAddress`] from user space, dispatching on the address
+    /// family like [`SocketAddr::read_from_user`] does.
+    fn read_from_user(addr: UserConstPtr<sockaddr>, addrlen: socklen_t) -> LinuxResult<Self> {
+        if size_of::<__kernel_sa_family_t>() > addrlen as usize
+            || addrlen as usize > size_of::<sockaddr>()
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let src_addr = addr.get_as_ref()?;
+        let family = unsafe {
+            src_addr
+                .__storage
+                .__bindgen_anon_1
+                .__bindgen_anon_1
+                .ss_family as u32
+        };
+        match family {
+            AF_INET | AF_INET6 => SocketAddr::read_from_user(addr, addrlen).map(Self::Inet),
+            AF_UNIX => UnixSocketAddr::read_from_user(addr, addrlen).map(Self::Unix),
+            AF_NETLINK => NetlinkSocketAddr::read_from_user(addr, addrlen).map(Self::Netlink),
+            AF_UNSPEC => Ok(Self::Unspec),
+            _ => Err(LinuxError::EAFNOSUPPORT),
+        }
+    }
+
+    /// Writes the [`SocketAddress`] to user space.
+    fn write_to_user(&self, addr: UserPtr<sockaddr>, addrlen: &mut socklen_t) -> LinuxResult {
+        match self {
+            Self::Inet(inet) => inet.write_to_user(addr, addrlen),
+            Self::Unix(unix) => unix.write_to_user(addr, addrlen),
+            Self::Netlink(netlink) => netlink.write_to_user(addr, addrlen),
+            Self::Unspec => {
+                let family = AF_UNSPEC as __kernel_sa_family_t;
+                copy_sockaddr_to_user(&family, self.addr_len(), addr, addrlen)
+            }
+        }
+    }
+
+    /// Gets the address family of the [`SocketAddress`].
+    fn family(&self) -> u16 {
+        match self {
+            Self::Inet(inet) => inet.family(),
+            Self::Unix(unix) => unix.family(),
+            Self::Netlink(netlink) => netlink.family(),
+            Self::Unspec => AF_UNSPEC as u16,
+        }
+    }
+
+    /// Gets the encoded length of the [`SocketAddress`].
+    fn addr_len(&self) -> socklen_t {
+        match self {
+            Self::Inet(inet) => inet.addr_len(),
+            Self::Unix(unix) => unix.addr_len(),
+            Self::Netlink(netlink) => netlink.addr_len(),
+            Self::Unspec => size_of::<__kernel_sa_family_t>() as socklen_t,
+        }
+    }
+}
+
+impl From<SocketAddr> for SocketAddress {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Inet(addr)
+    }
+}
+
+impl From<UnixSocketAddr> for SocketAddress {
+    fn from(addr: UnixSocketAddr) -> Self {
+        Self::Unix(addr)
+    }
+}
+
+impl From<NetlinkSocketAddr> for SocketAddress {
+    fn from(addr: NetlinkSocketAddr) -> Self {
+        Self::Netlink(addr)
+    }
+}
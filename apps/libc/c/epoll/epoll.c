@@ -0,0 +1,185 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <sys/epoll.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int add(int epfd, int fd, unsigned events, int tag) {
+  struct epoll_event ev = {0};
+  ev.events = events;
+  ev.data.u32 = tag;
+  return epoll_ctl(epfd, EPOLL_CTL_ADD, fd, &ev);
+}
+
+// Wait for at most `timeout` ms and return the tag of the single ready
+// entry, 0 if none is ready or -1 on errors.
+static int wait_one(int epfd, int timeout) {
+  struct epoll_event ev;
+  int n = epoll_wait(epfd, &ev, 1, timeout);
+  return n <= 0 ? n : (int)ev.data.u32;
+}
+
+void test_epoll_level() {
+  int epfd = epoll_create1(EPOLL_CLOEXEC);
+  int fds[2];
+  pipe(fds);
+  add(epfd, fds[0], EPOLLIN, 1);
+  if (wait_one(epfd, 0) == 0) {
+    puts("test_epoll_level ok1");
+  }
+  write(fds[1], "ab", 2);
+  // Level-triggered entries are reported while data is left.
+  char c;
+  if (wait_one(epfd, 0) == 1 && read(fds[0], &c, 1) == 1 &&
+      wait_one(epfd, 0) == 1 && read(fds[0], &c, 1) == 1 &&
+      wait_one(epfd, 0) == 0) {
+    puts("test_epoll_level ok2");
+  }
+  if (add(epfd, fds[0], EPOLLIN, 1) < 0 && errno == EEXIST) {
+    puts("test_epoll_level ok3");
+  }
+  close(fds[0]);
+  close(fds[1]);
+  close(epfd);
+}
+
+void test_epoll_edge() {
+  int epfd = epoll_create1(0);
+  int fds[2];
+  pipe(fds);
+  add(epfd, fds[0], EPOLLIN | EPOLLET, 2);
+  write(fds[1], "ab", 2);
+  if (wait_one(epfd, 0) == 2 && wait_one(epfd, 0) == 0) {
+    puts("test_epoll_edge ok1");
+  }
+  // New data is a new edge, even if older data was left.
+  write(fds[1], "c", 1);
+  if (wait_one(epfd, 0) == 2) {
+    puts("test_epoll_edge ok2");
+  }
+  close(fds[0]);
+  close(fds[1]);
+  close(epfd);
+}
+
+void test_epoll_oneshot() {
+  int epfd = epoll_create1(0);
+  int fds[2];
+  pipe(fds);
+  add(epfd, fds[0], EPOLLIN | EPOLLONESHOT, 3);
+  write(fds[1], "a", 1);
+  if (wait_one(epfd, 0) == 3 && wait_one(epfd, 0) == 0) {
+    puts("test_epoll_oneshot ok1");
+  }
+  // `EPOLL_CTL_MOD` rearms the entry.
+  struct epoll_event ev = {EPOLLIN | EPOLLONESHOT, {.u32 = 4}};
+  if (epoll_ctl(epfd, EPOLL_CTL_MOD, fds[0], &ev) == 0 &&
+      wait_one(epfd, 0) == 4) {
+    puts("test_epoll_oneshot ok2");
+  }
+  if (epoll_ctl(epfd, EPOLL_CTL_DEL, fds[0], NULL) == 0 &&
+      epoll_ctl(epfd, EPOLL_CTL_DEL, fds[0], NULL) < 0 && errno == ENOENT) {
+    puts("test_epoll_oneshot ok3");
+  }
+  close(fds[0]);
+  close(fds[1]);
+  close(epfd);
+}
+
+void test_epoll_nested() {
+  int outer = epoll_create1(0);
+  int inner = epoll_create1(0);
+  int fds[2];
+  pipe(fds);
+  add(inner, fds[0], EPOLLIN, 5);
+  add(outer, inner, EPOLLIN, 6);
+  if (wait_one(outer, 0) == 0) {
+    puts("test_epoll_nested ok1");
+  }
+  write(fds[1], "a", 1);
+  if (wait_one(outer, 0) == 6 && wait_one(inner, 0) == 5) {
+    puts("test_epoll_nested ok2");
+  }
+  if (add(inner, outer, EPOLLIN, 7) < 0 && errno == ELOOP) {
+    puts("test_epoll_nested ok3");
+  }
+  close(fds[0]);
+  close(fds[1]);
+  close(inner);
+  close(outer);
+}
+
+void test_epoll_close() {
+  int epfd = epoll_create1(0);
+  int fds[2];
+  pipe(fds);
+  int copy = dup(fds[0]);
+  add(epfd, fds[0], EPOLLIN, 8);
+  write(fds[1], "a", 1);
+  // Entries belong to the open file, which lives on in the copy.
+  close(fds[0]);
+  if (wait_one(epfd, 0) == 8) {
+    puts("test_epoll_close ok1");
+  }
+  close(copy);
+  if (wait_one(epfd, 0) == 0) {
+    puts("test_epoll_close ok2");
+  }
+  close(fds[1]);
+  close(epfd);
+}
+
+void test_epoll_tcp_edge() {
+  struct sockaddr_in addr = {0};
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(5061);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  int server = socket(AF_INET, SOCK_STREAM, 0);
+  int one = 1;
+  setsockopt(server, SOL_SOCKET, SO_REUSEADDR, &one, sizeof(one));
+  bind(server, (struct sockaddr *)&addr, sizeof(addr));
+  listen(server, 1);
+  int client = socket(AF_INET, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    perror("test_epoll_tcp_edge");
+    return;
+  }
+  int conn = accept(server, NULL, NULL);
+  fcntl(conn, F_SETFL, O_NONBLOCK);
+
+  int epfd = epoll_create1(0);
+  add(epfd, conn, EPOLLIN | EPOLLET, 9);
+  send(client, "abc", 3, 0);
+  if (wait_one(epfd, 1000) == 9) {
+    puts("test_epoll_tcp_edge ok1");
+  }
+  // Drain the socket, after which only new data fires again.
+  char buf[8];
+  while (recv(conn, buf, sizeof(buf), 0) > 0) {
+  }
+  if (errno == EAGAIN && wait_one(epfd, 0) == 0) {
+    puts("test_epoll_tcp_edge ok2");
+  }
+  send(client, "d", 1, 0);
+  if (wait_one(epfd, 1000) == 9 && recv(conn, buf, sizeof(buf), 0) == 1) {
+    puts("test_epoll_tcp_edge ok3");
+  }
+  close(epfd);
+  close(conn);
+  close(client);
+  close(server);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_epoll_level();
+  test_epoll_edge();
+  test_epoll_oneshot();
+  test_epoll_nested();
+  test_epoll_close();
+  test_epoll_tcp_edge();
+  return 0;
+}
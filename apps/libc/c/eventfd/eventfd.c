@@ -0,0 +1,67 @@
+#include <errno.h>
+#include <poll.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/eventfd.h>
+#include <unistd.h>
+
+void test_eventfd_counter() {
+  int fd = eventfd(3, EFD_NONBLOCK);
+  uint64_t value = 4;
+  write(fd, &value, sizeof(value));
+  if (read(fd, &value, sizeof(value)) == sizeof(value) && value == 7) {
+    puts("test_eventfd_counter ok1");
+  }
+  if (read(fd, &value, sizeof(value)) < 0 && errno == EAGAIN) {
+    puts("test_eventfd_counter ok2");
+  }
+  // The counter cannot reach the maximum value of a u64.
+  value = UINT64_MAX;
+  if (write(fd, &value, sizeof(value)) < 0 && errno == EINVAL) {
+    puts("test_eventfd_counter ok3");
+  }
+  value = UINT64_MAX - 1;
+  uint64_t one = 1;
+  if (write(fd, &value, sizeof(value)) == sizeof(value) &&
+      write(fd, &one, sizeof(one)) < 0 && errno == EAGAIN) {
+    puts("test_eventfd_counter ok4");
+  }
+  char small[4];
+  if (read(fd, small, sizeof(small)) < 0 && errno == EINVAL) {
+    puts("test_eventfd_counter ok5");
+  }
+  close(fd);
+}
+
+void test_eventfd_semaphore() {
+  int fd = eventfd(2, EFD_SEMAPHORE | EFD_NONBLOCK);
+  uint64_t a = 0, b = 0, c;
+  if (read(fd, &a, sizeof(a)) == sizeof(a) &&
+      read(fd, &b, sizeof(b)) == sizeof(b) && a == 1 && b == 1 &&
+      read(fd, &c, sizeof(c)) < 0 && errno == EAGAIN) {
+    puts("test_eventfd_semaphore ok");
+  }
+  close(fd);
+}
+
+void test_eventfd_poll() {
+  int fd = eventfd(0, 0);
+  struct pollfd pfd = {fd, POLLIN | POLLOUT, 0};
+  if (poll(&pfd, 1, 0) == 1 && pfd.revents == POLLOUT) {
+    puts("test_eventfd_poll ok1");
+  }
+  uint64_t value = 1;
+  write(fd, &value, sizeof(value));
+  if (poll(&pfd, 1, 0) == 1 && pfd.revents == (POLLIN | POLLOUT)) {
+    puts("test_eventfd_poll ok2");
+  }
+  close(fd);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_eventfd_counter();
+  test_eventfd_semaphore();
+  test_eventfd_poll();
+  return 0;
+}
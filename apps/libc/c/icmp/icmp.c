@@ -0,0 +1,26 @@
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <sys/socket.h>
+
+// axnet does not expose ICMP sockets, so ping and raw sockets are refused
+// with the errors Linux uses for unsupported protocols and types.
+void test_icmp_refused() {
+  if (socket(AF_INET, SOCK_DGRAM, IPPROTO_ICMP) < 0 &&
+      errno == EPROTONOSUPPORT) {
+    puts("test_icmp_refused ok1");
+  }
+  if (socket(AF_INET6, SOCK_DGRAM, IPPROTO_ICMPV6) < 0 &&
+      errno == EPROTONOSUPPORT) {
+    puts("test_icmp_refused ok2");
+  }
+  if (socket(AF_INET, SOCK_RAW, IPPROTO_ICMP) < 0 && errno == ESOCKTNOSUPPORT) {
+    puts("test_icmp_refused ok3");
+  }
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_icmp_refused();
+  return 0;
+}
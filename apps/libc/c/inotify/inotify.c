@@ -0,0 +1,96 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/inotify.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define DIR_PATH "/starry_inotify"
+#define FILE_PATH DIR_PATH "/a.txt"
+#define MOVED_PATH DIR_PATH "/b.txt"
+
+static char buf[4096] __attribute__((aligned(8)));
+static struct inotify_event *events[16];
+static int nevents;
+
+// Read the queued events into `events`.
+static void read_events(int fd) {
+  nevents = 0;
+  int len = read(fd, buf, sizeof(buf));
+  for (int pos = 0; pos < len && nevents < 16;) {
+    struct inotify_event *event = (struct inotify_event *)(buf + pos);
+    events[nevents++] = event;
+    pos += sizeof(*event) + event->len;
+  }
+}
+
+// Whether the `i`th event has `mask` and, if not null, `name`.
+static int is_event(int i, unsigned mask, const char *name) {
+  return i < nevents && events[i]->mask == mask &&
+         (name == NULL ||
+          (events[i]->len && strcmp(events[i]->name, name) == 0));
+}
+
+void test_inotify_events() {
+  int fd = inotify_init1(IN_NONBLOCK);
+  mkdirat(AT_FDCWD, DIR_PATH, 0755);
+  int wd = inotify_add_watch(fd, DIR_PATH,
+                             IN_CREATE | IN_MODIFY | IN_CLOSE_WRITE |
+                                 IN_MOVED_FROM | IN_MOVED_TO | IN_DELETE);
+  if (fd < 0 || wd < 0) {
+    perror("test_inotify_events");
+    return;
+  }
+
+  int file = open(FILE_PATH, O_WRONLY | O_CREAT, 0644);
+  write(file, "data", 4);
+  close(file);
+  read_events(fd);
+  if (nevents == 3 && is_event(0, IN_CREATE, "a.txt") &&
+      is_event(1, IN_MODIFY, "a.txt") &&
+      is_event(2, IN_CLOSE_WRITE, "a.txt") && events[0]->wd == wd) {
+    puts("test_inotify_events ok1");
+  }
+
+  rename(FILE_PATH, MOVED_PATH);
+  read_events(fd);
+  if (nevents == 2 && is_event(0, IN_MOVED_FROM, "a.txt") &&
+      is_event(1, IN_MOVED_TO, "b.txt") && events[0]->cookie != 0 &&
+      events[0]->cookie == events[1]->cookie) {
+    puts("test_inotify_events ok2");
+  }
+
+  // Writes at explicit offsets are modifications as well.
+  int src = open(MOVED_PATH, O_RDWR);
+  off_t off_in = 0, off_out = 4;
+  read_events(fd);
+  if (copy_file_range(src, &off_in, src, &off_out, 4, 0) == 4) {
+    read_events(fd);
+    if (is_event(0, IN_MODIFY, "b.txt")) {
+      puts("test_inotify_events ok3");
+    }
+  }
+  close(src);
+  read_events(fd);
+
+  unlink(MOVED_PATH);
+  read_events(fd);
+  if (nevents == 1 && is_event(0, IN_DELETE, "b.txt")) {
+    puts("test_inotify_events ok4");
+  }
+
+  inotify_rm_watch(fd, wd);
+  read_events(fd);
+  if (nevents == 1 && is_event(0, IN_IGNORED, NULL)) {
+    puts("test_inotify_events ok5");
+  }
+  unlinkat(AT_FDCWD, DIR_PATH, AT_REMOVEDIR);
+  close(fd);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_inotify_events();
+  return 0;
+}
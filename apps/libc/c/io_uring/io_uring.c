@@ -0,0 +1,260 @@
+#include <errno.h>
+#include <poll.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/eventfd.h>
+#include <sys/mman.h>
+#include <sys/syscall.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+// The io_uring ABI, as the kernel headers of the toolchain may predate it.
+#define SYS_uring_setup 425
+#define SYS_uring_enter 426
+#define SYS_uring_register 427
+
+#define OP_NOP 0
+#define OP_READV 1
+#define OP_WRITEV 2
+#define OP_POLL_ADD 6
+#define OP_TIMEOUT 11
+#define OP_READ 22
+#define OP_WRITE 23
+
+#define SQE_IO_LINK (1 << 2)
+#define ENTER_GETEVENTS 1
+#define REGISTER_EVENTFD 4
+
+#define OFF_SQ_RING 0ULL
+#define OFF_CQ_RING 0x8000000ULL
+#define OFF_SQES 0x10000000ULL
+
+struct uring_sqe {
+  uint8_t opcode;
+  uint8_t flags;
+  uint16_t ioprio;
+  int32_t fd;
+  uint64_t off;
+  uint64_t addr;
+  uint32_t len;
+  uint32_t op_flags;
+  uint64_t user_data;
+  uint64_t pad[3];
+};
+
+struct uring_cqe {
+  uint64_t user_data;
+  int32_t res;
+  uint32_t flags;
+};
+
+struct uring_params {
+  uint32_t sq_entries, cq_entries, flags, sq_thread_cpu, sq_thread_idle;
+  uint32_t features, wq_fd, resv[3];
+  struct {
+    uint32_t head, tail, ring_mask, ring_entries, flags, dropped, array;
+    uint32_t resv1;
+    uint64_t user_addr;
+  } sq_off;
+  struct {
+    uint32_t head, tail, ring_mask, ring_entries, overflow, cqes, flags;
+    uint32_t resv1;
+    uint64_t user_addr;
+  } cq_off;
+};
+
+struct uring {
+  int fd;
+  uint32_t *sq_tail, *sq_mask, *sq_array;
+  uint32_t *cq_head, *cq_tail, *cq_mask;
+  struct uring_sqe *sqes;
+  struct uring_cqe *cqes;
+  uint32_t queued;
+};
+
+static int uring_init(struct uring *ring, unsigned entries) {
+  struct uring_params params;
+  memset(&params, 0, sizeof(params));
+  ring->fd = syscall(SYS_uring_setup, entries, &params);
+  if (ring->fd < 0) {
+    return -1;
+  }
+  size_t sq_size = params.sq_off.array + params.sq_entries * 4;
+  size_t cq_size =
+      params.cq_off.cqes + params.cq_entries * sizeof(struct uring_cqe);
+  char *sq = mmap(NULL, sq_size, PROT_READ | PROT_WRITE, MAP_SHARED, ring->fd,
+                  OFF_SQ_RING);
+  char *cq = mmap(NULL, cq_size, PROT_READ | PROT_WRITE, MAP_SHARED, ring->fd,
+                  OFF_CQ_RING);
+  ring->sqes =
+      mmap(NULL, params.sq_entries * sizeof(struct uring_sqe),
+           PROT_READ | PROT_WRITE, MAP_SHARED, ring->fd, OFF_SQES);
+  if (sq == MAP_FAILED || cq == MAP_FAILED || ring->sqes == MAP_FAILED) {
+    return -1;
+  }
+  ring->sq_tail = (uint32_t *)(sq + params.sq_off.tail);
+  ring->sq_mask = (uint32_t *)(sq + params.sq_off.ring_mask);
+  ring->sq_array = (uint32_t *)(sq + params.sq_off.array);
+  ring->cq_head = (uint32_t *)(cq + params.cq_off.head);
+  ring->cq_tail = (uint32_t *)(cq + params.cq_off.tail);
+  ring->cq_mask = (uint32_t *)(cq + params.cq_off.ring_mask);
+  ring->cqes = (struct uring_cqe *)(cq + params.cq_off.cqes);
+  ring->queued = 0;
+  return 0;
+}
+
+// Queue a request, returning its entry to be filled in.
+static struct uring_sqe *uring_queue(struct uring *ring, uint8_t opcode,
+                                     int fd, uint64_t user_data) {
+  uint32_t tail = *ring->sq_tail + ring->queued++;
+  uint32_t index = tail & *ring->sq_mask;
+  struct uring_sqe *sqe = &ring->sqes[index];
+  memset(sqe, 0, sizeof(*sqe));
+  sqe->opcode = opcode;
+  sqe->fd = fd;
+  sqe->user_data = user_data;
+  ring->sq_array[index] = index;
+  return sqe;
+}
+
+// Submit the queued requests and wait for `wait` completions.
+static int uring_submit(struct uring *ring, unsigned wait) {
+  __atomic_store_n(ring->sq_tail, *ring->sq_tail + ring->queued,
+                   __ATOMIC_RELEASE);
+  unsigned count = ring->queued;
+  ring->queued = 0;
+  return syscall(SYS_uring_enter, ring->fd, count, wait, ENTER_GETEVENTS,
+                 NULL, 0);
+}
+
+// Pop a completion, returning 0 if there is none.
+static int uring_pop(struct uring *ring, struct uring_cqe *cqe) {
+  uint32_t head = *ring->cq_head;
+  if (head == __atomic_load_n(ring->cq_tail, __ATOMIC_ACQUIRE)) {
+    return 0;
+  }
+  *cqe = ring->cqes[head & *ring->cq_mask];
+  __atomic_store_n(ring->cq_head, head + 1, __ATOMIC_RELEASE);
+  return 1;
+}
+
+void test_uring_rw() {
+  struct uring ring;
+  int fds[2];
+  pipe(fds);
+  if (uring_init(&ring, 8) < 0) {
+    perror("test_uring_rw");
+    return;
+  }
+  struct uring_cqe cqe;
+  uring_queue(&ring, OP_NOP, -1, 1);
+  if (uring_submit(&ring, 1) == 1 && uring_pop(&ring, &cqe) &&
+      cqe.user_data == 1 && cqe.res == 0) {
+    puts("test_uring_rw ok1");
+  }
+
+  struct uring_sqe *sqe = uring_queue(&ring, OP_WRITE, fds[1], 2);
+  sqe->addr = (uintptr_t) "uring";
+  sqe->len = 5;
+  if (uring_submit(&ring, 1) == 1 && uring_pop(&ring, &cqe) &&
+      cqe.user_data == 2 && cqe.res == 5) {
+    puts("test_uring_rw ok2");
+  }
+
+  char a[2], b[3];
+  struct iovec iov[2] = {{a, 2}, {b, 3}};
+  sqe = uring_queue(&ring, OP_READV, fds[0], 3);
+  sqe->addr = (uintptr_t)iov;
+  sqe->len = 2;
+  if (uring_submit(&ring, 1) == 1 && uring_pop(&ring, &cqe) && cqe.res == 5 &&
+      memcmp(a, "ur", 2) == 0 && memcmp(b, "ing", 3) == 0) {
+    puts("test_uring_rw ok3");
+  }
+
+  // A read from an empty pipe completes once a writer comes along.
+  char buf[4];
+  sqe = uring_queue(&ring, OP_READ, fds[0], 4);
+  sqe->addr = (uintptr_t)buf;
+  sqe->len = sizeof(buf);
+  uring_submit(&ring, 0);
+  usleep(50000);
+  if (!uring_pop(&ring, &cqe) && write(fds[1], "late", 4) == 4 &&
+      syscall(SYS_uring_enter, ring.fd, 0, 1, ENTER_GETEVENTS, NULL, 0) == 0 &&
+      uring_pop(&ring, &cqe) && cqe.user_data == 4 && cqe.res == 4) {
+    puts("test_uring_rw ok4");
+  }
+  close(ring.fd);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_uring_link() {
+  struct uring ring;
+  if (uring_init(&ring, 8) < 0) {
+    perror("test_uring_link");
+    return;
+  }
+  // Requests linked after a failed one are canceled.
+  char buf[4];
+  struct uring_sqe *sqe = uring_queue(&ring, OP_READ, -1, 1);
+  sqe->addr = (uintptr_t)buf;
+  sqe->len = sizeof(buf);
+  sqe->flags = SQE_IO_LINK;
+  uring_queue(&ring, OP_NOP, -1, 2);
+  struct uring_cqe first, second;
+  if (uring_submit(&ring, 2) == 2 && uring_pop(&ring, &first) &&
+      uring_pop(&ring, &second) && first.res == -EBADF &&
+      second.res == -ECANCELED) {
+    puts("test_uring_link ok1");
+  }
+
+  struct {
+    int64_t tv_sec;
+    long long tv_nsec;
+  } ts = {0, 20000000};
+  sqe = uring_queue(&ring, OP_TIMEOUT, -1, 3);
+  sqe->addr = (uintptr_t)&ts;
+  sqe->len = 1;
+  if (uring_submit(&ring, 1) == 1 && uring_pop(&ring, &first) &&
+      first.res == -ETIME) {
+    puts("test_uring_link ok2");
+  }
+  close(ring.fd);
+}
+
+void test_uring_eventfd() {
+  struct uring ring;
+  int efd = eventfd(0, EFD_NONBLOCK);
+  int fds[2];
+  pipe(fds);
+  if (uring_init(&ring, 8) < 0 ||
+      syscall(SYS_uring_register, ring.fd, REGISTER_EVENTFD, &efd, 1) < 0) {
+    perror("test_uring_eventfd");
+    return;
+  }
+  struct uring_sqe *sqe = uring_queue(&ring, OP_POLL_ADD, fds[0], 1);
+  sqe->op_flags = POLLIN;
+  uring_submit(&ring, 0);
+  write(fds[1], "p", 1);
+  // The registered eventfd is signaled for the completion.
+  struct pollfd pfd = {efd, POLLIN, 0};
+  uint64_t count;
+  struct uring_cqe cqe;
+  if (poll(&pfd, 1, 1000) == 1 && read(efd, &count, sizeof(count)) == 8 &&
+      count >= 1 && uring_pop(&ring, &cqe) && (cqe.res & POLLIN)) {
+    puts("test_uring_eventfd ok");
+  }
+  close(ring.fd);
+  close(efd);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_uring_rw();
+  test_uring_link();
+  test_uring_eventfd();
+  return 0;
+}
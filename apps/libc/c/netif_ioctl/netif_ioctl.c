@@ -0,0 +1,65 @@
+#include <arpa/inet.h>
+#include <net/if.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static in_addr_t ifreq_addr(const struct ifreq *req) {
+  return ((const struct sockaddr_in *)&req->ifr_addr)->sin_addr.s_addr;
+}
+
+void test_ifconf() {
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  struct ifreq reqs[8];
+  struct ifconf conf;
+  conf.ifc_len = sizeof(reqs);
+  conf.ifc_req = reqs;
+  if (ioctl(fd, SIOCGIFCONF, &conf) < 0) {
+    perror("test_ifconf");
+    return;
+  }
+  int count = conf.ifc_len / sizeof(struct ifreq);
+  for (int i = 0; i < count; i++) {
+    if (strcmp(reqs[i].ifr_name, "lo") == 0 &&
+        ifreq_addr(&reqs[i]) == htonl(INADDR_LOOPBACK)) {
+      puts("test_ifconf ok");
+    }
+  }
+  close(fd);
+}
+
+void test_ifreq_loopback() {
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  struct ifreq req;
+  memset(&req, 0, sizeof(req));
+  strcpy(req.ifr_name, "lo");
+  if (ioctl(fd, SIOCGIFINDEX, &req) == 0 && req.ifr_ifindex > 0) {
+    puts("test_ifreq_loopback ok1");
+  }
+  if (ioctl(fd, SIOCGIFFLAGS, &req) == 0 && (req.ifr_flags & IFF_UP) &&
+      (req.ifr_flags & IFF_LOOPBACK)) {
+    puts("test_ifreq_loopback ok2");
+  }
+  if (ioctl(fd, SIOCGIFNETMASK, &req) == 0 &&
+      ifreq_addr(&req) == htonl(0xff000000)) {
+    puts("test_ifreq_loopback ok3");
+  }
+  if (ioctl(fd, SIOCGIFMTU, &req) == 0 && req.ifr_mtu > 0) {
+    puts("test_ifreq_loopback ok4");
+  }
+  strcpy(req.ifr_name, "nonexistent");
+  if (ioctl(fd, SIOCGIFINDEX, &req) < 0) {
+    puts("test_ifreq_loopback ok5");
+  }
+  close(fd);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_ifconf();
+  test_ifreq_loopback();
+  return 0;
+}
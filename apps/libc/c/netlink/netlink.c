@@ -0,0 +1,113 @@
+#include <arpa/inet.h>
+#include <linux/netlink.h>
+#include <linux/rtnetlink.h>
+#include <net/if.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static char buf[16384];
+
+// Send a dump request of `type` with the `payload` family message.
+static int request_dump(int fd, int type, const void *payload, int len) {
+  struct {
+    struct nlmsghdr hdr;
+    char payload[64];
+  } req;
+  memset(&req, 0, sizeof(req));
+  req.hdr.nlmsg_len = NLMSG_LENGTH(len);
+  req.hdr.nlmsg_type = type;
+  req.hdr.nlmsg_flags = NLM_F_REQUEST | NLM_F_DUMP;
+  req.hdr.nlmsg_seq = 1;
+  memcpy(req.payload, payload, len);
+  return send(fd, &req, req.hdr.nlmsg_len, 0);
+}
+
+// Receive the messages of a dump, calling `found` on each of them until
+// `NLMSG_DONE`, and return whether `found` matched any.
+static int read_dump(int fd, int (*found)(struct nlmsghdr *)) {
+  int matched = 0;
+  for (;;) {
+    int len = recv(fd, buf, sizeof(buf), 0);
+    if (len <= 0) {
+      return 0;
+    }
+    for (struct nlmsghdr *hdr = (struct nlmsghdr *)buf; NLMSG_OK(hdr, len);
+         hdr = NLMSG_NEXT(hdr, len)) {
+      if (hdr->nlmsg_type == NLMSG_DONE) {
+        return matched;
+      }
+      if (hdr->nlmsg_type == NLMSG_ERROR) {
+        return 0;
+      }
+      matched |= found(hdr);
+    }
+  }
+}
+
+static int is_loopback_link(struct nlmsghdr *hdr) {
+  struct ifinfomsg *info = NLMSG_DATA(hdr);
+  int len = IFLA_PAYLOAD(hdr);
+  if (!(info->ifi_flags & IFF_LOOPBACK)) {
+    return 0;
+  }
+  for (struct rtattr *attr = IFLA_RTA(info); RTA_OK(attr, len);
+       attr = RTA_NEXT(attr, len)) {
+    if (attr->rta_type == IFLA_IFNAME && strcmp(RTA_DATA(attr), "lo") == 0) {
+      return 1;
+    }
+  }
+  return 0;
+}
+
+static int is_loopback_addr(struct nlmsghdr *hdr) {
+  struct ifaddrmsg *info = NLMSG_DATA(hdr);
+  int len = IFA_PAYLOAD(hdr);
+  if (info->ifa_family != AF_INET || info->ifa_prefixlen != 8) {
+    return 0;
+  }
+  for (struct rtattr *attr = IFA_RTA(info); RTA_OK(attr, len);
+       attr = RTA_NEXT(attr, len)) {
+    if (attr->rta_type == IFA_ADDRESS &&
+        *(in_addr_t *)RTA_DATA(attr) == htonl(INADDR_LOOPBACK)) {
+      return 1;
+    }
+  }
+  return 0;
+}
+
+void test_netlink_link() {
+  int fd = socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE);
+  struct ifinfomsg info = {0};
+  info.ifi_family = AF_UNSPEC;
+  if (fd < 0 || request_dump(fd, RTM_GETLINK, &info, sizeof(info)) < 0) {
+    perror("test_netlink_link");
+    return;
+  }
+  if (read_dump(fd, is_loopback_link)) {
+    puts("test_netlink_link ok");
+  }
+  close(fd);
+}
+
+void test_netlink_addr() {
+  int fd = socket(AF_NETLINK, SOCK_RAW, NETLINK_ROUTE);
+  struct ifaddrmsg info = {0};
+  info.ifa_family = AF_INET;
+  if (fd < 0 || request_dump(fd, RTM_GETADDR, &info, sizeof(info)) < 0) {
+    perror("test_netlink_addr");
+    return;
+  }
+  if (read_dump(fd, is_loopback_addr)) {
+    puts("test_netlink_addr ok");
+  }
+  close(fd);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_netlink_link();
+  test_netlink_addr();
+  return 0;
+}
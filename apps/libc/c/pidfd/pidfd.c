@@ -0,0 +1,94 @@
+#define _GNU_SOURCE
+#include <poll.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#ifndef SYS_pidfd_send_signal
+#define SYS_pidfd_send_signal 424
+#endif
+#ifndef SYS_pidfd_open
+#define SYS_pidfd_open 434
+#endif
+#ifndef SYS_pidfd_getfd
+#define SYS_pidfd_getfd 438
+#endif
+#ifndef CLONE_PIDFD
+#define CLONE_PIDFD 0x1000
+#endif
+#ifndef P_PIDFD
+#define P_PIDFD 3
+#endif
+
+static int pidfd_open(int pid) { return syscall(SYS_pidfd_open, pid, 0); }
+
+void test_pidfd_exit() {
+  int pid = fork();
+  if (pid == 0) {
+    usleep(100000);
+    _exit(7);
+  }
+  int pidfd = pidfd_open(pid);
+  struct pollfd pfd = {pidfd, POLLIN, 0};
+  if (pidfd >= 0 && poll(&pfd, 1, 0) == 0) {
+    puts("test_pidfd_exit ok1");
+  }
+  // The pidfd becomes readable once the process exits.
+  if (poll(&pfd, 1, 2000) == 1 && (pfd.revents & POLLIN)) {
+    puts("test_pidfd_exit ok2");
+  }
+  siginfo_t info = {0};
+  if (waitid(P_PIDFD, pidfd, &info, WEXITED) == 0 && info.si_pid == pid &&
+      info.si_code == CLD_EXITED && info.si_status == 7) {
+    puts("test_pidfd_exit ok3");
+  }
+  close(pidfd);
+}
+
+void test_pidfd_signal() {
+  int pidfd = -1;
+  // `CLONE_PIDFD` stores a pidfd of the child in the parent.
+  int pid = syscall(SYS_clone, CLONE_PIDFD | SIGCHLD, 0, &pidfd, 0, 0);
+  if (pid == 0) {
+    for (;;) {
+      sleep(1);
+    }
+  }
+  if (pid > 0 && pidfd >= 0) {
+    puts("test_pidfd_signal ok1");
+  }
+  siginfo_t info = {0};
+  if (syscall(SYS_pidfd_send_signal, pidfd, SIGKILL, NULL, 0) == 0 &&
+      waitid(P_PIDFD, pidfd, &info, WEXITED) == 0 &&
+      info.si_code == CLD_KILLED && info.si_status == SIGKILL) {
+    puts("test_pidfd_signal ok2");
+  }
+  close(pidfd);
+}
+
+void test_pidfd_getfd() {
+  int fds[2];
+  pipe(fds);
+  int pidfd = pidfd_open(getpid());
+  int copy = syscall(SYS_pidfd_getfd, pidfd, fds[1], 0);
+  char c;
+  if (copy >= 0 && copy != fds[1] && write(copy, "g", 1) == 1 &&
+      read(fds[0], &c, 1) == 1 && c == 'g') {
+    puts("test_pidfd_getfd ok");
+  }
+  close(copy);
+  close(pidfd);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_pidfd_exit();
+  test_pidfd_signal();
+  test_pidfd_getfd();
+  return 0;
+}
@@ -0,0 +1,98 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <limits.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <unistd.h>
+
+static char buf[65536];
+
+void test_pipe_capacity() {
+  int fds[2];
+  pipe2(fds, O_NONBLOCK);
+  if (fcntl(fds[1], F_GETPIPE_SZ) == 65536) {
+    puts("test_pipe_capacity ok1");
+  }
+  // The default buffer takes exactly 64 KiB.
+  int total = 0, len;
+  while ((len = write(fds[1], buf, 4096)) > 0) {
+    total += len;
+  }
+  if (total == 65536 && errno == EAGAIN) {
+    puts("test_pipe_capacity ok2");
+  }
+  int available = 0;
+  if (ioctl(fds[0], FIONREAD, &available) == 0 && available == 65536) {
+    puts("test_pipe_capacity ok3");
+  }
+  // The buffer cannot shrink below the data it holds.
+  if (fcntl(fds[1], F_SETPIPE_SZ, 4096) < 0 && errno == EBUSY) {
+    puts("test_pipe_capacity ok4");
+  }
+  read(fds[0], buf, sizeof(buf));
+  if (fcntl(fds[1], F_SETPIPE_SZ, 5000) == 8192 &&
+      fcntl(fds[0], F_GETPIPE_SZ) == 8192) {
+    puts("test_pipe_capacity ok5");
+  }
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_pipe_atomic() {
+  int fds[2];
+  pipe2(fds, O_NONBLOCK);
+  fcntl(fds[1], F_SETPIPE_SZ, 4096);
+  write(fds[1], buf, 4096 - 100);
+  // Writes of at most `PIPE_BUF` bytes are never split.
+  if (write(fds[1], buf, PIPE_BUF) < 0 && errno == EAGAIN) {
+    puts("test_pipe_atomic ok1");
+  }
+  read(fds[0], buf, sizeof(buf));
+  if (write(fds[1], buf, PIPE_BUF) == PIPE_BUF) {
+    puts("test_pipe_atomic ok2");
+  }
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_pipe_packet() {
+  int fds[2];
+  if (pipe2(fds, O_DIRECT) < 0) {
+    perror("test_pipe_packet");
+    return;
+  }
+  write(fds[1], "one", 3);
+  write(fds[1], "three", 5);
+  // Each read takes a single packet.
+  char data[16];
+  if (read(fds[0], data, sizeof(data)) == 3 &&
+      read(fds[0], data, sizeof(data)) == 5 && memcmp(data, "three", 5) == 0) {
+    puts("test_pipe_packet ok");
+  }
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_pipe_empty_write() {
+  signal(SIGPIPE, SIG_DFL);
+  int fds[2];
+  pipe(fds);
+  close(fds[0]);
+  // Empty writes succeed even without readers.
+  if (write(fds[1], buf, 0) == 0) {
+    puts("test_pipe_empty_write ok");
+  }
+  close(fds[1]);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_pipe_capacity();
+  test_pipe_atomic();
+  test_pipe_packet();
+  test_pipe_empty_write();
+  return 0;
+}
@@ -0,0 +1,117 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static void empty_handler(int signum) {}
+
+// Fork a child sending `signum` to the caller after 100 ms.
+static int signal_later(int signum) {
+  int parent = getpid();
+  int pid = fork();
+  if (pid == 0) {
+    usleep(100000);
+    kill(parent, signum);
+    _exit(0);
+  }
+  return pid;
+}
+
+void test_poll_pipe() {
+  int fds[2];
+  pipe(fds);
+  struct pollfd pfds[2] = {{fds[0], POLLIN, 0}, {fds[1], POLLOUT, 0}};
+  if (poll(pfds, 2, 0) == 1 && pfds[0].revents == 0 &&
+      pfds[1].revents == POLLOUT) {
+    puts("test_poll_pipe ok1");
+  }
+  write(fds[1], "x", 1);
+  if (poll(pfds, 1, 0) == 1 && pfds[0].revents == POLLIN) {
+    puts("test_poll_pipe ok2");
+  }
+  close(fds[1]);
+  if (poll(pfds, 1, 0) == 1 && (pfds[0].revents & POLLHUP)) {
+    puts("test_poll_pipe ok3");
+  }
+  close(fds[0]);
+}
+
+void test_poll_nval() {
+  // Negative fds are skipped, closed ones are reported invalid.
+  struct pollfd pfds[2] = {{-1, POLLIN, 0}, {1000, POLLIN, 0}};
+  if (poll(pfds, 2, 0) == 1 && pfds[0].revents == 0 &&
+      pfds[1].revents == POLLNVAL) {
+    puts("test_poll_nval ok");
+  }
+}
+
+void test_poll_timeout() {
+  int fds[2];
+  pipe(fds);
+  struct pollfd pfd = {fds[0], POLLIN, 0};
+  struct timespec start, end;
+  clock_gettime(CLOCK_MONOTONIC, &start);
+  int ret = poll(&pfd, 1, 100);
+  clock_gettime(CLOCK_MONOTONIC, &end);
+  long elapsed = (end.tv_sec - start.tv_sec) * 1000 +
+                 (end.tv_nsec - start.tv_nsec) / 1000000;
+  if (ret == 0 && elapsed >= 90) {
+    puts("test_poll_timeout ok");
+  }
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_poll_eintr() {
+  struct sigaction sa = {0};
+  sa.sa_handler = empty_handler;
+  sigaction(SIGUSR1, &sa, NULL);
+  int fds[2];
+  pipe(fds);
+  struct pollfd pfd = {fds[0], POLLIN, 0};
+
+  int pid = signal_later(SIGUSR1);
+  if (poll(&pfd, 1, 5000) < 0 && errno == EINTR) {
+    puts("test_poll_eintr ok1");
+  }
+  waitpid(pid, NULL, 0);
+
+  // The signal stays pending while `ppoll` blocks it.
+  sigset_t mask, pending;
+  sigemptyset(&mask);
+  sigaddset(&mask, SIGUSR1);
+  sigprocmask(SIG_BLOCK, &mask, NULL);
+  pid = signal_later(SIGUSR1);
+  waitpid(pid, NULL, 0);
+  struct timespec timeout = {0, 100000000};
+  if (ppoll(&pfd, 1, &timeout, &mask) == 0) {
+    puts("test_poll_eintr ok2");
+  }
+  // Unblocking it during `ppoll` interrupts the wait.
+  sigemptyset(&mask);
+  if (ppoll(&pfd, 1, &timeout, &mask) < 0 && errno == EINTR &&
+      (sigpending(&pending), !sigismember(&pending, SIGUSR1))) {
+    puts("test_poll_eintr ok3");
+  }
+  sigemptyset(&mask);
+  sigaddset(&mask, SIGUSR1);
+  sigprocmask(SIG_UNBLOCK, &mask, NULL);
+  sa.sa_handler = SIG_DFL;
+  sigaction(SIGUSR1, &sa, NULL);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_poll_pipe();
+  test_poll_nval();
+  test_poll_timeout();
+  test_poll_eintr();
+  return 0;
+}
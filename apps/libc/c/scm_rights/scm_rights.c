@@ -0,0 +1,142 @@
+#define _GNU_SOURCE
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static int send_fds(int sock, const int *fds, int count) {
+  char control[CMSG_SPACE(2 * sizeof(int))] = {0};
+  struct iovec iov = {"f", 1};
+  struct msghdr msg = {0};
+  msg.msg_iov = &iov;
+  msg.msg_iovlen = 1;
+  msg.msg_control = control;
+  msg.msg_controllen = CMSG_SPACE(count * sizeof(int));
+  struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+  cmsg->cmsg_level = SOL_SOCKET;
+  cmsg->cmsg_type = SCM_RIGHTS;
+  cmsg->cmsg_len = CMSG_LEN(count * sizeof(int));
+  memcpy(CMSG_DATA(cmsg), fds, count * sizeof(int));
+  return sendmsg(sock, &msg, 0);
+}
+
+void test_scm_rights() {
+  int sv[2], pipefd[2];
+  socketpair(AF_UNIX, SOCK_STREAM, 0, sv);
+  pipe(pipefd);
+  if (send_fds(sv[0], &pipefd[0], 1) != 1) {
+    perror("test_scm_rights");
+    return;
+  }
+  close(pipefd[0]);
+
+  char data, control[CMSG_SPACE(sizeof(int))];
+  struct iovec iov = {&data, 1};
+  struct msghdr msg = {0};
+  msg.msg_iov = &iov;
+  msg.msg_iovlen = 1;
+  msg.msg_control = control;
+  msg.msg_controllen = sizeof(control);
+  if (recvmsg(sv[1], &msg, 0) != 1) {
+    perror("test_scm_rights");
+    return;
+  }
+  struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+  if (cmsg && cmsg->cmsg_level == SOL_SOCKET && cmsg->cmsg_type == SCM_RIGHTS) {
+    int fd;
+    memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+    char buf[4];
+    write(pipefd[1], "fd!", 3);
+    if (read(fd, buf, sizeof(buf)) == 3 && memcmp(buf, "fd!", 3) == 0) {
+      puts("test_scm_rights ok");
+    }
+    close(fd);
+  }
+  close(pipefd[1]);
+  close(sv[0]);
+  close(sv[1]);
+}
+
+void test_scm_ctrunc() {
+  int sv[2], pipefd[2];
+  socketpair(AF_UNIX, SOCK_DGRAM, 0, sv);
+  pipe(pipefd);
+  send_fds(sv[0], pipefd, 2);
+
+  char data, control[CMSG_SPACE(sizeof(int))];
+  struct iovec iov = {&data, 1};
+  struct msghdr msg = {0};
+  msg.msg_iov = &iov;
+  msg.msg_iovlen = 1;
+  msg.msg_control = control;
+  // Room for only one of the two files.
+  msg.msg_controllen = CMSG_LEN(sizeof(int));
+  if (recvmsg(sv[1], &msg, 0) == 1 && (msg.msg_flags & MSG_CTRUNC)) {
+    puts("test_scm_ctrunc ok");
+  }
+  struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+  if (cmsg && cmsg->cmsg_type == SCM_RIGHTS) {
+    int fd;
+    memcpy(&fd, CMSG_DATA(cmsg), sizeof(int));
+    close(fd);
+  }
+  close(pipefd[0]);
+  close(pipefd[1]);
+  close(sv[0]);
+  close(sv[1]);
+}
+
+void test_scm_credentials() {
+  int sv[2];
+  socketpair(AF_UNIX, SOCK_STREAM, 0, sv);
+  struct ucred cred;
+  socklen_t len = sizeof(cred);
+  if (getsockopt(sv[0], SOL_SOCKET, SO_PEERCRED, &cred, &len) == 0 &&
+      cred.pid == getpid() && cred.uid == getuid()) {
+    puts("test_scm_credentials ok1");
+  }
+
+  int one = 1;
+  setsockopt(sv[1], SOL_SOCKET, SO_PASSCRED, &one, sizeof(one));
+  char control[CMSG_SPACE(sizeof(struct ucred))] = {0};
+  struct iovec iov = {"c", 1};
+  struct msghdr msg = {0};
+  msg.msg_iov = &iov;
+  msg.msg_iovlen = 1;
+  msg.msg_control = control;
+  msg.msg_controllen = sizeof(control);
+  struct cmsghdr *cmsg = CMSG_FIRSTHDR(&msg);
+  cmsg->cmsg_level = SOL_SOCKET;
+  cmsg->cmsg_type = SCM_CREDENTIALS;
+  cmsg->cmsg_len = CMSG_LEN(sizeof(struct ucred));
+  cred.pid = getpid();
+  cred.uid = getuid();
+  cred.gid = getgid();
+  memcpy(CMSG_DATA(cmsg), &cred, sizeof(cred));
+  sendmsg(sv[0], &msg, 0);
+
+  char data;
+  memset(control, 0, sizeof(control));
+  iov.iov_base = &data;
+  msg.msg_controllen = sizeof(control);
+  if (recvmsg(sv[1], &msg, 0) == 1) {
+    cmsg = CMSG_FIRSTHDR(&msg);
+    struct ucred got;
+    if (cmsg && cmsg->cmsg_type == SCM_CREDENTIALS) {
+      memcpy(&got, CMSG_DATA(cmsg), sizeof(got));
+      if (got.pid == getpid() && got.gid == getgid()) {
+        puts("test_scm_credentials ok2");
+      }
+    }
+  }
+  close(sv[0]);
+  close(sv[1]);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_scm_rights();
+  test_scm_ctrunc();
+  test_scm_credentials();
+  return 0;
+}
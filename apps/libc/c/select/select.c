@@ -0,0 +1,104 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/select.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static void empty_handler(int signum) {}
+
+void test_select_pipe() {
+  int fds[2];
+  pipe(fds);
+  fd_set rfds, wfds;
+  FD_ZERO(&rfds);
+  FD_ZERO(&wfds);
+  FD_SET(fds[0], &rfds);
+  FD_SET(fds[1], &wfds);
+  struct timeval tv = {0, 0};
+  if (select(fds[1] + 1, &rfds, &wfds, NULL, &tv) == 1 &&
+      !FD_ISSET(fds[0], &rfds) && FD_ISSET(fds[1], &wfds)) {
+    puts("test_select_pipe ok1");
+  }
+
+  // A high descriptor number is watched as well.
+  int high = dup2(fds[0], 200);
+  write(fds[1], "x", 1);
+  FD_ZERO(&rfds);
+  FD_SET(high, &rfds);
+  if (high == 200 && select(high + 1, &rfds, NULL, NULL, &tv) == 1 &&
+      FD_ISSET(high, &rfds)) {
+    puts("test_select_pipe ok2");
+  }
+
+  int closed = dup(fds[0]);
+  close(closed);
+  FD_ZERO(&rfds);
+  FD_SET(closed, &rfds);
+  if (select(closed + 1, &rfds, NULL, NULL, &tv) < 0 && errno == EBADF) {
+    puts("test_select_pipe ok3");
+  }
+  close(high);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_select_timeout() {
+  int fds[2];
+  pipe(fds);
+  fd_set rfds;
+  FD_ZERO(&rfds);
+  FD_SET(fds[0], &rfds);
+  struct timeval tv = {0, 100000};
+  // Sets are cleared when nothing becomes ready.
+  if (select(fds[0] + 1, &rfds, NULL, NULL, &tv) == 0 &&
+      !FD_ISSET(fds[0], &rfds)) {
+    puts("test_select_timeout ok");
+  }
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_pselect_eintr() {
+  struct sigaction sa = {0};
+  sa.sa_handler = empty_handler;
+  sigaction(SIGUSR2, &sa, NULL);
+  sigset_t mask, empty;
+  sigemptyset(&mask);
+  sigaddset(&mask, SIGUSR2);
+  sigemptyset(&empty);
+  sigprocmask(SIG_BLOCK, &mask, NULL);
+
+  int fds[2];
+  pipe(fds);
+  fd_set rfds;
+  FD_ZERO(&rfds);
+  FD_SET(fds[0], &rfds);
+  int parent = getpid();
+  int pid = fork();
+  if (pid == 0) {
+    usleep(100000);
+    kill(parent, SIGUSR2);
+    _exit(0);
+  }
+  // The signal is only delivered while `pselect` unblocks it.
+  struct timespec ts = {5, 0};
+  if (pselect(fds[0] + 1, &rfds, NULL, NULL, &ts, &empty) < 0 &&
+      errno == EINTR) {
+    puts("test_pselect_eintr ok");
+  }
+  waitpid(pid, NULL, 0);
+  sigprocmask(SIG_UNBLOCK, &mask, NULL);
+  sa.sa_handler = SIG_DFL;
+  sigaction(SIGUSR2, &sa, NULL);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_select_pipe();
+  test_select_timeout();
+  test_pselect_eintr();
+  return 0;
+}
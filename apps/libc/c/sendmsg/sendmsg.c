@@ -0,0 +1,132 @@
+#define _GNU_SOURCE
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+void test_msg_iovec() {
+  int sv[2];
+  socketpair(AF_UNIX, SOCK_STREAM, 0, sv);
+  struct iovec out[2] = {{"hel", 3}, {"lo", 2}};
+  struct msghdr msg = {0};
+  msg.msg_iov = out;
+  msg.msg_iovlen = 2;
+  if (sendmsg(sv[0], &msg, 0) != 5) {
+    perror("test_msg_iovec");
+    return;
+  }
+
+  char a[2], b[3];
+  struct iovec in[2] = {{a, 2}, {b, 3}};
+  memset(&msg, 0, sizeof(msg));
+  msg.msg_iov = in;
+  msg.msg_iovlen = 2;
+  if (recvmsg(sv[1], &msg, MSG_WAITALL) == 5 && memcmp(a, "he", 2) == 0 &&
+      memcmp(b, "llo", 3) == 0) {
+    puts("test_msg_iovec ok");
+  }
+  close(sv[0]);
+  close(sv[1]);
+}
+
+void test_msg_flags() {
+  int sv[2];
+  socketpair(AF_UNIX, SOCK_DGRAM, 0, sv);
+  char buf[16];
+  if (recv(sv[1], buf, sizeof(buf), MSG_DONTWAIT) < 0 && errno == EAGAIN) {
+    puts("test_msg_flags ok1");
+  }
+
+  send(sv[0], "peekaboo", 8, 0);
+  if (recv(sv[1], buf, sizeof(buf), MSG_PEEK) == 8 &&
+      recv(sv[1], buf, sizeof(buf), 0) == 8) {
+    puts("test_msg_flags ok2");
+  }
+
+  send(sv[0], "0123456789", 10, 0);
+  struct iovec iov = {buf, 4};
+  struct msghdr msg = {0};
+  msg.msg_iov = &iov;
+  msg.msg_iovlen = 1;
+  if (recvmsg(sv[1], &msg, MSG_TRUNC) == 10 && (msg.msg_flags & MSG_TRUNC)) {
+    puts("test_msg_flags ok3");
+  }
+  close(sv[0]);
+  close(sv[1]);
+}
+
+void test_mmsg() {
+  int sv[2];
+  socketpair(AF_UNIX, SOCK_DGRAM, 0, sv);
+  struct iovec out[2] = {{"one", 3}, {"three", 5}};
+  struct mmsghdr msgs[2] = {0};
+  msgs[0].msg_hdr.msg_iov = &out[0];
+  msgs[0].msg_hdr.msg_iovlen = 1;
+  msgs[1].msg_hdr.msg_iov = &out[1];
+  msgs[1].msg_hdr.msg_iovlen = 1;
+  if (sendmmsg(sv[0], msgs, 2, 0) != 2) {
+    perror("test_mmsg");
+    return;
+  }
+
+  char a[8], b[8];
+  struct iovec in[2] = {{a, sizeof(a)}, {b, sizeof(b)}};
+  memset(msgs, 0, sizeof(msgs));
+  msgs[0].msg_hdr.msg_iov = &in[0];
+  msgs[0].msg_hdr.msg_iovlen = 1;
+  msgs[1].msg_hdr.msg_iov = &in[1];
+  msgs[1].msg_hdr.msg_iovlen = 1;
+  if (recvmmsg(sv[1], msgs, 2, MSG_DONTWAIT, NULL) == 2 &&
+      msgs[0].msg_len == 3 && msgs[1].msg_len == 5) {
+    puts("test_mmsg ok");
+  }
+  close(sv[0]);
+  close(sv[1]);
+}
+
+void test_msg_name() {
+  struct sockaddr_in addr = {0};
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(5021);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  struct sockaddr_in sender_addr = addr;
+  sender_addr.sin_port = htons(5022);
+  int receiver = socket(AF_INET, SOCK_DGRAM, 0);
+  int sender = socket(AF_INET, SOCK_DGRAM, 0);
+  bind(receiver, (struct sockaddr *)&addr, sizeof(addr));
+  bind(sender, (struct sockaddr *)&sender_addr, sizeof(sender_addr));
+
+  struct iovec iov = {"dns", 3};
+  struct msghdr msg = {0};
+  msg.msg_name = &addr;
+  msg.msg_namelen = sizeof(addr);
+  msg.msg_iov = &iov;
+  msg.msg_iovlen = 1;
+  sendmsg(sender, &msg, 0);
+
+  char buf[8];
+  struct sockaddr_in from = {0};
+  iov.iov_base = buf;
+  iov.iov_len = sizeof(buf);
+  msg.msg_name = &from;
+  msg.msg_namelen = sizeof(from);
+  if (recvmsg(receiver, &msg, 0) == 3 && msg.msg_namelen == sizeof(from) &&
+      from.sin_port == htons(5022)) {
+    puts("test_msg_name ok");
+  }
+  close(sender);
+  close(receiver);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_msg_iovec();
+  test_msg_flags();
+  test_mmsg();
+  test_msg_name();
+  return 0;
+}
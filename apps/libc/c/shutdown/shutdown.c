@@ -0,0 +1,95 @@
+#define _GNU_SOURCE
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+// Connect a client to a server on `port`, returning both ends.
+static int connect_pair(int port, int *client, int *conn) {
+  struct sockaddr_in addr = {0};
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(port);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  int server = socket(AF_INET, SOCK_STREAM, 0);
+  int one = 1;
+  setsockopt(server, SOL_SOCKET, SO_REUSEADDR, &one, sizeof(one));
+  if (bind(server, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+      listen(server, 1) < 0) {
+    close(server);
+    return -1;
+  }
+  *client = socket(AF_INET, SOCK_STREAM, 0);
+  if (connect(*client, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    close(server);
+    return -1;
+  }
+  *conn = accept(server, NULL, NULL);
+  close(server);
+  return *conn < 0 ? -1 : 0;
+}
+
+void test_shutdown_write() {
+  int client, conn;
+  if (connect_pair(5041, &client, &conn) < 0) {
+    perror("test_shutdown_write");
+    return;
+  }
+  send(client, "req", 3, 0);
+  shutdown(client, SHUT_WR);
+
+  char buf[8];
+  int n = 0, len;
+  while ((len = recv(conn, buf + n, sizeof(buf) - n, 0)) > 0) {
+    n += len;
+  }
+  if (n == 3 && len == 0) {
+    puts("test_shutdown_write ok1");
+  }
+  struct pollfd pfd = {conn, POLLIN | POLLRDHUP, 0};
+  if (poll(&pfd, 1, 1000) == 1 && (pfd.revents & POLLRDHUP)) {
+    puts("test_shutdown_write ok2");
+  }
+
+  // The client can still read the reply.
+  send(conn, "reply", 5, 0);
+  if (recv(client, buf, 5, MSG_WAITALL) == 5 &&
+      memcmp(buf, "reply", 5) == 0) {
+    puts("test_shutdown_write ok3");
+  }
+  if (send(client, "x", 1, MSG_NOSIGNAL) < 0 && errno == EPIPE) {
+    puts("test_shutdown_write ok4");
+  }
+  close(conn);
+  close(client);
+}
+
+void test_shutdown_read() {
+  int client, conn;
+  if (connect_pair(5042, &client, &conn) < 0) {
+    perror("test_shutdown_read");
+    return;
+  }
+  shutdown(client, SHUT_RD);
+  char buf[4];
+  if (recv(client, buf, sizeof(buf), 0) == 0) {
+    puts("test_shutdown_read ok1");
+  }
+  shutdown(client, SHUT_WR);
+  struct pollfd pfd = {client, POLLIN, 0};
+  if (poll(&pfd, 1, 0) == 1 && (pfd.revents & POLLHUP)) {
+    puts("test_shutdown_read ok2");
+  }
+  close(conn);
+  close(client);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_shutdown_write();
+  test_shutdown_read();
+  return 0;
+}
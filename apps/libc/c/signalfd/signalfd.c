@@ -0,0 +1,69 @@
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/signalfd.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+void test_signalfd_read() {
+  sigset_t mask;
+  sigemptyset(&mask);
+  sigaddset(&mask, SIGUSR1);
+  sigprocmask(SIG_BLOCK, &mask, NULL);
+  int fd = signalfd(-1, &mask, SFD_NONBLOCK);
+
+  struct pollfd pfd = {fd, POLLIN, 0};
+  if (poll(&pfd, 1, 0) == 0) {
+    puts("test_signalfd_read ok1");
+  }
+  kill(getpid(), SIGUSR1);
+  struct signalfd_siginfo info;
+  if (poll(&pfd, 1, 0) == 1 && read(fd, &info, sizeof(info)) == sizeof(info) &&
+      info.ssi_signo == SIGUSR1) {
+    puts("test_signalfd_read ok2");
+  }
+  sigset_t pending;
+  sigpending(&pending);
+  if (!sigismember(&pending, SIGUSR1)) {
+    puts("test_signalfd_read ok3");
+  }
+
+  // The mask of an existing signalfd can be replaced.
+  sigaddset(&mask, SIGUSR2);
+  sigprocmask(SIG_BLOCK, &mask, NULL);
+  kill(getpid(), SIGUSR2);
+  if (signalfd(fd, &mask, 0) == fd &&
+      read(fd, &info, sizeof(info)) == sizeof(info) &&
+      info.ssi_signo == SIGUSR2) {
+    puts("test_signalfd_read ok4");
+  }
+  sigprocmask(SIG_UNBLOCK, &mask, NULL);
+  close(fd);
+}
+
+void test_signalfd_child() {
+  sigset_t mask;
+  sigemptyset(&mask);
+  sigaddset(&mask, SIGCHLD);
+  sigprocmask(SIG_BLOCK, &mask, NULL);
+  int fd = signalfd(-1, &mask, 0);
+  int pid = fork();
+  if (pid == 0) {
+    _exit(3);
+  }
+  struct signalfd_siginfo info;
+  if (read(fd, &info, sizeof(info)) == sizeof(info) &&
+      info.ssi_signo == SIGCHLD) {
+    puts("test_signalfd_child ok");
+  }
+  waitpid(pid, NULL, 0);
+  sigprocmask(SIG_UNBLOCK, &mask, NULL);
+  close(fd);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_signalfd_read();
+  test_signalfd_child();
+  return 0;
+}
@@ -0,0 +1,76 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile int caught;
+
+static void sigpipe_handler(int signum) { caught++; }
+
+void test_sigpipe_pipe() {
+  struct sigaction sa = {0};
+  sa.sa_handler = sigpipe_handler;
+  sigaction(SIGPIPE, &sa, NULL);
+  int fds[2];
+  pipe(fds);
+  close(fds[0]);
+  caught = 0;
+  if (write(fds[1], "x", 1) < 0 && errno == EPIPE && caught == 1) {
+    puts("test_sigpipe_pipe ok1");
+  }
+  // Ignoring the signal leaves only the error.
+  signal(SIGPIPE, SIG_IGN);
+  if (write(fds[1], "x", 1) < 0 && errno == EPIPE && caught == 1) {
+    puts("test_sigpipe_pipe ok2");
+  }
+  signal(SIGPIPE, SIG_DFL);
+  close(fds[1]);
+}
+
+void test_sigpipe_socket() {
+  struct sigaction sa = {0};
+  sa.sa_handler = sigpipe_handler;
+  sigaction(SIGPIPE, &sa, NULL);
+  int sv[2];
+  socketpair(AF_UNIX, SOCK_STREAM, 0, sv);
+  close(sv[1]);
+  caught = 0;
+  if (send(sv[0], "x", 1, 0) < 0 && errno == EPIPE && caught == 1) {
+    puts("test_sigpipe_socket ok1");
+  }
+  if (send(sv[0], "x", 1, MSG_NOSIGNAL) < 0 && errno == EPIPE &&
+      caught == 1) {
+    puts("test_sigpipe_socket ok2");
+  }
+  signal(SIGPIPE, SIG_DFL);
+  close(sv[0]);
+}
+
+void test_sigpipe_default() {
+  int fds[2];
+  pipe(fds);
+  close(fds[0]);
+  int pid = fork();
+  if (pid == 0) {
+    write(fds[1], "x", 1);
+    _exit(0);
+  }
+  close(fds[1]);
+  // By default, the signal kills the writer.
+  int status;
+  if (waitpid(pid, &status, 0) == pid && WIFSIGNALED(status) &&
+      WTERMSIG(status) == SIGPIPE) {
+    puts("test_sigpipe_default ok");
+  }
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_sigpipe_pipe();
+  test_sigpipe_socket();
+  test_sigpipe_default();
+  return 0;
+}
@@ -0,0 +1,112 @@
+#define _GNU_SOURCE
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static struct sockaddr_in loopback(int port) {
+  struct sockaddr_in addr = {0};
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(port);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  return addr;
+}
+
+static int listen_on(int port, int flags) {
+  struct sockaddr_in addr = loopback(port);
+  int fd = socket(AF_INET, SOCK_STREAM | flags, 0);
+  int one = 1;
+  setsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &one, sizeof(one));
+  if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0 ||
+      listen(fd, 4) < 0) {
+    perror("listen_on");
+    close(fd);
+    return -1;
+  }
+  return fd;
+}
+
+void test_tcp_loopback() {
+  struct sockaddr_in addr = loopback(5001);
+  int server = listen_on(5001, 0);
+  if (fork() == 0) {
+    int client = socket(AF_INET, SOCK_STREAM, 0);
+    char buf[4];
+    if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) == 0 &&
+        send(client, "ping", 4, 0) == 4 && recv(client, buf, 4, 0) == 4 &&
+        memcmp(buf, "pong", 4) == 0) {
+      puts("test_tcp_loopback ok1");
+    }
+    close(client);
+    _exit(0);
+  }
+  struct sockaddr_in peer, local;
+  socklen_t peer_len = sizeof(peer), local_len = sizeof(local);
+  int conn = accept4(server, (struct sockaddr *)&peer, &peer_len, SOCK_CLOEXEC);
+  char buf[4];
+  if (conn >= 0 && recv(conn, buf, 4, 0) == 4 && memcmp(buf, "ping", 4) == 0) {
+    send(conn, "pong", 4, 0);
+  }
+  getsockname(conn, (struct sockaddr *)&local, &local_len);
+  if (peer.sin_addr.s_addr == htonl(INADDR_LOOPBACK) &&
+      local.sin_port == htons(5001)) {
+    puts("test_tcp_loopback ok2");
+  }
+  wait(NULL);
+  close(conn);
+  close(server);
+}
+
+void test_udp_loopback() {
+  struct sockaddr_in addr = loopback(5002);
+  struct sockaddr_in sender_addr = loopback(5003);
+  int receiver = socket(AF_INET, SOCK_DGRAM, 0);
+  int sender = socket(AF_INET, SOCK_DGRAM, 0);
+  bind(receiver, (struct sockaddr *)&addr, sizeof(addr));
+  bind(sender, (struct sockaddr *)&sender_addr, sizeof(sender_addr));
+  sendto(sender, "hello", 5, 0, (struct sockaddr *)&addr, sizeof(addr));
+
+  char buf[16];
+  struct sockaddr_in from;
+  socklen_t from_len = sizeof(from);
+  ssize_t n = recvfrom(receiver, buf, sizeof(buf), 0, (struct sockaddr *)&from,
+                       &from_len);
+  if (n == 5 && memcmp(buf, "hello", 5) == 0 && from.sin_port == htons(5003)) {
+    puts("test_udp_loopback ok");
+  }
+  close(sender);
+  close(receiver);
+}
+
+void test_socket_errors() {
+  int server = listen_on(5004, SOCK_NONBLOCK);
+  if (accept(server, NULL, NULL) < 0 && errno == EAGAIN) {
+    puts("test_socket_errors ok1");
+  }
+  close(server);
+
+  struct sockaddr_in addr = loopback(5005);
+  int client = socket(AF_INET, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, sizeof(addr)) < 0 &&
+      errno == ECONNREFUSED) {
+    puts("test_socket_errors ok2");
+  }
+  close(client);
+
+  if (socket(AF_INET, SOCK_STREAM, IPPROTO_UDP) < 0 &&
+      errno == EPROTONOSUPPORT) {
+    puts("test_socket_errors ok3");
+  }
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_tcp_loopback();
+  test_udp_loopback();
+  test_socket_errors();
+  return 0;
+}
@@ -0,0 +1,58 @@
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+void test_socketpair_stream() {
+  int sv[2];
+  if (socketpair(AF_UNIX, SOCK_STREAM, 0, sv) < 0) {
+    perror("test_socketpair_stream");
+    return;
+  }
+  char buf[8];
+  if (write(sv[0], "ping", 4) == 4 && read(sv[1], buf, sizeof(buf)) == 4 &&
+      write(sv[1], "pong", 4) == 4 && read(sv[0], buf, sizeof(buf)) == 4 &&
+      memcmp(buf, "pong", 4) == 0) {
+    puts("test_socketpair_stream ok1");
+  }
+
+  struct pollfd pfd = {sv[1], POLLIN, 0};
+  if (poll(&pfd, 1, 0) == 0) {
+    write(sv[0], "x", 1);
+    if (poll(&pfd, 1, 0) == 1 && (pfd.revents & POLLIN)) {
+      puts("test_socketpair_stream ok2");
+    }
+  }
+
+  close(sv[0]);
+  if (read(sv[1], buf, sizeof(buf)) == 1 &&
+      read(sv[1], buf, sizeof(buf)) == 0) {
+    puts("test_socketpair_stream ok3");
+  }
+  close(sv[1]);
+}
+
+void test_socketpair_dgram() {
+  int sv[2];
+  if (socketpair(AF_UNIX, SOCK_DGRAM, 0, sv) < 0) {
+    perror("test_socketpair_dgram");
+    return;
+  }
+  char buf[8];
+  write(sv[0], "a", 1);
+  write(sv[0], "bc", 2);
+  if (read(sv[1], buf, sizeof(buf)) == 1 &&
+      read(sv[1], buf, sizeof(buf)) == 2) {
+    puts("test_socketpair_dgram ok");
+  }
+  close(sv[0]);
+  close(sv[1]);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_socketpair_stream();
+  test_socketpair_dgram();
+  return 0;
+}
@@ -0,0 +1,78 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <netinet/tcp.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <sys/time.h>
+#include <unistd.h>
+
+static int get_int(int fd, int level, int optname) {
+  int value = -1;
+  socklen_t len = sizeof(value);
+  if (getsockopt(fd, level, optname, &value, &len) < 0 ||
+      len != sizeof(value)) {
+    return -1;
+  }
+  return value;
+}
+
+static int set_int(int fd, int level, int optname, int value) {
+  return setsockopt(fd, level, optname, &value, sizeof(value));
+}
+
+void test_sockopt_flags() {
+  int fd = socket(AF_INET, SOCK_STREAM, 0);
+  if (get_int(fd, SOL_SOCKET, SO_TYPE) == SOCK_STREAM &&
+      get_int(fd, SOL_SOCKET, SO_ERROR) == 0) {
+    puts("test_sockopt_flags ok1");
+  }
+  if (set_int(fd, SOL_SOCKET, SO_REUSEADDR, 1) == 0 &&
+      get_int(fd, SOL_SOCKET, SO_REUSEADDR) != 0 &&
+      set_int(fd, SOL_SOCKET, SO_KEEPALIVE, 1) == 0 &&
+      get_int(fd, SOL_SOCKET, SO_KEEPALIVE) != 0) {
+    puts("test_sockopt_flags ok2");
+  }
+  if (set_int(fd, IPPROTO_TCP, TCP_NODELAY, 1) == 0 &&
+      get_int(fd, IPPROTO_TCP, TCP_NODELAY) != 0) {
+    puts("test_sockopt_flags ok3");
+  }
+  if (set_int(fd, SOL_SOCKET, SO_RCVBUF, 32768) == 0 &&
+      get_int(fd, SOL_SOCKET, SO_RCVBUF) >= 32768 &&
+      get_int(fd, SOL_SOCKET, SO_SNDBUF) > 0) {
+    puts("test_sockopt_flags ok4");
+  }
+  close(fd);
+}
+
+void test_sockopt_timeout() {
+  struct sockaddr_in addr = {0};
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(5011);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  bind(fd, (struct sockaddr *)&addr, sizeof(addr));
+
+  struct timeval tv = {0, 100000};
+  setsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &tv, sizeof(tv));
+  struct timeval got = {0};
+  socklen_t len = sizeof(got);
+  getsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &got, &len);
+  if (got.tv_sec == 0 && got.tv_usec == 100000) {
+    puts("test_sockopt_timeout ok1");
+  }
+
+  char buf[8];
+  if (recv(fd, buf, sizeof(buf), 0) < 0 &&
+      (errno == EAGAIN || errno == EWOULDBLOCK)) {
+    puts("test_sockopt_timeout ok2");
+  }
+  close(fd);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_sockopt_flags();
+  test_sockopt_timeout();
+  return 0;
+}
@@ -0,0 +1,127 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/sendfile.h>
+#include <sys/uio.h>
+#include <unistd.h>
+
+#define SRC_PATH "/starry_splice_src.txt"
+#define DST_PATH "/starry_splice_dst.txt"
+
+// Create a file at `path` holding `data`, returning a read-write fd on it.
+static int create_file(const char *path, const char *data) {
+  int fd = open(path, O_RDWR | O_CREAT | O_TRUNC, 0644);
+  if (fd >= 0) {
+    write(fd, data, strlen(data));
+    lseek(fd, 0, SEEK_SET);
+  }
+  return fd;
+}
+
+void test_sendfile() {
+  int fds[2];
+  int src = create_file(SRC_PATH, "hello world");
+  if (src < 0 || pipe(fds) < 0) {
+    perror("test_sendfile");
+    return;
+  }
+  char buf[16] = {0};
+  if (sendfile(fds[1], src, NULL, 5) == 5 && read(fds[0], buf, 5) == 5 &&
+      memcmp(buf, "hello", 5) == 0 && lseek(src, 0, SEEK_CUR) == 5) {
+    puts("test_sendfile ok1");
+  }
+  // With an offset, the file position is left alone.
+  off_t offset = 6;
+  if (sendfile(fds[1], src, &offset, 5) == 5 && offset == 11 &&
+      read(fds[0], buf, 5) == 5 && memcmp(buf, "world", 5) == 0 &&
+      lseek(src, 0, SEEK_CUR) == 5) {
+    puts("test_sendfile ok2");
+  }
+  close(fds[0]);
+  close(fds[1]);
+  close(src);
+  unlink(SRC_PATH);
+}
+
+void test_splice_tee() {
+  int a[2], b[2];
+  int dst = create_file(DST_PATH, "ab");
+  if (dst < 0 || pipe(a) < 0 || pipe(b) < 0) {
+    perror("test_splice_tee");
+    return;
+  }
+  write(a[1], "spliced", 7);
+  // `tee` duplicates the data, leaving it in the source pipe.
+  char buf[16] = {0};
+  if (tee(a[0], b[1], 7, 0) == 7 && read(b[0], buf, 7) == 7 &&
+      memcmp(buf, "spliced", 7) == 0) {
+    puts("test_splice_tee ok1");
+  }
+  off_t offset = 2;
+  memset(buf, 0, sizeof(buf));
+  if (splice(a[0], NULL, dst, &offset, 7, 0) == 7 && offset == 9 &&
+      read(dst, buf, sizeof(buf)) == 9 && memcmp(buf, "abspliced", 9) == 0) {
+    puts("test_splice_tee ok2");
+  }
+  if (splice(a[0], NULL, a[1], NULL, 1, 0) < 0) {
+    puts("test_splice_tee ok3");
+  }
+  close(a[0]);
+  close(a[1]);
+  close(b[0]);
+  close(b[1]);
+  close(dst);
+  unlink(DST_PATH);
+}
+
+void test_vmsplice() {
+  int fds[2];
+  if (pipe(fds) < 0) {
+    perror("test_vmsplice");
+    return;
+  }
+  struct iovec iov[2] = {{"vm", 2}, {"splice", 6}};
+  char buf[16] = {0};
+  if (vmsplice(fds[1], iov, 2, 0) == 8 && read(fds[0], buf, 8) == 8 &&
+      memcmp(buf, "vmsplice", 8) == 0) {
+    puts("test_vmsplice ok");
+  }
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_copy_file_range() {
+  int src = create_file(SRC_PATH, "0123456789");
+  int dst = create_file(DST_PATH, "");
+  if (src < 0 || dst < 0) {
+    perror("test_copy_file_range");
+    return;
+  }
+  off_t off_in = 4;
+  char buf[16] = {0};
+  if (copy_file_range(src, &off_in, dst, NULL, 3, 0) == 3 && off_in == 7 &&
+      lseek(dst, 0, SEEK_SET) == 0 && read(dst, buf, sizeof(buf)) == 3 &&
+      memcmp(buf, "456", 3) == 0) {
+    puts("test_copy_file_range ok1");
+  }
+  // Overlapping ranges within a file are refused.
+  off_t off_out = 2;
+  off_in = 0;
+  if (copy_file_range(src, &off_in, src, &off_out, 4, 0) < 0) {
+    puts("test_copy_file_range ok2");
+  }
+  close(src);
+  close(dst);
+  unlink(SRC_PATH);
+  unlink(DST_PATH);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_sendfile();
+  test_splice_tee();
+  test_vmsplice();
+  test_copy_file_range();
+  return 0;
+}
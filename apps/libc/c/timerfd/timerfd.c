@@ -0,0 +1,86 @@
+#include <errno.h>
+#include <poll.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/timerfd.h>
+#include <time.h>
+#include <unistd.h>
+
+void test_timerfd_oneshot() {
+  int fd = timerfd_create(CLOCK_MONOTONIC, TFD_NONBLOCK);
+  uint64_t count;
+  if (read(fd, &count, sizeof(count)) < 0 && errno == EAGAIN) {
+    puts("test_timerfd_oneshot ok1");
+  }
+  struct itimerspec spec = {{0, 0}, {0, 50000000}};
+  timerfd_settime(fd, 0, &spec, NULL);
+  struct itimerspec curr;
+  if (timerfd_gettime(fd, &curr) == 0 && curr.it_value.tv_sec == 0 &&
+      curr.it_value.tv_nsec > 0 && curr.it_value.tv_nsec <= 50000000) {
+    puts("test_timerfd_oneshot ok2");
+  }
+  struct pollfd pfd = {fd, POLLIN, 0};
+  if (poll(&pfd, 1, 1000) == 1 && read(fd, &count, sizeof(count)) == 8 &&
+      count == 1) {
+    puts("test_timerfd_oneshot ok3");
+  }
+  // Expired one-shot timers are disarmed.
+  if (timerfd_gettime(fd, &curr) == 0 && curr.it_value.tv_sec == 0 &&
+      curr.it_value.tv_nsec == 0) {
+    puts("test_timerfd_oneshot ok4");
+  }
+  close(fd);
+}
+
+void test_timerfd_interval() {
+  int fd = timerfd_create(CLOCK_MONOTONIC, 0);
+  struct itimerspec spec = {{0, 20000000}, {0, 20000000}};
+  timerfd_settime(fd, 0, &spec, NULL);
+  usleep(110000);
+  // Expirations accumulate until the timer is read.
+  uint64_t count;
+  if (read(fd, &count, sizeof(count)) == 8 && count >= 4) {
+    puts("test_timerfd_interval ok1");
+  }
+  // Blocking reads wait for the next expiration.
+  if (read(fd, &count, sizeof(count)) == 8 && count >= 1) {
+    puts("test_timerfd_interval ok2");
+  }
+  struct itimerspec old;
+  spec.it_value.tv_nsec = 0;
+  if (timerfd_settime(fd, 0, &spec, &old) == 0 &&
+      old.it_interval.tv_nsec == 20000000) {
+    puts("test_timerfd_interval ok3");
+  }
+  close(fd);
+}
+
+void test_timerfd_abstime() {
+  int fd = timerfd_create(CLOCK_REALTIME, TFD_NONBLOCK);
+  struct itimerspec spec = {{0, 0}, {0, 0}};
+  clock_gettime(CLOCK_REALTIME, &spec.it_value);
+  spec.it_value.tv_nsec += 50000000;
+  if (spec.it_value.tv_nsec >= 1000000000) {
+    spec.it_value.tv_sec++;
+    spec.it_value.tv_nsec -= 1000000000;
+  }
+  timerfd_settime(fd, TFD_TIMER_ABSTIME, &spec, NULL);
+  uint64_t count;
+  struct pollfd pfd = {fd, POLLIN, 0};
+  if (poll(&pfd, 1, 1000) == 1 && read(fd, &count, sizeof(count)) == 8 &&
+      count == 1) {
+    puts("test_timerfd_abstime ok1");
+  }
+  if (timerfd_create(CLOCK_PROCESS_CPUTIME_ID, 0) < 0 && errno == EINVAL) {
+    puts("test_timerfd_abstime ok2");
+  }
+  close(fd);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_timerfd_oneshot();
+  test_timerfd_interval();
+  test_timerfd_abstime();
+  return 0;
+}
@@ -0,0 +1,148 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <unistd.h>
+
+static struct sockaddr_in inet_addr_port(in_addr_t ip, int port) {
+  struct sockaddr_in addr = {0};
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(port);
+  addr.sin_addr.s_addr = ip;
+  return addr;
+}
+
+static int bound_socket(int port) {
+  struct sockaddr_in addr = inet_addr_port(htonl(INADDR_ANY), port);
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  int one = 1;
+  setsockopt(fd, SOL_SOCKET, SO_REUSEADDR, &one, sizeof(one));
+  if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    close(fd);
+    return -1;
+  }
+  return fd;
+}
+
+void test_udp_autobind() {
+  int server = bound_socket(5051);
+  int client = socket(AF_INET, SOCK_DGRAM, 0);
+  struct sockaddr_in dst = inet_addr_port(htonl(INADDR_LOOPBACK), 5051);
+  if (server < 0 ||
+      sendto(client, "a", 1, 0, (struct sockaddr *)&dst, sizeof(dst)) != 1) {
+    perror("test_udp_autobind");
+    return;
+  }
+  struct sockaddr_in local, from;
+  socklen_t len = sizeof(local);
+  getsockname(client, (struct sockaddr *)&local, &len);
+  char buf[4];
+  len = sizeof(from);
+  if (recvfrom(server, buf, sizeof(buf), 0, (struct sockaddr *)&from, &len) ==
+          1 &&
+      local.sin_port != 0 && from.sin_port == local.sin_port) {
+    puts("test_udp_autobind ok");
+  }
+  close(client);
+  close(server);
+}
+
+void test_udp_connected() {
+  int server = bound_socket(5052);
+  int peer = bound_socket(5053);
+  int other = socket(AF_INET, SOCK_DGRAM, 0);
+  struct sockaddr_in server_addr =
+      inet_addr_port(htonl(INADDR_LOOPBACK), 5052);
+  struct sockaddr_in peer_addr = inet_addr_port(htonl(INADDR_LOOPBACK), 5053);
+  if (server < 0 || peer < 0 ||
+      connect(server, (struct sockaddr *)&peer_addr, sizeof(peer_addr)) < 0) {
+    perror("test_udp_connected");
+    return;
+  }
+
+  // Only datagrams from the connected peer are received.
+  sendto(other, "x", 1, 0, (struct sockaddr *)&server_addr,
+         sizeof(server_addr));
+  sendto(peer, "p", 1, 0, (struct sockaddr *)&server_addr,
+         sizeof(server_addr));
+  char buf[4];
+  if (recv(server, buf, sizeof(buf), 0) == 1 && buf[0] == 'p') {
+    puts("test_udp_connected ok1");
+  }
+  if (send(server, "r", 1, 0) == 1 && recv(peer, buf, sizeof(buf), 0) == 1 &&
+      buf[0] == 'r') {
+    puts("test_udp_connected ok2");
+  }
+
+  // Connecting to `AF_UNSPEC` dissolves the association.
+  struct sockaddr unspec = {0};
+  unspec.sa_family = AF_UNSPEC;
+  struct sockaddr_in name;
+  socklen_t len = sizeof(name);
+  if (connect(server, &unspec, sizeof(unspec)) == 0 &&
+      getpeername(server, (struct sockaddr *)&name, &len) < 0 &&
+      errno == ENOTCONN && send(server, "r", 1, 0) < 0 &&
+      errno == EDESTADDRREQ) {
+    puts("test_udp_connected ok3");
+  }
+  close(other);
+  close(peer);
+  close(server);
+}
+
+void test_udp_broadcast() {
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  int value = 1;
+  socklen_t len = sizeof(value);
+  struct sockaddr_in dst = inet_addr_port(htonl(INADDR_BROADCAST), 5054);
+  if (sendto(fd, "b", 1, 0, (struct sockaddr *)&dst, sizeof(dst)) < 0 &&
+      errno == EACCES) {
+    puts("test_udp_broadcast ok1");
+  }
+  setsockopt(fd, SOL_SOCKET, SO_BROADCAST, &value, sizeof(value));
+  value = 0;
+  if (getsockopt(fd, SOL_SOCKET, SO_BROADCAST, &value, &len) == 0 &&
+      value == 1) {
+    puts("test_udp_broadcast ok2");
+  }
+  close(fd);
+}
+
+void test_udp_multicast() {
+  int fd = bound_socket(5055);
+  // Without an interface, the group is joined on loopback.
+  struct ip_mreq mreq;
+  mreq.imr_multiaddr.s_addr = inet_addr("239.1.2.3");
+  mreq.imr_interface.s_addr = htonl(INADDR_ANY);
+  if (fd < 0 || setsockopt(fd, IPPROTO_IP, IP_ADD_MEMBERSHIP, &mreq,
+                           sizeof(mreq)) < 0) {
+    perror("test_udp_multicast");
+    return;
+  }
+  puts("test_udp_multicast ok1");
+
+  int sender = socket(AF_INET, SOCK_DGRAM, 0);
+  struct sockaddr_in dst = inet_addr_port(mreq.imr_multiaddr.s_addr, 5055);
+  char buf[4];
+  if (sendto(sender, "m", 1, 0, (struct sockaddr *)&dst, sizeof(dst)) == 1 &&
+      recv(fd, buf, sizeof(buf), 0) == 1 && buf[0] == 'm') {
+    puts("test_udp_multicast ok2");
+  }
+  if (setsockopt(fd, IPPROTO_IP, IP_DROP_MEMBERSHIP, &mreq, sizeof(mreq)) ==
+      0) {
+    puts("test_udp_multicast ok3");
+  }
+  close(sender);
+  close(fd);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_udp_autobind();
+  test_udp_connected();
+  test_udp_broadcast();
+  test_udp_multicast();
+  return 0;
+}
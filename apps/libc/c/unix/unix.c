@@ -0,0 +1,96 @@
+#include <errno.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/un.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static socklen_t abstract_addr(struct sockaddr_un *addr, const char *name) {
+  memset(addr, 0, sizeof(*addr));
+  addr->sun_family = AF_UNIX;
+  strcpy(addr->sun_path + 1, name);
+  return offsetof(struct sockaddr_un, sun_path) + 1 + strlen(name);
+}
+
+static socklen_t path_addr(struct sockaddr_un *addr, const char *path) {
+  memset(addr, 0, sizeof(*addr));
+  addr->sun_family = AF_UNIX;
+  strcpy(addr->sun_path, path);
+  return sizeof(*addr);
+}
+
+void test_unix_stream() {
+  struct sockaddr_un addr;
+  socklen_t len = abstract_addr(&addr, "starry-unix-stream");
+  int server = socket(AF_UNIX, SOCK_STREAM, 0);
+  if (bind(server, (struct sockaddr *)&addr, len) < 0 ||
+      listen(server, 1) < 0) {
+    perror("test_unix_stream");
+    return;
+  }
+  if (fork() == 0) {
+    int client = socket(AF_UNIX, SOCK_STREAM, 0);
+    char buf[5];
+    if (connect(client, (struct sockaddr *)&addr, len) == 0 &&
+        write(client, "hello", 5) == 5 && read(client, buf, 5) == 5 &&
+        memcmp(buf, "world", 5) == 0) {
+      puts("test_unix_stream ok1");
+    }
+    _exit(0);
+  }
+  int conn = accept(server, NULL, NULL);
+  char buf[5];
+  if (read(conn, buf, 5) == 5 && memcmp(buf, "hello", 5) == 0) {
+    write(conn, "world", 5);
+  }
+  wait(NULL);
+  if (read(conn, buf, 5) == 0) {
+    puts("test_unix_stream ok2");
+  }
+  close(conn);
+  close(server);
+}
+
+void test_unix_dgram() {
+  const char *path = "/starry_unix_dgram.sock";
+  unlink(path);
+  struct sockaddr_un addr;
+  socklen_t len = path_addr(&addr, path);
+  int receiver = socket(AF_UNIX, SOCK_DGRAM, 0);
+  if (bind(receiver, (struct sockaddr *)&addr, len) < 0) {
+    perror("test_unix_dgram");
+    return;
+  }
+  struct stat st;
+  if (stat(path, &st) == 0 && S_ISSOCK(st.st_mode)) {
+    puts("test_unix_dgram ok1");
+  }
+
+  int sender = socket(AF_UNIX, SOCK_DGRAM, 0);
+  sendto(sender, "abc", 3, 0, (struct sockaddr *)&addr, len);
+  sendto(sender, "de", 2, 0, (struct sockaddr *)&addr, len);
+  char buf[8];
+  if (recv(receiver, buf, sizeof(buf), 0) == 3 &&
+      recv(receiver, buf, sizeof(buf), 0) == 2) {
+    puts("test_unix_dgram ok2");
+  }
+  close(sender);
+  close(receiver);
+  unlink(path);
+
+  int client = socket(AF_UNIX, SOCK_STREAM, 0);
+  if (connect(client, (struct sockaddr *)&addr, len) < 0 && errno == ENOENT) {
+    puts("test_unix_dgram ok3");
+  }
+  close(client);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_unix_stream();
+  test_unix_dgram();
+  return 0;
+}
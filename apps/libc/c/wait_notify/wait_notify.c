@@ -0,0 +1,94 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/socket.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static void empty_handler(int signum) {}
+
+static long now_ms() {
+  struct timespec ts;
+  clock_gettime(CLOCK_MONOTONIC, &ts);
+  return ts.tv_sec * 1000 + ts.tv_nsec / 1000000;
+}
+
+void test_wake_pipe() {
+  int fds[2];
+  pipe(fds);
+  int pid = fork();
+  if (pid == 0) {
+    usleep(100000);
+    write(fds[1], "w", 1);
+    _exit(0);
+  }
+  char c;
+  long start = now_ms();
+  if (read(fds[0], &c, 1) == 1 && c == 'w' && now_ms() - start >= 90) {
+    puts("test_wake_pipe ok");
+  }
+  waitpid(pid, NULL, 0);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+void test_wake_socket() {
+  struct sockaddr_in addr = {0};
+  addr.sin_family = AF_INET;
+  addr.sin_port = htons(5071);
+  addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+  int fd = socket(AF_INET, SOCK_DGRAM, 0);
+  if (bind(fd, (struct sockaddr *)&addr, sizeof(addr)) < 0) {
+    perror("test_wake_socket");
+    return;
+  }
+  int pid = fork();
+  if (pid == 0) {
+    int sender = socket(AF_INET, SOCK_DGRAM, 0);
+    usleep(100000);
+    sendto(sender, "s", 1, 0, (struct sockaddr *)&addr, sizeof(addr));
+    _exit(0);
+  }
+  char c;
+  if (recv(fd, &c, 1, 0) == 1 && c == 's') {
+    puts("test_wake_socket ok");
+  }
+  waitpid(pid, NULL, 0);
+  close(fd);
+}
+
+void test_wake_signal() {
+  // Without `SA_RESTART`, a signal interrupts the blocking read.
+  struct sigaction sa = {0};
+  sa.sa_handler = empty_handler;
+  sigaction(SIGUSR1, &sa, NULL);
+  int fds[2];
+  pipe(fds);
+  int parent = getpid();
+  int pid = fork();
+  if (pid == 0) {
+    usleep(100000);
+    kill(parent, SIGUSR1);
+    _exit(0);
+  }
+  char c;
+  if (read(fds[0], &c, 1) < 0 && errno == EINTR) {
+    puts("test_wake_signal ok");
+  }
+  waitpid(pid, NULL, 0);
+  sa.sa_handler = SIG_DFL;
+  sigaction(SIGUSR1, &sa, NULL);
+  close(fds[0]);
+  close(fds[1]);
+}
+
+int main() {
+  setvbuf(stdout, NULL, _IONBF, 0);
+  test_wake_pipe();
+  test_wake_socket();
+  test_wake_signal();
+  return 0;
+}
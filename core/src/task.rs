@@ -21,7 +21,7 @@ use axmm::{AddrSpace, kernel_aspace};
 use axns::{AxNamespace, AxNamespaceIf};
 use axprocess::{Pid, Process, ProcessGroup, Session, Thread};
 use axsignal::{
-    Signo,
+    SignalSet, Signo,
     api::{ProcessSignalManager, SignalActions, ThreadSignalManager},
 };
 use axsync::{Mutex, RawMutex};
@@ -162,6 +162,10 @@ pub struct ThreadData {
 
     /// The thread-level signal manager
     pub signal: ThreadSignalManager<RawMutex, WaitQueueWrapper>,
+
+    /// The signal mask to restore once the signal that interrupted a system
+    /// call with a temporary signal mask (like `ppoll`) has been handled.
+    pub saved_sigmask: Mutex<Option<SignalSet>>,
 }
 
 impl ThreadData {
@@ -172,6 +176,8 @@ impl ThreadData {
             clear_child_tid: AtomicUsize::new(0),
 
             signal: ThreadSignalManager::new(proc.signal.clone()),
+
+            saved_sigmask: Mutex::new(None),
         }
     }
 